@@ -28,6 +28,11 @@ impl Expr {
     pub fn return_type(&self) -> Type {
         self.return_type
     }
+    /// Returns the arguments (other expressions) passed to this expression's
+    /// function.
+    pub fn args(&self) -> &Args {
+        &self.args
+    }
     /// Constructs a new expression by applying the given Args to the given
     /// Function.
     pub fn try_new(
@@ -40,14 +45,15 @@ impl Expr {
         // Figure out the function signature, which will tells us the return
         // type. If there is no matching function signature, then return an
         // Err(InvalidArguments).
-        let signature = func
-            .get_signature(&arg_types)
-            .ok_or_else(|| InvalidArguments {
+        let signature = func.get_signature(&arg_types).ok_or_else(|| {
+            InvalidArguments {
                 name: func.name(),
                 omit_first: matches!(func.kind(), FunctionKind::Method | FunctionKind::Property),
                 expected: func.signatures().iter().map(|s| s.args.clone()).collect(),
                 got: arg_types,
-            })?;
+            }
+            .with_span(span)
+        })?;
         Ok(Self {
             span,
             func,
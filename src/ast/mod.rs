@@ -32,3 +32,15 @@ pub fn make_rule(source_code: Rc<String>) -> LangResult<Rule> {
     let parse_tree = super::parser::parse(source_code.clone(), &tokens)?;
     Rule::try_from(parse_tree)
 }
+
+/// Constructs a rule from source code, returning every error encountered
+/// instead of only the first one.
+///
+/// Tokenization stops at the first error. If there are any syntax errors, the
+/// AST is not built, so that type errors caused by malformed code are not
+/// reported alongside them.
+pub fn make_rule_collecting_errors(source_code: Rc<String>) -> MultiLangResult<Rule> {
+    let tokens = super::lexer::tokenize(&source_code).map_err(|e| vec![e])?;
+    let parse_tree = super::parser::parse_collecting_errors(source_code.clone(), &tokens)?;
+    Rule::try_from_collecting_errors(parse_tree)
+}
@@ -4,13 +4,22 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::rc::Rc;
 
+use super::super::compiler::{CompiledFunction, Compiler};
 use super::super::errors::*;
-use super::super::parser::{Directive, DirectiveContents, HelperFunc, ParseTree};
-use super::super::{ConstValue, Type, MAX_NDIM, MAX_STATES};
+use super::super::parser::{Directive, DirectiveContents, Expr, HelperFunc, ParseTree};
+use super::super::types::{LangCellState, LangInt};
+use super::super::{
+    ConstValue, LineIndex, Span, Spanned, Type, MAX_LAYERS, MAX_NDIM, MAX_STATES, MAX_TABLE_SIZE,
+    NDCA_VERSION, NEIGHBORHOOD_RADIUS,
+};
+use super::userfunc::block_always_diverges;
 use super::{FnSignature, UserFunction};
 use LangErrorMsg::{
-    Expected, FunctionNameConflict, InternalError, InvalidDimensionCount, InvalidStateCount,
-    TypeError,
+    CellStateOutOfRange, ConstantUsedBeforeDefinition, DuplicateConstant, DuplicateStateName,
+    Expected, FunctionNameConflict, InternalError, InvalidDimensionCount, InvalidLayerCount,
+    InvalidNeighborhoodOffset, InvalidStateCount, InvalidStepLimit, MissingReturn,
+    NeighborOutOfRange, TableTooLarge, TypeError, UnsupportedSymmetry, UnsupportedTableExport,
+    UnsupportedVersion, UseOfUninitializedVariable,
 };
 
 /// Number of dimensions to use when the user doesn't specify.
@@ -24,6 +33,97 @@ fn make_default_states(count: Option<usize>) -> Vec<CellState> {
     return vec![CellState::default(); count.unwrap_or(DEFAULT_STATE_COUNT)];
 }
 
+/// Returns every neighbor offset within NEIGHBORHOOD_RADIUS of the center
+/// cell (excluding the center cell itself), in the same order as the flat
+/// neighborhood array.
+fn make_moore_neighborhood(ndim: u8) -> Vec<Vec<LangInt>> {
+    let mut offsets: Vec<Vec<LangInt>> = vec![vec![]];
+    for _ in 0..ndim {
+        // The X axis varies fastest, so add each new axis at the end.
+        let mut next_offsets = vec![];
+        for x in -NEIGHBORHOOD_RADIUS..=NEIGHBORHOOD_RADIUS {
+            for offset in &offsets {
+                let mut offset = offset.clone();
+                offset.push(x);
+                next_offsets.push(offset);
+            }
+        }
+        offsets = next_offsets;
+    }
+    offsets.retain(|offset| offset.iter().any(|&x| x != 0));
+    offsets
+}
+
+/// Returns every neighbor offset within NEIGHBORHOOD_RADIUS of the center
+/// cell by Manhattan distance (excluding the center cell itself), in the same
+/// order as the flat neighborhood array.
+fn make_von_neumann_neighborhood(ndim: u8) -> Vec<Vec<LangInt>> {
+    let mut offsets = make_moore_neighborhood(ndim);
+    offsets.retain(|offset| {
+        let distance: LangInt = offset.iter().map(|x| x.abs()).sum();
+        distance <= NEIGHBORHOOD_RADIUS
+    });
+    offsets
+}
+
+/// Returns the name of the Golly neighborhood containing the same cells as the
+/// given one, along with its offsets in the order that Golly expects them, or
+/// None if Golly has no such neighborhood.
+fn golly_neighborhood(
+    ndim: u8,
+    neighborhood: &[Vec<LangInt>],
+) -> Option<(&'static str, Vec<Vec<LangInt>>)> {
+    let candidates = match ndim {
+        1 => vec![("oneDimensional", vec![vec![-1], vec![1]])],
+        2 => vec![
+            (
+                "Moore",
+                vec![
+                    vec![0, -1],
+                    vec![1, -1],
+                    vec![1, 0],
+                    vec![1, 1],
+                    vec![0, 1],
+                    vec![-1, 1],
+                    vec![-1, 0],
+                    vec![-1, -1],
+                ],
+            ),
+            (
+                "vonNeumann",
+                vec![vec![0, -1], vec![1, 0], vec![0, 1], vec![-1, 0]],
+            ),
+        ],
+        _ => vec![],
+    };
+    let mut sorted_neighborhood = neighborhood.to_vec();
+    sorted_neighborhood.sort();
+    candidates.into_iter().find(|(_, offsets)| {
+        let mut sorted_offsets = offsets.clone();
+        sorted_offsets.sort();
+        sorted_offsets == sorted_neighborhood
+    })
+}
+
+/// Parses a version string of the form `MAJOR.MINOR` (e.g. `1.0`), returning
+/// None if it is malformed.
+fn parse_version(s: &str) -> Option<(u32, u32)> {
+    let parse_part = |part: &str| {
+        if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse().ok()
+        } else {
+            None
+        }
+    };
+    let mut parts = s.split('.');
+    let major = parse_part(parts.next()?)?;
+    let minor = parse_part(parts.next()?)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor))
+}
+
 /// Root node of an abstract syntax tree representing a Rule, along with any
 /// associated metadata (such as cell state information).
 #[derive(Debug)]
@@ -37,8 +137,196 @@ pub struct Rule {
 }
 impl TryFrom<ParseTree> for Rule {
     type Error = LangError;
-    fn try_from(mut parse_tree: ParseTree) -> LangResult<Self> {
-        let mut temp_func = UserFunction::default();
+    fn try_from(parse_tree: ParseTree) -> LangResult<Self> {
+        Self::try_from_collecting_errors(parse_tree).map_err(|mut errors| errors.remove(0))
+    }
+}
+impl Rule {
+    /// Constructs a rule from a parse tree, returning every error encountered
+    /// instead of only the first one.
+    ///
+    /// Errors in directives such as `@dimensions` and `@states` are still
+    /// fatal, since nothing else can be checked without them.
+    pub fn try_from_collecting_errors(mut parse_tree: ParseTree) -> MultiLangResult<Self> {
+        let (meta, helper_function_parse_trees) =
+            Self::build_meta(&mut parse_tree).map_err(|e| vec![e])?;
+        let mut errors = vec![];
+
+        // Build helper functions.
+        let mut helper_functions = HashMap::new();
+        for helper_func in helper_function_parse_trees {
+            let name = helper_func.name.inner.clone();
+            let userfunc = UserFunction::build_helper_function(&meta, helper_func);
+            errors.extend_from_slice(userfunc.errors());
+            helper_functions.insert(name, userfunc);
+        }
+
+        // Build transition function.
+        let mut transition_function = UserFunction::new_transition_function(meta.clone());
+        match parse_tree.take_single_directive(Directive::Transition) {
+            // The user gave a block of code.
+            Ok(Some((_span, DirectiveContents::Block(statements)))) => {
+                transition_function.build_top_level_statement_block_ast(&statements.inner);
+                // Every path through the transition function must end in
+                // `become` or `remain` unless there is a default state; point
+                // at the closing brace otherwise.
+                if transition_function.errors().is_empty()
+                    && meta.default_state.is_none()
+                    && !block_always_diverges(&statements.inner)
+                {
+                    let end = statements.span.end;
+                    let start = end.saturating_sub(1);
+                    errors.push(MissingReturn.with_span(Span { start, end }));
+                }
+            }
+            // The user gave something else instead of a code block.
+            Ok(Some((span, _contents))) => errors.push(Expected("code block").with_span(span)),
+            // The user did not provide a transition function.
+            Ok(None) => transition_function.build_top_level_statement_block_ast(&vec![]),
+            // The user gave more than one transition function.
+            Err(e) => errors.push(e),
+        }
+        errors.extend_from_slice(transition_function.errors());
+
+        // No directive left behind!
+        if let Some((dir, _contents)) = parse_tree
+            .directives
+            .drain()
+            .filter(|(_, v)| !v.is_empty())
+            .next()
+        {
+            errors.push(InternalError(format!("Unused directive {:?}", dir).into()).without_span());
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Construct the rule.
+        Ok(Rule {
+            meta,
+            helper_functions,
+            transition_function,
+        })
+    }
+    /// Builds the metadata for a rule from the directives in a parse tree,
+    /// returning it along with the parse trees of the rule's helper functions.
+    fn build_meta(parse_tree: &mut ParseTree) -> LangResult<(Rc<RuleMeta>, Vec<HelperFunc>)> {
+        // Get language version.
+        let version = match parse_tree.take_single_directive(Directive::NdcaVersion)? {
+            // There is no `@ndca_version` directive; assume the rule works
+            // with this version.
+            None => None,
+            // There is an `@ndca_version` directive with a string.
+            Some((span, DirectiveContents::String(s))) => match parse_version(&s.inner) {
+                // Newer minor versions are backwards-compatible, but newer
+                // major versions are not.
+                Some((major, minor)) if major > NDCA_VERSION.0 => {
+                    Err(UnsupportedVersion(major, minor).with_span(span))?
+                }
+                Some(version) => Some(version),
+                None => Err(Expected("version string, e.g. \"1.0\"").with_span(span))?,
+            },
+            // The user gave something else instead of a string.
+            Some((span, _contents)) => {
+                Err(Expected("version string, e.g. \"1.0\"").with_span(span))?
+            }
+        };
+
+        // Get name.
+        let name = match parse_tree.take_single_directive(Directive::Name)? {
+            // There is no `@name` directive; the rule has no name.
+            None => None,
+            // There is a `@name` directive with a string.
+            Some((_span, DirectiveContents::String(s))) => Some(s.inner),
+            // The user gave something else instead of a string.
+            Some((span, _contents)) => Err(Expected("string").with_span(span))?,
+        };
+
+        // Get description.
+        let description = match parse_tree.take_single_directive(Directive::Description)? {
+            // There is no `@description` directive; the rule has no
+            // description.
+            None => None,
+            // There is a `@description` directive with a string.
+            Some((_span, DirectiveContents::String(s))) => Some(s.inner),
+            // The user gave something else instead of a string.
+            Some((span, _contents)) => Err(Expected("string").with_span(span))?,
+        };
+
+        // Get arithmetic mode.
+        let arithmetic = match parse_tree.take_single_directive(Directive::Arithmetic)? {
+            // There is no `@arithmetic` directive; use the default.
+            None => ArithmeticMode::default(),
+            // There is an `@arithmetic` directive.
+            Some((span, DirectiveContents::Expr(expr))) => match &expr.inner {
+                Expr::Ident(s) if s == "checked" => ArithmeticMode::Checked,
+                Expr::Ident(s) if s == "wrapping" => ArithmeticMode::Wrapping,
+                _ => Err(Expected("'checked' or 'wrapping'").with_span(span))?,
+            },
+            // The user gave something else instead of an expression.
+            Some((span, _contents)) => Err(Expected("'checked' or 'wrapping'").with_span(span))?,
+        };
+
+        // Constant expressions in directives use the same arithmetic mode as
+        // the rest of the rule.
+        let make_temp_func = |constants: &HashMap<String, LangInt>| {
+            UserFunction::new_helper_function(
+                Rc::new(RuleMeta {
+                    arithmetic,
+                    constants: constants.clone(),
+                    ..RuleMeta::default()
+                }),
+                String::new(),
+                vec![],
+                Type::Int,
+            )
+        };
+
+        // Get constants, in the order that they are defined.
+        let const_definitions = parse_tree
+            .directives
+            .remove(&Directive::Const)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|contents| match contents.inner {
+                DirectiveContents::Const { name, value } => Ok((name, value)),
+                _ => Err(InternalError("Invalid parse tree on constant".into()).without_span()),
+            })
+            .collect::<LangResult<Vec<_>>>()?;
+        let mut constants = HashMap::new();
+        for (name, value) in &const_definitions {
+            if constants.contains_key(&name.inner) {
+                Err(DuplicateConstant.with_span(name.span))?;
+            }
+            // Each constant can only use the ones defined before it.
+            let mut temp_func = make_temp_func(&constants);
+            let value_expr = temp_func
+                .build_expression_ast(value)
+                .map_err(|e| match e.span {
+                    // If the unknown variable is a constant defined later, say so.
+                    Some(span)
+                        if e.msg == UseOfUninitializedVariable
+                            && const_definitions.iter().any(|(name, _)| {
+                                parse_tree.source_code.get(span.start..span.end)
+                                    == Some(name.inner.as_str())
+                            }) =>
+                    {
+                        ConstantUsedBeforeDefinition.with_span(span)
+                    }
+                    _ => e,
+                })?;
+            let const_value = temp_func.const_eval_expr(value_expr)?;
+            match const_value {
+                ConstValue::Int(i) => constants.insert(name.inner.clone(), i),
+                _ => Err(TypeError {
+                    expected: Type::Int,
+                    got: const_value.ty(),
+                }
+                .with_span(value.span))?,
+            };
+        }
+        let mut temp_func = make_temp_func(&constants);
 
         // Get number of dimensions.
         let ndim = match parse_tree.take_single_directive(Directive::Dimensions)? {
@@ -66,6 +354,120 @@ impl TryFrom<ParseTree> for Rule {
             Some((span, _contents)) => Err(Expected("expression").with_span(span))?,
         };
 
+        // Get number of layers.
+        let layers = match parse_tree.take_single_directive(Directive::Layers)? {
+            // There is no `@layers` directive; there are only cell states.
+            None => 1,
+            // There is a `@layers` directive.
+            Some((_span, DirectiveContents::Expr(expr))) => {
+                let layers_expr = temp_func.build_expression_ast(&expr)?;
+                let layers_value = temp_func.const_eval_expr(layers_expr)?;
+                match layers_value {
+                    // The user specified a valid layer count.
+                    ConstValue::Int(i @ 1..=MAX_LAYERS) => i as usize,
+                    // The user specified a number, but it's not a valid layer
+                    // count.
+                    ConstValue::Int(_) => Err(InvalidLayerCount.with_span(expr))?,
+                    // The user specified some other value.
+                    _ => Err(TypeError {
+                        expected: Type::Int,
+                        got: layers_value.ty(),
+                    }
+                    .with_span(expr.span))?,
+                }
+            }
+            // The user gave something else instead of an expression.
+            Some((span, _contents)) => Err(Expected("expression").with_span(span))?,
+        };
+
+        // Get step limit.
+        let max_steps = match parse_tree.take_single_directive(Directive::MaxSteps)? {
+            // There is no `@max_steps` directive; use the compiler's limit.
+            None => None,
+            // There is a `@max_steps` directive.
+            Some((_span, DirectiveContents::Expr(expr))) => {
+                let max_steps_expr = temp_func.build_expression_ast(&expr)?;
+                let max_steps_value = temp_func.const_eval_expr(max_steps_expr)?;
+                match max_steps_value {
+                    // The user specified a valid step limit.
+                    ConstValue::Int(i) if i > 0 => Some(i as u64),
+                    // The user specified a number, but it's not a valid step
+                    // limit.
+                    ConstValue::Int(_) => Err(InvalidStepLimit.with_span(expr))?,
+                    // The user specified some other value.
+                    _ => Err(TypeError {
+                        expected: Type::Int,
+                        got: max_steps_value.ty(),
+                    }
+                    .with_span(expr.span))?,
+                }
+            }
+            // The user gave something else instead of an expression.
+            Some((span, _contents)) => Err(Expected("expression").with_span(span))?,
+        };
+
+        // Get neighborhood.
+        let expected_neighborhood =
+            "'moore', 'vonNeumann', or list of offsets, e.g. '[[-1, 0], [1, 0]]'";
+        let neighborhood = match parse_tree.take_single_directive(Directive::Neighborhood)? {
+            // There is no `@neighborhood` directive; use the Moore
+            // neighborhood.
+            None => make_moore_neighborhood(ndim),
+            // There is a `@neighborhood` directive.
+            Some((span, DirectiveContents::Expr(expr))) => match &expr.inner {
+                Expr::Ident(s) if s.eq_ignore_ascii_case("moore") => make_moore_neighborhood(ndim),
+                Expr::Ident(s) if s.eq_ignore_ascii_case("vonNeumann") => {
+                    make_von_neumann_neighborhood(ndim)
+                }
+                Expr::Vector(offsets) => {
+                    let mut neighborhood = vec![];
+                    for offset in offsets {
+                        let offset_expr = temp_func.build_expression_ast(offset)?;
+                        let offset_value = match temp_func.const_eval_expr(offset_expr)? {
+                            ConstValue::Int(i) => vec![i],
+                            ConstValue::Vector(v) => v,
+                            _ => Err(InvalidNeighborhoodOffset(ndim).with_span(offset))?,
+                        };
+                        if offset_value.len() != ndim as usize {
+                            Err(InvalidNeighborhoodOffset(ndim).with_span(offset))?;
+                        }
+                        let radius = -NEIGHBORHOOD_RADIUS..=NEIGHBORHOOD_RADIUS;
+                        if offset_value.iter().any(|x| !radius.contains(x)) {
+                            Err(NeighborOutOfRange.with_span(offset))?;
+                        }
+                        neighborhood.push(offset_value);
+                    }
+                    neighborhood
+                }
+                _ => Err(Expected(expected_neighborhood).with_span(span))?,
+            },
+            // The user gave something else instead of an expression.
+            Some((span, _contents)) => Err(Expected(expected_neighborhood).with_span(span))?,
+        };
+
+        // Get symmetry.
+        let expected_symmetry = "'none', 'rotate4', 'rotate8', or 'reflect'";
+        let symmetry = match parse_tree.take_single_directive(Directive::Symmetry)? {
+            // There is no `@symmetry` directive; the rule has no symmetry.
+            None => Symmetry::default(),
+            // There is a `@symmetry` directive.
+            Some((span, DirectiveContents::Expr(expr))) => {
+                let symmetry = match &expr.inner {
+                    Expr::Ident(s) if s == "none" => Symmetry::None,
+                    Expr::Ident(s) if s == "rotate4" => Symmetry::Rotate4,
+                    Expr::Ident(s) if s == "rotate8" => Symmetry::Rotate8,
+                    Expr::Ident(s) if s == "reflect" => Symmetry::Reflect,
+                    _ => Err(Expected(expected_symmetry).with_span(span))?,
+                };
+                if symmetry != Symmetry::None && ndim != 2 {
+                    Err(UnsupportedSymmetry.with_span(span))?;
+                }
+                symmetry
+            }
+            // The user gave something else instead of an expression.
+            Some((span, _contents)) => Err(Expected(expected_symmetry).with_span(span))?,
+        };
+
         // Get states.
         let states = match parse_tree.take_single_directive(Directive::States)? {
             // There is no `@states` directive; use the default states.
@@ -92,6 +494,85 @@ impl TryFrom<ParseTree> for Rule {
             Some((span, _contents)) => Err(Expected("expression").with_span(span))?,
         };
 
+        // Get cell state names.
+        let state_names = match parse_tree.take_single_directive(Directive::Names)? {
+            // There is no `@names` directive; cell states have no names.
+            None => HashMap::new(),
+            // There is a `@names` directive with a list of names.
+            Some((
+                _span,
+                DirectiveContents::Expr(Spanned {
+                    inner: Expr::Vector(names),
+                    ..
+                }),
+            )) => {
+                let mut state_names = HashMap::new();
+                for (id, name) in names.iter().enumerate() {
+                    let name_str = match &name.inner {
+                        Expr::Ident(s) => s,
+                        _ => Err(Expected("cell state name").with_span(name))?,
+                    };
+                    if id >= states.len() {
+                        Err(CellStateOutOfRange.with_span(name))?;
+                    }
+                    if state_names.contains_key(name_str) {
+                        Err(DuplicateStateName.with_span(name))?;
+                    }
+                    state_names.insert(name_str.clone(), id as LangCellState);
+                }
+                state_names
+            }
+            // The user gave something else instead of a list of names.
+            Some((span, _contents)) => {
+                Err(Expected("list of cell state names, e.g. '[dead, alive]'").with_span(span))?
+            }
+        };
+
+        // Get default state. This can refer to cell states by name, so it must
+        // come after the cell state names.
+        let default_state = match parse_tree.take_single_directive(Directive::DefaultState)? {
+            // There is no `@default_state` directive; falling off the end of
+            // the transition function keeps the center cell unchanged.
+            None => None,
+            // There is a `@default_state` directive.
+            Some((_span, DirectiveContents::Expr(expr))) => {
+                let mut temp_func = UserFunction::new_helper_function(
+                    Rc::new(RuleMeta {
+                        arithmetic,
+                        constants: constants.clone(),
+                        states: states.clone(),
+                        state_names: state_names.clone(),
+                        ..RuleMeta::default()
+                    }),
+                    String::new(),
+                    vec![],
+                    Type::CellState,
+                );
+                let default_state_expr = temp_func.build_expression_ast(&expr)?;
+                let default_state_value = temp_func.const_eval_expr(default_state_expr)?;
+                match default_state_value {
+                    // The user specified a cell state, which has already been
+                    // checked.
+                    ConstValue::CellState(i) => Some(i),
+                    // The user specified a valid cell state ID.
+                    ConstValue::Int(i) if 0 <= i && i < states.len() as LangInt => {
+                        Some(i as LangCellState)
+                    }
+                    // The user specified a number, but it's not a valid cell
+                    // state ID.
+                    ConstValue::Int(_) => Err(CellStateOutOfRange.with_span(expr))?,
+                    // The user specified some other value.
+                    _ => Err(TypeError {
+                        expected: Type::CellState,
+                        got: default_state_value.ty(),
+                    }
+                    .with_span(expr.span))?,
+                }
+            }
+            // The user gave something else instead of an expression.
+            Some((span, _contents)) => Err(Expected("cell state").with_span(span))?,
+        };
+
         // Gather a list of helper functions.
         let helper_function_parse_trees: Vec<HelperFunc> = parse_tree
             .directives
@@ -119,64 +600,188 @@ impl TryFrom<ParseTree> for Rule {
 
         let meta = Rc::new(RuleMeta {
             source_code: parse_tree.source_code.clone(),
+            version,
+            name,
+            description,
+            constants,
             ndim,
+            layers,
+            max_steps,
+            neighborhood,
+            symmetry,
             states,
+            default_state,
+            arithmetic,
+            state_names,
             helper_function_signatures,
         });
 
-        // Build helper functions.
-        let helper_functions = helper_function_parse_trees
-            .into_iter()
-            .map(|helper_func| {
-                Ok((
-                    helper_func.name.inner.clone(),
-                    UserFunction::build_helper_function(&meta, helper_func)?,
-                ))
-            })
-            .collect::<LangResult<HashMap<_, _>>>()?;
-
-        // Build transition function.
-        let mut transition_function = UserFunction::new_transition_function(meta.clone());
-        match parse_tree.take_single_directive(Directive::Transition)? {
-            // The user gave a block of code.
-            Some((_span, DirectiveContents::Block(statements))) => {
-                transition_function.build_top_level_statement_block_ast(&statements.inner)?;
-            }
-            // The user gave something else instead of a code block.
-            Some((span, _contents)) => {
-                Err(Expected("code block").with_span(span))?;
-            }
-            // The user did not provide a transition function.
-            None => transition_function.build_top_level_statement_block_ast(&vec![])?,
-        }
-
-        // No directive left behind!
-        if let Some((dir, _contents)) = parse_tree
-            .directives
-            .drain()
-            .filter(|(_, v)| !v.is_empty())
-            .next()
-        {
-            Err(InternalError(format!("Unused directive {:?}", dir).into()))?;
-        }
+        Ok((meta, helper_function_parse_trees))
+    }
 
-        // Construct the rule.
-        Ok(Rule {
-            meta,
-            helper_functions,
-            transition_function,
-        })
+    /// Returns the version of the language that this rule targets, as
+    /// `(major, minor)`, as declared by the `@ndca_version` directive.
+    pub fn version(&self) -> Option<(u32, u32)> {
+        self.meta.version
+    }
+    /// Returns the display name of this rule, as declared by the `@name`
+    /// directive.
+    pub fn name(&self) -> Option<&str> {
+        self.meta.name.as_deref()
+    }
+    /// Returns the description of this rule, as declared by the `@description`
+    /// directive.
+    pub fn description(&self) -> Option<&str> {
+        self.meta.description.as_deref()
+    }
+    /// Returns the maximum number of loop iterations per function call, as
+    /// declared by the `@max_steps` directive.
+    pub fn max_steps(&self) -> Option<u64> {
+        self.meta.max_steps
+    }
+    /// Returns the cell state used when the transition function ends without
+    /// `become` or `remain`, as declared by the `@default_state` directive.
+    pub fn default_state(&self) -> Option<LangCellState> {
+        self.meta.default_state
     }
-}
-impl Rule {
     /// Returns this rule's transition function.
     pub fn transition_function(&self) -> &UserFunction {
         &self.transition_function
     }
+    /// Returns the offsets of the cells that count as neighbors of the center
+    /// cell, as declared by the `@neighborhood` directive.
+    pub fn neighborhood(&self) -> &[Vec<LangInt>] {
+        &self.meta.neighborhood
+    }
     /// Returns this rule's helper functions.
     pub fn helper_functions(&self) -> &HashMap<String, UserFunction> {
         &self.helper_functions
     }
+    /// Returns every warning in this rule, sorted by function name (with the
+    /// transition function last) and then by order of appearance.
+    pub fn warnings(&self) -> Vec<LangError> {
+        let mut helper_functions: Vec<&UserFunction> = self.helper_functions.values().collect();
+        helper_functions.sort_by(|a, b| a.name().cmp(b.name()));
+        helper_functions
+            .into_iter()
+            .chain(std::iter::once(&self.transition_function))
+            .flat_map(|userfunc| userfunc.warnings().iter().cloned())
+            .collect()
+    }
+    /// Parses and checks a rule from source code without compiling or running
+    /// it, returning the first error if there is one or every warning
+    /// otherwise.
+    pub fn validate(source_code: Rc<String>) -> CompleteLangResult<Vec<LangErrorWithSource>> {
+        let line_index = LineIndex::new(&source_code);
+        let rule = super::make_rule(source_code.clone())
+            .map_err(|e| e.with_line_index(&source_code, &line_index))?;
+        Ok(rule
+            .warnings()
+            .into_iter()
+            .map(|w| w.with_line_index(&source_code, &line_index))
+            .collect())
+    }
+
+    /// JIT compiles this rule's transition function and returns an executable
+    /// function.
+    pub fn compile_transition_function(
+        &self,
+        compiler: &mut Compiler,
+    ) -> LangResult<CompiledFunction> {
+        self.compile_function(compiler, &self.transition_function)
+    }
+    /// Returns the symbol name of this rule's transition function in object
+    /// files emitted by Compiler::compile_to_object().
+    pub fn transition_function_symbol(&self) -> String {
+        UserFunction::extern_fn_name(self.transition_function.name())
+    }
+    /// JIT compiles the helper function with the given name and returns an
+    /// executable function.
+    pub fn compile_helper_function(
+        &self,
+        compiler: &mut Compiler,
+        name: &str,
+    ) -> LangResult<CompiledFunction> {
+        let userfunc = self.helper_functions.get(name).ok_or_else(|| {
+            InternalError(format!("No helper function named {:?}", name).into()).without_span()
+        })?;
+        self.compile_function(compiler, userfunc)
+    }
+    /// JIT compiles this rule's transition function and runs it on every
+    /// configuration of the neighborhood, returning the results as the
+    /// `@TABLE` section of a Golly rule file.
+    ///
+    /// Only rules with one layer and a Moore or von Neumann neighborhood in 1D
+    /// or 2D can be exported. North is -Y, as in Golly. Configurations in which
+    /// the center cell does not change are omitted, since Golly leaves those
+    /// cells unchanged anyway.
+    pub fn export_golly_table(&self, compiler: &mut Compiler) -> LangResult<String> {
+        let meta = &self.meta;
+        if meta.layers != 1 {
+            return Err(UnsupportedTableExport("a rule with more than one layer").without_span());
+        }
+        let (neighborhood_name, offsets) = golly_neighborhood(meta.ndim, &meta.neighborhood)
+            .ok_or_else(|| {
+                UnsupportedTableExport("this neighborhood; use 'moore' or 'vonNeumann'")
+                    .without_span()
+            })?;
+
+        // The center cell comes first, followed by its neighbors in Golly's
+        // order.
+        let cell_indices: Vec<usize> = std::iter::once(meta.center_cell_index())
+            .chain(offsets.iter().map(|offset| meta.neighbor_index(offset)))
+            .collect();
+        let state_count = meta.states.len();
+        let table_size = state_count
+            .checked_pow(cell_indices.len() as u32)
+            .filter(|&size| size <= MAX_TABLE_SIZE)
+            .ok_or_else(|| TableTooLarge.without_span())?;
+
+        let mut transition_function = self.compile_transition_function(compiler)?;
+        let mut table = format!(
+            "@TABLE\nn_states:{}\nneighborhood:{}\nsymmetries:none\n",
+            state_count, neighborhood_name,
+        );
+        let mut cells = vec![0; meta.neighborhood_len()];
+        let mut row = vec![0; cell_indices.len()];
+        for i in 0..table_size {
+            // The last neighbor varies fastest, so that rows are sorted.
+            let mut remaining = i;
+            for (j, &cell_index) in cell_indices.iter().enumerate().rev() {
+                row[j] = (remaining % state_count) as LangCellState;
+                cells[cell_index] = row[j];
+                remaining /= state_count;
+            }
+            transition_function.set_neighborhood(&cells);
+            let new_state = transition_function.call()?.as_cell_state()?;
+            if new_state != row[0] {
+                for state in &row {
+                    table.push_str(&format!("{},", state));
+                }
+                table.push_str(&format!("{}\n", new_state));
+            }
+        }
+        Ok(table)
+    }
+    /// JIT compiles the given user function, along with every helper function
+    /// so that they can be called from it, and returns an executable function.
+    fn compile_function(
+        &self,
+        compiler: &mut Compiler,
+        userfunc: &UserFunction,
+    ) -> LangResult<CompiledFunction> {
+        compiler.set_rule_step_limit(self.meta.max_steps);
+        // Sort the helper functions by name so that error indices are
+        // consistent.
+        let mut helper_functions: Vec<&UserFunction> = self.helper_functions.values().collect();
+        helper_functions.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut error_points = vec![];
+        for helper_function in helper_functions {
+            helper_function.compile_intern(compiler, error_points.len())?;
+            error_points.extend_from_slice(helper_function.error_points());
+        }
+        userfunc.compile(compiler, error_points)
+    }
 }
 
 /// Metadata about a rule, such as the number of dimensions and a list of
@@ -185,10 +790,36 @@ impl Rule {
 pub struct RuleMeta {
     /// Raw source code.
     pub source_code: Rc<String>,
+    /// Version of the language that the rule targets, as `(major, minor)`, if
+    /// the rule declares one using the `@ndca_version` directive.
+    pub version: Option<(u32, u32)>,
+    /// Display name of the rule.
+    pub name: Option<String>,
+    /// Longer description of the rule.
+    pub description: Option<String>,
+    /// Values of named constants declared using `const`.
+    pub constants: HashMap<String, LangInt>,
     /// Number of dimensions (from 1 to 6).
     pub ndim: u8,
+    /// Number of layers of cell data, including cell states (from 1 to 2).
+    pub layers: usize,
+    /// Maximum number of loop iterations per function call, if the rule
+    /// declares one using the `@max_steps` directive.
+    pub max_steps: Option<u64>,
+    /// Offsets of the cells that count as neighbors of the center cell.
+    pub neighborhood: Vec<Vec<LangInt>>,
+    /// Symmetry of the transition function.
+    pub symmetry: Symmetry,
     /// List of cell states.
     pub states: Vec<CellState>,
+    /// Cell state returned when the transition function ends without `become`
+    /// or `remain`, if the rule declares one using the `@default_state`
+    /// directive.
+    pub default_state: Option<LangCellState>,
+    /// Behavior of integer arithmetic on overflow.
+    pub arithmetic: ArithmeticMode,
+    /// Map of cell state names to cell state IDs.
+    pub state_names: HashMap<String, LangCellState>,
     /// Map of names and signatures of helper functions.
     pub helper_function_signatures: HashMap<String, FnSignature>,
     // /// Cell state tags.
@@ -198,8 +829,19 @@ impl Default for RuleMeta {
     fn default() -> Self {
         Self {
             source_code: Rc::new(String::new()),
+            version: None,
+            name: None,
+            description: None,
+            constants: HashMap::new(),
             ndim: DEFAULT_NDIM,
+            layers: 1,
+            max_steps: None,
+            neighborhood: make_moore_neighborhood(DEFAULT_NDIM),
+            symmetry: Symmetry::default(),
             states: make_default_states(None),
+            default_state: None,
+            arithmetic: ArithmeticMode::default(),
+            state_names: HashMap::new(),
             helper_function_signatures: HashMap::new(),
         }
     }
@@ -209,6 +851,114 @@ impl RuleMeta {
     pub fn new() -> Self {
         Self::default()
     }
+    /// Returns the number of cells in the flat neighborhood array, including
+    /// the center cell.
+    pub fn neighborhood_len(&self) -> usize {
+        let diameter = 2 * NEIGHBORHOOD_RADIUS as usize + 1;
+        diameter.pow(self.ndim as u32)
+    }
+    /// Returns the index of the center cell in the flat neighborhood array.
+    pub fn center_cell_index(&self) -> usize {
+        self.neighborhood_len() / 2
+    }
+    /// Returns a list of permutations of the flat neighborhood array, one for
+    /// each transformation of the neighborhood under the rule's symmetry,
+    /// starting with the identity. The cell at index `i` of a transformed
+    /// neighborhood is the cell at index `permutation[i]` of the original.
+    ///
+    /// Returns an empty list if the rule has no symmetry.
+    pub fn symmetry_permutations(&self) -> Vec<Vec<usize>> {
+        if self.symmetry == Symmetry::None {
+            return vec![];
+        }
+        let offsets = make_moore_neighborhood(self.ndim);
+        (0..self.symmetry.transform_count())
+            .map(|i| {
+                let mut permutation: Vec<usize> = (0..self.neighborhood_len()).collect();
+                for offset in &offsets {
+                    let (x, y) = self.symmetry.transform(i, (offset[0], offset[1]));
+                    permutation[self.neighbor_index(offset)] = self.neighbor_index(&[x, y]);
+                }
+                permutation
+            })
+            .collect()
+    }
+    /// Returns the index of the cell at the given offset from the center in
+    /// the flat neighborhood array.
+    pub fn neighbor_index(&self, offset: &[LangInt]) -> usize {
+        let diameter = 2 * NEIGHBORHOOD_RADIUS + 1;
+        // The X axis varies fastest, so iterate over the axes in reverse.
+        offset
+            .iter()
+            .rev()
+            .fold(0, |index, &x| index * diameter + x + NEIGHBORHOOD_RADIUS) as usize
+    }
+}
+
+/// Behavior of integer arithmetic on overflow.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArithmeticMode {
+    /// Overflow is an error.
+    Checked,
+    /// Overflow wraps around, as in two's complement.
+    Wrapping,
+}
+impl Default for ArithmeticMode {
+    fn default() -> Self {
+        Self::Checked
+    }
+}
+
+/// Symmetry of a rule's transition function.
+///
+/// A symmetric transition function is tried on each transformation of the
+/// neighborhood in turn, and the first result other than the current state of
+/// the center cell is used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Symmetry {
+    /// No symmetry.
+    None,
+    /// Rotations by multiples of 90 degrees.
+    Rotate4,
+    /// Rotations by multiples of 45 degrees, moving each neighbor around the
+    /// ring of cells surrounding the center cell.
+    Rotate8,
+    /// Reflection across the vertical axis.
+    Reflect,
+}
+impl Default for Symmetry {
+    fn default() -> Self {
+        Self::None
+    }
+}
+impl Symmetry {
+    /// Returns the number of transformations of the neighborhood under this
+    /// symmetry, including the identity.
+    pub fn transform_count(self) -> usize {
+        match self {
+            Self::None => 1,
+            Self::Rotate4 => 4,
+            Self::Rotate8 => 8,
+            Self::Reflect => 2,
+        }
+    }
+    /// Applies the `i`th transformation of the neighborhood under this
+    /// symmetry to a 2D offset. The 0th transformation is the identity.
+    pub fn transform(self, i: usize, (x, y): (LangInt, LangInt)) -> (LangInt, LangInt) {
+        match self {
+            Self::None => (x, y),
+            Self::Rotate4 => (0..i).fold((x, y), |(x, y), _| (-y, x)),
+            Self::Rotate8 => (0..i).fold((x, y), |(x, y), _| rotate_45(x, y)),
+            Self::Reflect if i % 2 == 1 => (-x, y),
+            Self::Reflect => (x, y),
+        }
+    }
+}
+
+/// Rotates an offset in a 3x3 neighborhood by 45 degrees, moving it one cell
+/// around the ring of cells surrounding the center cell.
+fn rotate_45(x: LangInt, y: LangInt) -> (LangInt, LangInt) {
+    ((x - y).max(-1).min(1), (x + y).max(-1).min(1))
 }
 
 /// A cell state.
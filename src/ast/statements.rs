@@ -1,8 +1,14 @@
+use inkwell::IntPredicate;
+
 use super::super::compiler::*;
 use super::super::errors::*;
+use super::super::types::LangCellState;
 use super::super::{Span, Type};
-use super::{ExprRef, StatementRef, UserFunction};
-use LangErrorMsg::{CannotAssignTypeToVariable, InternalError, TypeError};
+use super::{ErrorPointRef, ExprRef, StatementRef, UserFunction};
+use LangErrorMsg::{
+    AssertionFailed, CannotAssignTypeToVariable, DuplicateMatchArm, InternalError,
+    MatchWithoutElse, StepLimitExceeded, TypeError,
+};
 
 /// List of statements, executed one after another.
 pub type StatementBlock = Vec<StatementRef>;
@@ -46,7 +52,7 @@ impl SetVar {
         // Check that the type of the result of the expression matches the type
         // of the variable.
         let got = expr_type;
-        let expected = userfunc.get_or_create_var(&var_name, got);
+        let (var_name, expected) = userfunc.get_or_create_var(&var_name, got);
         if expected != got {
             Err(TypeError { expected, got }.with_span(value_expr_span))?;
         }
@@ -75,62 +81,726 @@ impl Statement for SetVar {
     }
 }
 
-/// A conditional statement, such as `if x == 3 { ... } else { ... }`.
+/// Parallel variable assignment statement, such as `set x, y = y, x`.
+#[derive(Debug)]
+pub struct SetVars {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Assignments to perform.
+    assignments: Vec<SetVar>,
+}
+impl SetVars {
+    /// Constructs a new parallel variable assignment statement that evaluates
+    /// the expressions of all the given assignments before storing any of
+    /// them.
+    pub fn new(span: Span, assignments: Vec<SetVar>) -> Self {
+        Self { span, assignments }
+    }
+}
+impl Statement for SetVars {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let mut values = vec![];
+        for assignment in &self.assignments {
+            values.push(
+                userfunc
+                    .compile_expr(compiler, assignment.value_expr)?
+                    .into_basic_value()?,
+            );
+        }
+        for (assignment, value) in self.assignments.iter().zip(values) {
+            let var_ptr = compiler
+                .vars()
+                .get(&assignment.var_name)
+                .ok_or_else(|| InternalError("Invalid variable index".into()))?
+                .ptr;
+            compiler.builder().build_store(var_ptr, value);
+        }
+        Ok(())
+    }
+}
+
+/// Chained variable assignment statement, such as `set a = b = 0`.
+#[derive(Debug)]
+pub struct SetVarChain {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Assignments to perform, which all have the same expression.
+    assignments: Vec<SetVar>,
+}
+impl SetVarChain {
+    /// Constructs a new chained variable assignment statement that evaluates
+    /// the expression of the given assignments once and stores the result in
+    /// every variable. All of the assignments must have the same expression.
+    pub fn new(span: Span, assignments: Vec<SetVar>) -> Self {
+        Self { span, assignments }
+    }
+}
+impl Statement for SetVarChain {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let value_expr = self
+            .assignments
+            .first()
+            .ok_or_else(|| InternalError("Empty chained assignment".into()))?
+            .value_expr;
+        let value = userfunc
+            .compile_expr(compiler, value_expr)?
+            .into_basic_value()?;
+        for assignment in &self.assignments {
+            let var_ptr = compiler
+                .vars()
+                .get(&assignment.var_name)
+                .ok_or_else(|| InternalError("Invalid variable index".into()))?
+                .ptr;
+            compiler.builder().build_store(var_ptr, value);
+        }
+        Ok(())
+    }
+}
+
+/// A conditional statement, such as `if x == 3 { ... } else if x == 4 { ...
+/// } else { ... }`.
 #[derive(Debug)]
 pub struct If {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Condition and block of statements for each arm.
+    arms: Vec<(ExprRef, StatementBlock)>,
+    /// Block of statements to evaluate if every condition is false.
+    else_block: StatementBlock,
+}
+impl If {
+    /// Constructs a new conditional statement that branches to the block of
+    /// the first arm whose condition is true, or to the `else` block if there
+    /// is none.
+    ///
+    /// This method checks the types of the condition expressions.
+    pub fn try_new(
+        span: Span,
+        userfunc: &mut UserFunction,
+        arms: Vec<(ExprRef, StatementBlock)>,
+        else_block: StatementBlock,
+    ) -> LangResult<Self> {
+        let expected = Type::Bool;
+        for &(cond_expr, _) in &arms {
+            let got = userfunc[cond_expr].return_type();
+            if expected != got {
+                let cond_expr_span = userfunc[cond_expr].span();
+                Err(TypeError { expected, got }.with_span(cond_expr_span))?;
+            }
+        }
+        Ok(Self {
+            span,
+            arms,
+            else_block,
+        })
+    }
+}
+impl Statement for If {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let end_bb = compiler.append_basic_block("endIf");
+
+        // Each condition is evaluated only if all the previous ones were
+        // false, so build the arms one after another rather than nesting them.
+        for (cond_expr, block) in &self.arms {
+            let condition_value = userfunc[*cond_expr]
+                .compile(compiler, userfunc)?
+                .as_bool()?;
+            let if_true_bb = compiler.append_basic_block("ifTrue");
+            let if_false_bb = compiler.append_basic_block("ifFalse");
+            // Build the switch instruction (because condition_value might not
+            // be 1-bit).
+            compiler.builder().build_switch(
+                condition_value,
+                if_true_bb,
+                &[(condition_value.get_type().const_zero(), if_false_bb)],
+            );
+
+            // Build the instructions to execute if true.
+            compiler.builder().position_at_end(if_true_bb);
+            userfunc.compile_statement_block(compiler, block)?;
+            if compiler.needs_terminator() {
+                compiler.builder().build_unconditional_branch(end_bb);
+            }
+
+            // Continue with the next condition if false.
+            compiler.builder().position_at_end(if_false_bb);
+        }
+
+        // Build the instructions to execute if every condition is false.
+        userfunc.compile_statement_block(compiler, &self.else_block)?;
+        if compiler.needs_terminator() {
+            compiler.builder().build_unconditional_branch(end_bb);
+        }
+
+        compiler.builder().position_at_end(end_bb);
+        Ok(())
+    }
+}
+
+/// A statement that branches to one of several blocks depending on the cell
+/// state that an expression evaluates to, such as `match neighbor(0, 1) { #0 =>
+/// { ... } else => { ... } }`.
+#[derive(Debug)]
+pub struct Match {
     /// Span of this statement in the original source code.
     span: Span,
     /// Expression to branch based on.
+    expr: ExprRef,
+    /// Cell state and block of statements for each arm.
+    arms: Vec<(LangCellState, StatementBlock)>,
+    /// Block of statements to evaluate if no arm matches.
+    else_arm: StatementBlock,
+}
+impl Match {
+    /// Constructs a new match statement that branches to the block of the arm
+    /// whose value equals the result of the given expression, or to the `else`
+    /// block if there is none.
+    ///
+    /// This method checks the types of the expression and the arm values,
+    /// evaluates the arm values as constants, and returns an error if any value
+    /// is repeated or if there is no `else` arm and the arms do not cover every
+    /// cell state.
+    pub fn try_new(
+        span: Span,
+        userfunc: &mut UserFunction,
+        expr: ExprRef,
+        arms: Vec<(ExprRef, StatementBlock)>,
+        else_arm: Option<StatementBlock>,
+    ) -> LangResult<Self> {
+        let expected = Type::CellState;
+        let got = userfunc[expr].return_type();
+        if expected != got {
+            Err(TypeError { expected, got }.with_span(userfunc[expr].span()))?;
+        }
+        let mut const_arms: Vec<(LangCellState, StatementBlock)> = vec![];
+        for (value_expr, block) in arms {
+            let value_span = userfunc[value_expr].span();
+            let got = userfunc[value_expr].return_type();
+            if expected != got {
+                Err(TypeError { expected, got }.with_span(value_span))?;
+            }
+            let value = userfunc.const_eval_expr(value_expr)?.as_cell_state()?;
+            if const_arms.iter().any(|(existing, _)| *existing == value) {
+                Err(DuplicateMatchArm.with_span(value_span))?;
+            }
+            const_arms.push((value, block));
+        }
+        let else_arm = match else_arm {
+            Some(block) => block,
+            None => {
+                // Without an `else` arm, every cell state must be matched.
+                let state_count = userfunc.rule_meta().states.len();
+                let missing: Vec<LangCellState> = (0..state_count)
+                    .map(|id| id as LangCellState)
+                    .filter(|id| const_arms.iter().all(|(value, _)| value != id))
+                    .collect();
+                if !missing.is_empty() {
+                    Err(MatchWithoutElse(missing).with_span(span))?;
+                }
+                // The `else` block is unreachable.
+                vec![]
+            }
+        };
+        Ok(Self {
+            span,
+            expr,
+            arms: const_arms,
+            else_arm,
+        })
+    }
+}
+impl Statement for Match {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let value = userfunc[self.expr]
+            .compile(compiler, userfunc)?
+            .as_cell_state()?;
+
+        // Build the destination blocks.
+        let arm_bbs: Vec<_> = self
+            .arms
+            .iter()
+            .map(|_| compiler.append_basic_block("matchArm"))
+            .collect();
+        let else_bb = compiler.append_basic_block("matchElse");
+        let end_bb = compiler.append_basic_block("endMatch");
+
+        // Build the switch instruction, which LLVM may turn into a jump table.
+        let cases: Vec<_> = self
+            .arms
+            .iter()
+            .zip(&arm_bbs)
+            .map(|((cell_state, _), &bb)| {
+                (value.get_type().const_int(*cell_state as u64, false), bb)
+            })
+            .collect();
+        compiler.builder().build_switch(value, else_bb, &cases);
+
+        // Build each arm, then jump to the end.
+        let mut arm_blocks: Vec<_> = arm_bbs
+            .into_iter()
+            .zip(self.arms.iter().map(|(_, block)| block))
+            .collect();
+        arm_blocks.push((else_bb, &self.else_arm));
+        for (bb, block) in arm_blocks {
+            compiler.builder().position_at_end(bb);
+            userfunc.compile_statement_block(compiler, block)?;
+            if compiler.needs_terminator() {
+                compiler.builder().build_unconditional_branch(end_bb);
+            }
+        }
+
+        compiler.builder().position_at_end(end_bb);
+        Ok(())
+    }
+}
+
+/// A loop that executes a block of statements once for each integer in a
+/// half-open range, such as `for i in 0..10 { ... }`.
+#[derive(Debug)]
+pub struct For {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Name of the loop variable.
+    var_name: String,
+    /// Expression for the start of the range (inclusive).
+    start_expr: ExprRef,
+    /// Expression for the end of the range (exclusive).
+    end_expr: ExprRef,
+    /// Block of statements to evaluate each iteration.
+    body: StatementBlock,
+    /// Error returned if the step limit is exceeded.
+    step_limit_error: ErrorPointRef,
+}
+impl For {
+    /// Constructs a new loop that evaluates the given block once for each
+    /// integer from the result of `start_expr` (inclusive) to the result of
+    /// `end_expr` (exclusive), assigning each one to the given variable. The
+    /// variable must already exist.
+    ///
+    /// This method checks the types of the range expressions.
+    pub fn try_new(
+        span: Span,
+        userfunc: &mut UserFunction,
+        var_name: String,
+        start_expr: ExprRef,
+        end_expr: ExprRef,
+        body: StatementBlock,
+    ) -> LangResult<Self> {
+        for &expr in &[start_expr, end_expr] {
+            let expected = Type::Int;
+            let got = userfunc[expr].return_type();
+            if expected != got {
+                Err(TypeError { expected, got }.with_span(userfunc[expr].span()))?;
+            }
+        }
+        let range_span = Span::merge(userfunc[start_expr].span(), userfunc[end_expr].span());
+        let step_limit_error = userfunc.add_error_point(StepLimitExceeded.with_span(range_span));
+        Ok(Self {
+            span,
+            var_name,
+            start_expr,
+            end_expr,
+            body,
+            step_limit_error,
+        })
+    }
+}
+impl Statement for For {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let var_ptr = compiler
+            .vars()
+            .get(&self.var_name)
+            .ok_or_else(|| InternalError("Invalid variable index".into()))?
+            .ptr;
+
+        // Evaluate the range bounds once, before the loop.
+        let start_value = userfunc.compile_expr(compiler, self.start_expr)?.as_int()?;
+        let end_value = userfunc.compile_expr(compiler, self.end_expr)?.as_int()?;
+        let entry_bb = compiler.builder().get_insert_block().unwrap();
+
+        // Build the destination blocks.
+        let cond_bb = compiler.append_basic_block("forCond");
+        let body_bb = compiler.append_basic_block("forBody");
+        let step_bb = compiler.append_basic_block("forStep");
+        let end_bb = compiler.append_basic_block("endFor");
+
+        // Keep track of the iteration using a counter that is separate from
+        // the loop variable, so that assigning to the loop variable inside the
+        // body does not affect the number of iterations.
+        compiler.builder().build_unconditional_branch(cond_bb);
+        compiler.builder().position_at_end(cond_bb);
+        let int_type = compiler.int_type();
+        let counter = compiler.builder().build_phi(int_type, "forCounter");
+        let counter_value = counter.as_basic_value().into_int_value();
+        let is_in_range = compiler.builder().build_int_compare(
+            IntPredicate::SLT,
+            counter_value,
+            end_value,
+            "isInRange",
+        );
+        compiler
+            .builder()
+            .build_conditional_branch(is_in_range, body_bb, end_bb);
+
+        // Build the body of the loop.
+        compiler.builder().position_at_end(body_bb);
+        compiler.build_step(|c| Ok(self.step_limit_error.compile(c)))?;
+        compiler.builder().build_store(var_ptr, counter_value);
+        compiler.build_loop_body(step_bb, end_bb, |c| {
+            userfunc.compile_statement_block(c, &self.body)
+        })?;
+        if compiler.needs_terminator() {
+            compiler.builder().build_unconditional_branch(step_bb);
+        }
+
+        // Increment the counter, then jump back to the condition. This cannot
+        // overflow, because the counter is less than the end of the range.
+        compiler.builder().position_at_end(step_bb);
+        let one = int_type.const_int(1, false);
+        let next_counter_value =
+            compiler
+                .builder()
+                .build_int_add(counter_value, one, "nextForCounter");
+        compiler.builder().build_unconditional_branch(cond_bb);
+        counter.add_incoming(&[(&start_value, entry_bb), (&next_counter_value, step_bb)]);
+
+        compiler.builder().position_at_end(end_bb);
+        Ok(())
+    }
+}
+
+/// A loop that executes a block of statements once for each neighbor declared
+/// by the `@neighborhood` directive, such as `for n in neighborhood { ... }`.
+#[derive(Debug)]
+pub struct ForNeighbors {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Name of the loop variable.
+    var_name: String,
+    /// Indices of the neighbors in the flat neighborhood array.
+    neighbor_indices: Vec<usize>,
+    /// Block of statements to evaluate each iteration.
+    body: StatementBlock,
+    /// Error returned if the step limit is exceeded.
+    step_limit_error: ErrorPointRef,
+}
+impl ForNeighbors {
+    /// Constructs a new loop that evaluates the given block once for each
+    /// neighbor, in the order they were declared, assigning the cell state of
+    /// each one to the given variable. The variable must already exist.
+    pub fn new(
+        span: Span,
+        userfunc: &mut UserFunction,
+        var_name: String,
+        body: StatementBlock,
+    ) -> Self {
+        let rule_meta = userfunc.rule_meta().clone();
+        let neighbor_indices = rule_meta
+            .neighborhood
+            .iter()
+            .map(|offset| rule_meta.neighbor_index(offset))
+            .collect();
+        let step_limit_error = userfunc.add_error_point(StepLimitExceeded.with_span(span));
+        Self {
+            span,
+            var_name,
+            neighbor_indices,
+            body,
+            step_limit_error,
+        }
+    }
+}
+impl Statement for ForNeighbors {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let var_ptr = compiler
+            .vars()
+            .get(&self.var_name)
+            .ok_or_else(|| InternalError("Invalid variable index".into()))?
+            .ptr;
+
+        // The neighborhood is known at compile time, so store the index of
+        // each neighbor in a constant array and loop over that, just like a
+        // `for` loop over a range.
+        let indices: Vec<u64> = self.neighbor_indices.iter().map(|&i| i as u64).collect();
+        let indices_ptr = compiler.build_const_int_array("neighborIndices", &indices);
+        let entry_bb = compiler.builder().get_insert_block().unwrap();
+
+        // Build the destination blocks.
+        let cond_bb = compiler.append_basic_block("forNeighborsCond");
+        let body_bb = compiler.append_basic_block("forNeighborsBody");
+        let step_bb = compiler.append_basic_block("forNeighborsStep");
+        let end_bb = compiler.append_basic_block("endForNeighbors");
+
+        // Check the counter against the number of neighbors.
+        compiler.builder().build_unconditional_branch(cond_bb);
+        compiler.builder().position_at_end(cond_bb);
+        let int_type = compiler.int_type();
+        let counter = compiler
+            .builder()
+            .build_phi(int_type, "forNeighborsCounter");
+        let counter_value = counter.as_basic_value().into_int_value();
+        let neighbor_count = int_type.const_int(indices.len() as u64, false);
+        let is_in_range = compiler.builder().build_int_compare(
+            IntPredicate::ULT,
+            counter_value,
+            neighbor_count,
+            "isInRange",
+        );
+        compiler
+            .builder()
+            .build_conditional_branch(is_in_range, body_bb, end_bb);
+
+        // Build the body of the loop, which loads the next neighbor into the
+        // loop variable.
+        compiler.builder().position_at_end(body_bb);
+        compiler.build_step(|c| Ok(self.step_limit_error.compile(c)))?;
+        let index = compiler.build_load_const_array_element(indices_ptr, counter_value);
+        let neighbor = compiler.build_load_neighbor(index)?;
+        compiler.builder().build_store(var_ptr, neighbor);
+        compiler.build_loop_body(step_bb, end_bb, |c| {
+            userfunc.compile_statement_block(c, &self.body)
+        })?;
+        if compiler.needs_terminator() {
+            compiler.builder().build_unconditional_branch(step_bb);
+        }
+
+        // Increment the counter, then jump back to the condition.
+        compiler.builder().position_at_end(step_bb);
+        let one = int_type.const_int(1, false);
+        let next_counter_value =
+            compiler
+                .builder()
+                .build_int_add(counter_value, one, "nextForNeighborsCounter");
+        compiler.builder().build_unconditional_branch(cond_bb);
+        let zero = int_type.const_zero();
+        counter.add_incoming(&[(&zero, entry_bb), (&next_counter_value, step_bb)]);
+
+        compiler.builder().position_at_end(end_bb);
+        Ok(())
+    }
+}
+
+/// A loop that executes a block of statements as long as a condition is true,
+/// such as `while x < 10 { ... }`.
+#[derive(Debug)]
+pub struct While {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Expression to check before each iteration.
     cond_expr: ExprRef,
-    /// Block of statement to evaluate if the condition is truthy.
-    if_true: StatementBlock,
-    /// Block of statements to evaluate if the condition if falsey.
-    if_false: StatementBlock,
+    /// Block of statements to evaluate each iteration.
+    body: StatementBlock,
+    /// Error returned if the step limit is exceeded.
+    step_limit_error: ErrorPointRef,
 }
-impl If {
-    /// Constructs a new conditional statement branches to either of the given
-    /// blocks depending on whether the result of the given expression is truthy
-    /// (nonzero) or falsey (zero).
+impl While {
+    /// Constructs a new loop that repeatedly evaluates the given block as long
+    /// as the result of the given expression is true.
     ///
     /// This method checks the type of the condition expression.
     pub fn try_new(
         span: Span,
         userfunc: &mut UserFunction,
         cond_expr: ExprRef,
-        if_true: StatementBlock,
-        if_false: StatementBlock,
+        body: StatementBlock,
     ) -> LangResult<Self> {
-        let expected = Type::Int;
+        let expected = Type::Bool;
+        let got = userfunc[cond_expr].return_type();
+        let cond_expr_span = userfunc[cond_expr].span();
+        if expected != got {
+            Err(TypeError { expected, got }.with_span(cond_expr_span))?;
+        }
+        let step_limit_error =
+            userfunc.add_error_point(StepLimitExceeded.with_span(cond_expr_span));
+        Ok(Self {
+            span,
+            cond_expr,
+            body,
+            step_limit_error,
+        })
+    }
+}
+impl Statement for While {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        // Build the destination blocks.
+        let cond_bb = compiler.append_basic_block("whileCond");
+        let body_bb = compiler.append_basic_block("whileBody");
+        let end_bb = compiler.append_basic_block("endWhile");
+
+        // Check the condition before each iteration.
+        compiler.builder().build_unconditional_branch(cond_bb);
+        compiler.builder().position_at_end(cond_bb);
+        let condition_value = userfunc[self.cond_expr]
+            .compile(compiler, userfunc)?
+            .as_bool()?;
+        compiler
+            .builder()
+            .build_conditional_branch(condition_value, body_bb, end_bb);
+
+        // Build the body of the loop, then jump back to the condition.
+        compiler.builder().position_at_end(body_bb);
+        compiler.build_step(|c| Ok(self.step_limit_error.compile(c)))?;
+        compiler.build_loop_body(cond_bb, end_bb, |c| {
+            userfunc.compile_statement_block(c, &self.body)
+        })?;
+        if compiler.needs_terminator() {
+            compiler.builder().build_unconditional_branch(cond_bb);
+        }
+
+        compiler.builder().position_at_end(end_bb);
+        Ok(())
+    }
+}
+
+/// A statement that returns an error if a condition is false, such as `assert
+/// x > 0, "x must be positive"`.
+#[derive(Debug)]
+pub struct Assert {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Condition that must be true.
+    cond_expr: ExprRef,
+    /// Error returned if the condition is false.
+    assertion_error: ErrorPointRef,
+}
+impl Assert {
+    /// Constructs a new statement that returns an error, with the given
+    /// message if any, if the result of the given expression is false.
+    ///
+    /// This method checks the type of the condition expression.
+    pub fn try_new(
+        span: Span,
+        userfunc: &mut UserFunction,
+        cond_expr: ExprRef,
+        msg: Option<String>,
+    ) -> LangResult<Self> {
+        let expected = Type::Bool;
         let got = userfunc[cond_expr].return_type();
         if expected != got {
             let cond_expr_span = userfunc[cond_expr].span();
             Err(TypeError { expected, got }.with_span(cond_expr_span))?;
         }
+        let assertion_error = userfunc.add_error_point(AssertionFailed(msg).with_span(span));
         Ok(Self {
             span,
             cond_expr,
-            if_true,
-            if_false,
+            assertion_error,
         })
     }
 }
-impl Statement for If {
+impl Statement for Assert {
     fn span(&self) -> Span {
         self.span
     }
     fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
         let condition_value = userfunc[self.cond_expr]
             .compile(compiler, userfunc)?
-            .as_int()?;
+            .as_bool()?;
         compiler.build_conditional(
             condition_value,
-            |c| userfunc.compile_statement_block(c, &self.if_true),
-            |c| userfunc.compile_statement_block(c, &self.if_false),
+            |_| Ok(()),
+            |c| Ok(self.assertion_error.compile(c)),
         )?;
         Ok(())
     }
 }
 
+/// A statement that records the value of an expression for debugging, such as
+/// `debug x`.
+///
+/// The value is only recorded in debug mode (see
+/// CompiledFunction::debug_values()); otherwise the expression is evaluated
+/// without using the result, so that any runtime errors in it are still
+/// reported.
+#[derive(Debug)]
+pub struct DebugStmt {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Expression to evaluate.
+    expr: ExprRef,
+}
+impl DebugStmt {
+    /// Constructs a new statement that records the value of the given
+    /// expression.
+    pub fn new(span: Span, expr: ExprRef) -> Self {
+        Self { span, expr }
+    }
+}
+impl Statement for DebugStmt {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, userfunc: &UserFunction) -> LangResult<()> {
+        let value = userfunc.compile_expr(compiler, self.expr)?;
+        compiler.build_debug_trace_entry(&value)
+    }
+}
+
+/// A statement that exits the innermost loop: `break`.
+#[derive(Debug)]
+pub struct Break {
+    /// Span of this statement in the original source code.
+    span: Span,
+}
+impl Break {
+    /// Constructs a new statement that exits the innermost loop.
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+}
+impl Statement for Break {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, _userfunc: &UserFunction) -> LangResult<()> {
+        compiler.build_break()
+    }
+}
+
+/// A statement that skips to the next iteration of the innermost loop:
+/// `continue`.
+#[derive(Debug)]
+pub struct Continue {
+    /// Span of this statement in the original source code.
+    span: Span,
+}
+impl Continue {
+    /// Constructs a new statement that skips to the next iteration of the
+    /// innermost loop.
+    pub fn new(span: Span) -> Self {
+        Self { span }
+    }
+}
+impl Statement for Continue {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, _userfunc: &UserFunction) -> LangResult<()> {
+        compiler.build_continue()
+    }
+}
+
 /// A return statement, such as `return 3` or `become #live`.
 #[derive(Debug)]
 pub struct Return {
@@ -164,3 +834,35 @@ impl Statement for Return {
         Ok(())
     }
 }
+
+/// A statement that returns the state of the center cell from the transition
+/// function, leaving it unchanged: `remain`.
+#[derive(Debug)]
+pub struct Remain {
+    /// Span of this statement in the original source code.
+    span: Span,
+    /// Index of the center cell in the flat neighborhood array.
+    center_cell_index: usize,
+}
+impl Remain {
+    /// Constructs a new statement that returns the state of the center cell.
+    pub fn new(span: Span, userfunc: &UserFunction) -> Self {
+        Self {
+            span,
+            center_cell_index: userfunc.rule_meta().center_cell_index(),
+        }
+    }
+}
+impl Statement for Remain {
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn compile(&self, compiler: &mut Compiler, _userfunc: &UserFunction) -> LangResult<()> {
+        let index = compiler
+            .int_type()
+            .const_int(self.center_cell_index as u64, false);
+        let center_cell_state = compiler.build_load_neighbor(index)?;
+        compiler.build_return_ok(Value::CellState(center_cell_state))?;
+        Ok(())
+    }
+}
@@ -5,14 +5,15 @@ use std::rc::Rc;
 use super::super::compiler::{CompiledFunction, Compiler, Value};
 use super::super::errors::*;
 use super::super::functions;
-use super::super::lexer::{OperatorToken, PunctuationToken};
+use super::super::lexer::{AssignmentToken, OperatorToken, PunctuationToken};
 use super::super::parser;
 use super::super::{ConstValue, Span, Spanned, Type};
 use super::statements;
 use super::{Args, Expr, Function, RuleMeta, Statement, StatementBlock};
 use LangErrorMsg::{
-    BecomeInHelperFunction, ExpectedGot, InternalError, ReturnInTransitionFunction,
-    UseOfUninitializedVariable,
+    AssignToConstant, AssignmentCountMismatch, BecomeInHelperFunction, BreakOutsideLoop,
+    CannotEvalAsConst, ExpectedGot, InternalError, RemainInHelperFunction,
+    ReturnInTransitionFunction, UnreachableCode, UseOfUninitializedVariable,
 };
 
 /// A user-defined function node in the AST.
@@ -25,6 +26,9 @@ pub struct UserFunction {
     /// Whether this is the transition function, as opposed to a helper function
     /// (determines whether `become`/`remain` or `return` is accepted).
     is_transition_function: bool,
+    /// Number of loops enclosing the statement currently being built
+    /// (determines whether `break`/`continue` is accepted).
+    loop_depth: usize,
 
     /// Top-level statement block, consisting of StatementRefs to self.statements.
     top_level_statements: StatementBlock,
@@ -34,9 +38,18 @@ pub struct UserFunction {
     expressions: Vec<Expr>,
     /// List of every possible runtime error.
     error_points: Vec<LangError>,
+    /// List of every error encountered while building this function's AST, in
+    /// order.
+    errors: Vec<LangError>,
+    /// List of every warning encountered while building this function's AST,
+    /// in order.
+    warnings: Vec<LangError>,
 
-    /// HashMap of variable types, indexed by name.
+    /// HashMap of variable types, indexed by unique name.
     variables: HashMap<String, Type>,
+    /// Stack of lexical scopes, from outermost to innermost, each mapping the
+    /// names of variables declared in that scope to their unique names.
+    scopes: Vec<HashMap<String, String>>,
     /// List of variable names for arguments.
     arg_names: Vec<String>,
     /// Return ttype of this function.
@@ -60,30 +73,39 @@ impl UserFunction {
         return_type: Type,
     ) -> Self {
         let mut variables = HashMap::new();
+        let mut arg_scope = HashMap::new();
         let mut arg_names = vec![];
         for (name, ty) in args {
             variables.insert(name.clone(), ty);
+            arg_scope.insert(name.clone(), name.clone());
             arg_names.push(name);
         }
         Self {
             rule_meta,
             name,
             is_transition_function: false,
+            loop_depth: 0,
 
             top_level_statements: vec![],
             statements: vec![],
             expressions: vec![],
             error_points: vec![],
+            errors: vec![],
+            warnings: vec![],
 
             arg_names,
             variables,
+            scopes: vec![arg_scope],
             return_type,
         }
     }
+    /// Constructs a helper function and builds its AST from a parse tree.
+    ///
+    /// Any errors are recorded in the list returned by errors().
     pub fn build_helper_function(
         rule_meta: &Rc<RuleMeta>,
         helper_func: parser::HelperFunc,
-    ) -> LangResult<Self> {
+    ) -> Self {
         let mut ret = Self::new_helper_function(
             rule_meta.clone(),
             helper_func.name.inner,
@@ -94,8 +116,8 @@ impl UserFunction {
                 .collect(),
             helper_func.return_type.inner.resolve(rule_meta.ndim),
         );
-        ret.build_top_level_statement_block_ast(&helper_func.body.inner)?;
-        Ok(ret)
+        ret.build_top_level_statement_block_ast(&helper_func.body.inner);
+        ret
     }
 
     /// Returns the metadata associated with the rule that this function is a
@@ -116,108 +138,433 @@ impl UserFunction {
     pub fn return_type(&self) -> Type {
         self.return_type
     }
+    /// Returns the list of every possible runtime error of this function.
+    pub fn error_points(&self) -> &[LangError] {
+        &self.error_points
+    }
+    /// Returns the list of every error encountered while building this
+    /// function's AST, in order.
+    pub fn errors(&self) -> &[LangError] {
+        &self.errors
+    }
+    /// Returns the list of every warning encountered while building this
+    /// function's AST, in order.
+    pub fn warnings(&self) -> &[LangError] {
+        &self.warnings
+    }
 
-    /// Returns the type of an existing variable with the given name, or an
-    /// Err(UseOfUninitializedVariable) if it does not exist.
-    pub fn try_get_var(&self, span: Span, var_name: &str) -> LangResult<Type> {
-        self.variables
-            .get(var_name)
-            .copied()
+    /// Returns the unique name of the variable with the given name that is
+    /// visible from the current scope, if there is one.
+    fn resolve_var(&self, var_name: &str) -> Option<&String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(var_name))
+    }
+    /// Returns the unique name and type of an existing variable with the given
+    /// name that is visible from the current scope, or an
+    /// Err(UseOfUninitializedVariable) if there is none.
+    pub fn try_get_var(&self, span: Span, var_name: &str) -> LangResult<(String, Type)> {
+        self.resolve_var(var_name)
+            .map(|unique_name| (unique_name.clone(), self.variables[unique_name]))
             .ok_or_else(|| UseOfUninitializedVariable.with_span(span))
     }
-    /// Returns the type of the variable with the given name, creating it with
-    /// the given type if it does not already exist.
-    pub fn get_or_create_var(&mut self, var_name: &str, new_ty: Type) -> Type {
-        if let Some(existing_type) = self.variables.get(var_name) {
-            *existing_type
+    /// Returns the unique name and type of the variable with the given name
+    /// that is visible from the current scope, creating it in the current
+    /// scope with the given type if there is none.
+    pub fn get_or_create_var(&mut self, var_name: &str, new_ty: Type) -> (String, Type) {
+        match self.resolve_var(var_name) {
+            Some(unique_name) => (unique_name.clone(), self.variables[unique_name]),
+            None => (self.create_var(var_name, new_ty), new_ty),
+        }
+    }
+    /// Returns an error if the given variable name is the name of a constant,
+    /// which cannot be assigned to.
+    fn check_not_constant(&self, var_name: &Spanned<String>) -> LangResult<()> {
+        if self.rule_meta.constants.contains_key(&var_name.inner) {
+            Err(AssignToConstant.with_span(var_name.span))
         } else {
-            self.variables.insert(var_name.to_owned(), new_ty);
-            new_ty
+            Ok(())
+        }
+    }
+    /// Creates a new variable with the given name and type in the current
+    /// scope, shadowing any variable with the same name in an outer scope, and
+    /// returns its unique name.
+    pub fn create_var(&mut self, var_name: &str, ty: Type) -> String {
+        // Variables in different scopes may have the same name but different
+        // types, so give each one a unique name.
+        let mut unique_name = var_name.to_owned();
+        let mut i = 1;
+        while self.variables.contains_key(&unique_name) {
+            unique_name = format!("{}.{}", var_name, i);
+            i += 1;
         }
+        self.variables.insert(unique_name.clone(), ty);
+        self.scopes
+            .last_mut()
+            .expect("No variable scope")
+            .insert(var_name.to_owned(), unique_name.clone());
+        unique_name
     }
 
     /// Constructs AST nodes for statements in a block from a parse tree and
     /// adds those AST nodes to a list of top-level statements (i.e. statements
     /// that are not inside a loop or conditional block).
+    ///
+    /// Any errors are recorded in the list returned by errors().
     pub fn build_top_level_statement_block_ast(
         &mut self,
         parser_statements: &parser::StatementBlock,
-    ) -> LangResult<()> {
-        self.top_level_statements = self.build_statement_block_ast(parser_statements)?;
+    ) {
+        self.top_level_statements = self.build_statement_block_ast(parser_statements);
+        // Statements that failed to build may leave behind partially-built
+        // expressions, so only fold constants if there were no errors.
+        if self.errors.is_empty() {
+            if let Err(e) = self.fold_all_constants() {
+                self.errors.push(e);
+            }
+        }
+    }
+    /// Replaces every expression that can be evaluated at compile time with a
+    /// literal, starting from the outermost expressions so that
+    /// short-circuiting is respected.
+    fn fold_all_constants(&mut self) -> LangResult<()> {
+        let mut is_root = vec![true; self.expressions.len()];
+        for expr in &self.expressions {
+            for &ExprRef(idx) in expr.args().iter_refs() {
+                is_root[idx] = false;
+            }
+        }
+        for (idx, root) in is_root.into_iter().enumerate() {
+            if root {
+                self.fold_constants(ExprRef(idx))?;
+            }
+        }
         Ok(())
     }
+    /// Replaces the given expression with a literal if it can be evaluated at
+    /// compile time; otherwise tries the same for each of its arguments.
+    ///
+    /// Errors encountered while evaluating (e.g. overflow or division by zero)
+    /// are returned as compile errors.
+    fn fold_constants(&mut self, expr_ref: ExprRef) -> LangResult<()> {
+        let expr = &self[expr_ref];
+        // Literals and variables are already as simple as possible.
+        if expr.args().len() == 0 {
+            return Ok(());
+        }
+        let function: Box<dyn Function> = match expr.const_eval(self) {
+            Ok(ConstValue::Int(i)) => Box::new(functions::literals::Int(i)),
+            Ok(ConstValue::CellState(i)) => Box::new(functions::literals::CellState(i)),
+            Ok(ConstValue::Bool(b)) => Box::new(functions::literals::Bool(b)),
+            // There is no vector literal, but the components may be constant.
+            Ok(ConstValue::Vector(_))
+            | Err(LangError {
+                msg: CannotEvalAsConst,
+                ..
+            }) => {
+                let args: Vec<ExprRef> = expr.args().iter_refs().copied().collect();
+                for arg in args {
+                    self.fold_constants(arg)?;
+                }
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let span = expr.span();
+        self.expressions[expr_ref.0] = Expr::try_new(span, self, function, Args::none())?;
+        Ok(())
+    }
+    /// Constructs AST nodes for statements in the body of a loop from a parse
+    /// tree.
+    fn build_loop_body_ast(
+        &mut self,
+        parser_statements: &parser::StatementBlock,
+    ) -> StatementBlock {
+        self.loop_depth += 1;
+        let ret = self.build_statement_block_ast(parser_statements);
+        self.loop_depth -= 1;
+        ret
+    }
     /// Constructs AST nodes for statements in a block from a parse tree.
+    ///
+    /// If a statement cannot be built, the error is recorded in the list
+    /// returned by errors() and the statement is left out of the block, so
+    /// that errors in later statements are found as well.
     pub fn build_statement_block_ast(
         &mut self,
         parser_statements: &parser::StatementBlock,
-    ) -> LangResult<StatementBlock> {
+    ) -> StatementBlock {
+        // Variables created in this block are not visible outside of it.
+        self.scopes.push(HashMap::new());
         let mut block = vec![];
         for parser_statement in parser_statements {
-            let span = parser_statement.span;
-
-            let new_statement: Box<dyn Statement> = match &parser_statement.inner {
-                // Variable assignment statement
-                parser::Statement::SetVar {
-                    var_name,
-                    assign_op,
+            match self.build_statement_ast(parser_statement) {
+                Ok(new_statement) => block.push(self.add_statement(new_statement)),
+                Err(e) => self.errors.push(e),
+            }
+        }
+        self.scopes.pop();
+        // Warn about the first statement that can never be reached, if any.
+        if let Some(i) = parser_statements
+            .iter()
+            .position(|s| always_diverges(&s.inner))
+        {
+            if let Some(unreachable) = parser_statements.get(i + 1) {
+                self.warnings
+                    .push(UnreachableCode.with_span(unreachable.span));
+            }
+        }
+        block
+    }
+    /// Constructs an AST node for the value assigned to a variable, handling
+    /// assignments with operators (e.g. `x += 3`).
+    fn build_assigned_value_ast(
+        &mut self,
+        span: Span,
+        var_name: &Spanned<String>,
+        assign_op: AssignmentToken,
+        value_expr: &Spanned<parser::Expr>,
+    ) -> LangResult<ExprRef> {
+        match assign_op.op() {
+            Some(op) => self.build_expression_ast(&Spanned {
+                span,
+                inner: parser::Expr::BinaryOp {
+                    lhs: Box::new(var_name.clone().map(parser::Expr::Ident)),
+                    op,
+                    rhs: Box::new(value_expr.clone()),
+                },
+            }),
+            None => self.build_expression_ast(value_expr),
+        }
+    }
+    /// Constructs an AST node for a statement from a parse tree.
+    fn build_statement_ast(
+        &mut self,
+        parser_statement: &Spanned<parser::Statement>,
+    ) -> LangResult<Box<dyn Statement>> {
+        let span = parser_statement.span;
+        Ok(match &parser_statement.inner {
+            // Variable assignment statement
+            parser::Statement::SetVar {
+                var_name,
+                assign_op,
+                value_expr,
+            } => {
+                self.check_not_constant(var_name)?;
+                let value_expr =
+                    self.build_assigned_value_ast(span, var_name, *assign_op, value_expr)?;
+                Box::new(statements::SetVar::try_new(
+                    span,
+                    self,
+                    var_name.inner.clone(),
                     value_expr,
-                } => {
-                    // Handle assignments with operators (e.g. `x += 3`).
-                    let value_expr = match assign_op.op() {
-                        Some(op) => self.build_expression_ast(&Spanned {
-                            span,
-                            inner: parser::Expr::BinaryOp {
-                                lhs: Box::new(var_name.clone().map(parser::Expr::Ident)),
-                                op,
-                                rhs: Box::new(value_expr.clone()),
-                            },
-                        })?,
-                        None => self.build_expression_ast(&value_expr)?,
-                    };
-                    Box::new(statements::SetVar::try_new(
-                        span,
-                        self,
-                        var_name.inner.clone(),
-                        value_expr,
-                    )?)
+                )?)
+            }
+            // Parallel variable assignment statement
+            parser::Statement::SetVars {
+                var_names,
+                assign_op,
+                value_exprs,
+            } => {
+                if var_names.len() != value_exprs.len() {
+                    Err(AssignmentCountMismatch {
+                        vars: var_names.len(),
+                        values: value_exprs.len(),
+                    }
+                    .with_span(span))?;
                 }
-                // If statement
-                parser::Statement::If {
-                    cond_expr,
-                    if_true,
-                    if_false,
-                } => {
-                    let cond_expr = self.build_expression_ast(cond_expr)?;
-                    let if_true = self.build_statement_block_ast(if_true)?;
-                    let if_false = self.build_statement_block_ast(if_false)?;
-                    Box::new(statements::If::try_new(
-                        span, self, cond_expr, if_true, if_false,
-                    )?)
+                for var_name in var_names {
+                    self.check_not_constant(var_name)?;
                 }
-                // Become statement (In a transition function, `become` should be used, not `return`.)
-                parser::Statement::Become(ret_expr) => {
-                    if self.is_transition_function {
-                        let ret_expr = self.build_expression_ast(ret_expr)?;
-                        Box::new(statements::Return::try_new(span, self, ret_expr)?)
-                    } else {
-                        Err(BecomeInHelperFunction.with_span(span))?
-                    }
+                // Build every value before assigning to any variable.
+                let values = var_names
+                    .iter()
+                    .zip(value_exprs)
+                    .map(|(var_name, value_expr)| {
+                        let span = Span::merge(var_name.span, value_expr.span);
+                        let value_expr =
+                            self.build_assigned_value_ast(span, var_name, *assign_op, value_expr)?;
+                        Ok((span, value_expr))
+                    })
+                    .collect::<LangResult<Vec<_>>>()?;
+                let assignments = var_names
+                    .iter()
+                    .zip(values)
+                    .map(|(var_name, (span, value_expr))| {
+                        statements::SetVar::try_new(span, self, var_name.inner.clone(), value_expr)
+                    })
+                    .collect::<LangResult<Vec<_>>>()?;
+                Box::new(statements::SetVars::new(span, assignments))
+            }
+            // Chained variable assignment statement
+            parser::Statement::SetVarChain {
+                var_names,
+                value_expr,
+            } => {
+                for var_name in var_names {
+                    self.check_not_constant(var_name)?;
                 }
+                // Every variable gets the result of the same expression.
+                let value_expr = self.build_expression_ast(value_expr)?;
+                let assignments = var_names
+                    .iter()
+                    .map(|var_name| {
+                        statements::SetVar::try_new(span, self, var_name.inner.clone(), value_expr)
+                    })
+                    .collect::<LangResult<Vec<_>>>()?;
+                Box::new(statements::SetVarChain::new(span, assignments))
+            }
+            // If statement
+            parser::Statement::If { arms, else_block } => {
+                let arms = arms
+                    .iter()
+                    .map(|(cond_expr, block)| {
+                        Ok((
+                            self.build_expression_ast(cond_expr)?,
+                            self.build_statement_block_ast(block),
+                        ))
+                    })
+                    .collect::<LangResult<Vec<_>>>()?;
+                let else_block = self.build_statement_block_ast(else_block);
+                Box::new(statements::If::try_new(span, self, arms, else_block)?)
+            }
+            // Match statement
+            parser::Statement::Match {
+                expr,
+                arms,
+                else_arm,
+            } => {
+                let expr = self.build_expression_ast(expr)?;
+                let arms = arms
+                    .iter()
+                    .map(|(value, block)| {
+                        Ok((
+                            self.build_expression_ast(value)?,
+                            self.build_statement_block_ast(block),
+                        ))
+                    })
+                    .collect::<LangResult<Vec<_>>>()?;
+                let else_arm = else_arm
+                    .as_ref()
+                    .map(|block| self.build_statement_block_ast(block));
+                Box::new(statements::Match::try_new(
+                    span, self, expr, arms, else_arm,
+                )?)
+            }
+            // For loop
+            parser::Statement::For {
+                var_name,
+                start_expr,
+                end_expr,
+                body,
+            } => {
+                self.check_not_constant(var_name)?;
+                let start_expr = self.build_expression_ast(start_expr)?;
+                let end_expr = self.build_expression_ast(end_expr)?;
+                // Create the loop variable in its own scope before building
+                // the body, so that the body can use it but nothing after the
+                // loop can.
+                self.scopes.push(HashMap::new());
+                let unique_var_name = self.create_var(&var_name.inner, Type::Int);
+                let body = self.build_loop_body_ast(body);
+                self.scopes.pop();
+                Box::new(statements::For::try_new(
+                    span,
+                    self,
+                    unique_var_name,
+                    start_expr,
+                    end_expr,
+                    body,
+                )?)
+            }
+            // Loop over the neighborhood
+            parser::Statement::ForNeighbors { var_name, body } => {
+                self.check_not_constant(var_name)?;
+                self.scopes.push(HashMap::new());
+                let unique_var_name = self.create_var(&var_name.inner, Type::CellState);
+                let body = self.build_loop_body_ast(body);
+                self.scopes.pop();
+                Box::new(statements::ForNeighbors::new(
+                    span,
+                    self,
+                    unique_var_name,
+                    body,
+                ))
+            }
+            // While loop
+            parser::Statement::While { cond_expr, body } => {
+                let cond_expr = self.build_expression_ast(cond_expr)?;
+                let body = self.build_loop_body_ast(body);
+                Box::new(statements::While::try_new(span, self, cond_expr, body)?)
+            }
+            // Loop control statements (only allowed inside a loop)
+            parser::Statement::Break | parser::Statement::Continue => {
+                if self.loop_depth == 0 {
+                    Err(BreakOutsideLoop.with_span(span))?
+                } else if parser_statement.inner == parser::Statement::Break {
+                    Box::new(statements::Break::new(span))
+                } else {
+                    Box::new(statements::Continue::new(span))
+                }
+            }
+            // Assert statement
+            parser::Statement::Assert { cond_expr, msg } => {
+                let cond_expr = self.build_expression_ast(cond_expr)?;
+                let msg = msg.as_ref().map(|msg| msg.inner.clone());
+                Box::new(statements::Assert::try_new(span, self, cond_expr, msg)?)
+            }
+            // Debug statement
+            parser::Statement::Debug(expr) => {
+                let expr = self.build_expression_ast(expr)?;
+                Box::new(statements::DebugStmt::new(span, expr))
+            }
+            // Become statement (In a transition function, `become` should be used, not `return`.)
+            parser::Statement::Become(ret_expr) => {
+                if self.is_transition_function {
+                    let ret_expr = self.build_return_value_ast(ret_expr)?;
+                    Box::new(statements::Return::try_new(span, self, ret_expr)?)
+                } else {
+                    Err(BecomeInHelperFunction.with_span(span))?
+                }
+            }
 
-                // Retrurn statement (In a helper function, `return` should be used, not `become`.)
-                parser::Statement::Return(ret_expr) => {
-                    if self.is_transition_function {
-                        Err(ReturnInTransitionFunction.with_span(span))?
-                    } else {
-                        let ret_expr = self.build_expression_ast(ret_expr)?;
-                        Box::new(statements::Return::try_new(span, self, ret_expr)?)
-                    }
+            // Remain statement (only allowed in a transition function)
+            parser::Statement::Remain => {
+                if self.is_transition_function {
+                    Box::new(statements::Remain::new(span, self))
+                } else {
+                    Err(RemainInHelperFunction.with_span(span))?
                 }
-            };
+            }
 
-            block.push(self.add_statement(new_statement));
+            // Retrurn statement (In a helper function, `return` should be used, not `become`.)
+            parser::Statement::Return(ret_expr) => {
+                if self.is_transition_function {
+                    Err(ReturnInTransitionFunction.with_span(span))?
+                } else {
+                    let ret_expr = self.build_return_value_ast(ret_expr)?;
+                    Box::new(statements::Return::try_new(span, self, ret_expr)?)
+                }
+            }
+        })
+    }
+    /// Constructs an AST node for the value returned by a `become` or `return`
+    /// statement from a parse tree, implicitly converting an integer to a cell
+    /// state (as if using `#`) if the function returns a cell state.
+    fn build_return_value_ast(
+        &mut self,
+        parser_expr: &Spanned<parser::Expr>,
+    ) -> LangResult<ExprRef> {
+        let span = parser_expr.span;
+        let ret_expr = self.build_expression_ast(parser_expr)?;
+        if self.return_type == Type::CellState && self[ret_expr].return_type() == Type::Int {
+            let function = Box::new(functions::convert::IntToCellState::try_new(self, span)?);
+            let expr = Expr::try_new(span, self, function, Args::from(vec![ret_expr]))?;
+            Ok(self.add_expr(expr))
+        } else {
+            Ok(ret_expr)
         }
-        Ok(block)
     }
     /// Constructs an AST node for an expression from a parse tree.
     pub fn build_expression_ast(
@@ -234,10 +581,33 @@ impl UserFunction {
                 args = Args::none();
                 function = Box::new(functions::literals::Int(*i));
             }
-            // Identifier (variable)
+            // Boolean literal
+            parser::Expr::Bool(b) => {
+                args = Args::none();
+                function = Box::new(functions::literals::Bool(*b));
+            }
+            // State of the center cell
+            parser::Expr::This => {
+                args = Args::none();
+                function = Box::new(functions::neighborhood::GetCenterCell::new(self));
+            }
+            // Identifier (variable or cell state name)
             parser::Expr::Ident(s) => {
                 args = Args::none();
-                function = Box::new(functions::misc::GetVar::try_new(self, span, s.to_owned())?);
+                // Variables take precedence over constants, which take
+                // precedence over cell state names.
+                let constant = self.rule_meta.constants.get(s).copied();
+                let state_id = self.rule_meta.state_names.get(s).copied();
+                function = match (constant, state_id) {
+                    _ if self.resolve_var(s).is_some() => {
+                        Box::new(functions::misc::GetVar::try_new(self, span, s.to_owned())?)
+                    }
+                    (Some(value), _) => Box::new(functions::literals::Int(value)),
+                    (None, Some(id)) => Box::new(functions::literals::CellState(id)),
+                    (None, None) => {
+                        Box::new(functions::misc::GetVar::try_new(self, span, s.to_owned())?)
+                    }
+                };
             }
             // Parenthetical/bracketed group
             parser::Expr::Group { start_token, inner } => {
@@ -258,16 +628,31 @@ impl UserFunction {
             }
             // Unary operator
             parser::Expr::UnaryOp { op, operand } => match op {
+                // Unary plus (no-op)
+                OperatorToken::Plus => {
+                    args = Args::from(vec![self.build_expression_ast(operand)?]);
+                    function = Box::new(functions::math::PosInt);
+                }
                 // Negation
                 OperatorToken::Minus => {
                     args = Args::from(vec![self.build_expression_ast(operand)?]);
                     function = Box::new(functions::math::NegInt::try_new(self, span)?);
                 }
+                // Bitwise NOT
+                OperatorToken::Tilde => {
+                    args = Args::from(vec![self.build_expression_ast(operand)?]);
+                    function = Box::new(functions::math::BitwiseNotInt);
+                }
                 // Get cell state from integer ID
                 OperatorToken::Tag => {
                     args = Args::from(vec![self.build_expression_ast(operand)?]);
                     function = Box::new(functions::convert::IntToCellState::try_new(self, span)?);
                 }
+                // Get cell state from integer ID, clamped to the valid range
+                OperatorToken::TagClamp => {
+                    args = Args::from(vec![self.build_expression_ast(operand)?]);
+                    function = Box::new(functions::convert::ClampIntToCellState::new(self));
+                }
                 _ => return Err(InternalError("Invalid unary operator".into()).with_span(span)),
             },
             // Binary operator
@@ -278,17 +663,27 @@ impl UserFunction {
                 | OperatorToken::Asterisk
                 | OperatorToken::Slash
                 | OperatorToken::Percent
+                | OperatorToken::DoublePercent
+                | OperatorToken::SlashPercent
                 | OperatorToken::DoubleAsterisk
                 | OperatorToken::DoubleLessThan
                 | OperatorToken::DoubleGreaterThan
                 | OperatorToken::TripleGreaterThan
                 | OperatorToken::Ampersand
-                | OperatorToken::Pipe => {
+                | OperatorToken::Pipe
+                | OperatorToken::Caret => {
                     args = Args::from(vec![
                         self.build_expression_ast(lhs)?,
                         self.build_expression_ast(rhs)?,
                     ]);
-                    function = Box::new(functions::math::BinaryIntOp::try_new(self, span, *op)?);
+                    let is_vector_op = args
+                        .iter(self)
+                        .any(|e| matches!(e.return_type(), Type::Vector(_)));
+                    function = if is_vector_op {
+                        Box::new(functions::vectors::BinaryVecOp::try_new(self, span, *op)?)
+                    } else {
+                        Box::new(functions::math::BinaryIntOp::try_new(self, span, *op)?)
+                    };
                 }
                 // Method call
                 OperatorToken::Dot => todo!("Method call"),
@@ -296,6 +691,63 @@ impl UserFunction {
                 OperatorToken::DotDot => todo!("Range"),
                 _ => return Err(InternalError("Invalid binary operator".into()).with_span(span)),
             },
+            // Vector literal
+            parser::Expr::Vector(components) => {
+                args = Args::from(
+                    components
+                        .iter()
+                        .map(|e| self.build_expression_ast(e))
+                        .collect::<LangResult<Vec<_>>>()?,
+                );
+                function = Box::new(functions::vectors::BuildVector::try_new(span, args.len())?);
+            }
+            // Vector component access
+            parser::Expr::Index { expr, index } => {
+                args = Args::from(vec![
+                    self.build_expression_ast(expr)?,
+                    self.build_expression_ast(index)?,
+                ]);
+                function = Box::new(functions::vectors::IndexVector::try_new(self, span)?);
+            }
+            // Logical NOT
+            parser::Expr::LogicalNot(operand) => {
+                args = Args::from(vec![self.build_expression_ast(operand)?]);
+                function = Box::new(functions::logic::LogicalNot);
+            }
+            // Logical binary operator
+            parser::Expr::LogicalOp { lhs, op, rhs } => {
+                args = Args::from(vec![
+                    self.build_expression_ast(lhs)?,
+                    self.build_expression_ast(rhs)?,
+                ]);
+                function = Box::new(functions::logic::LogicalBinaryOp::try_new(span, *op)?);
+            }
+            // Conditional expression
+            parser::Expr::IfExpr {
+                cond_expr,
+                if_true,
+                if_false,
+            } => {
+                args = Args::from(vec![
+                    self.build_expression_ast(cond_expr)?,
+                    self.build_expression_ast(if_true)?,
+                    self.build_expression_ast(if_false)?,
+                ]);
+                function = Box::new(functions::logic::Conditional::try_new(self, &args)?);
+            }
+            // Function call
+            parser::Expr::FnCall {
+                func,
+                args: arg_exprs,
+            } => {
+                args = Args::from(
+                    arg_exprs
+                        .iter()
+                        .map(|e| self.build_expression_ast(e))
+                        .collect::<LangResult<Vec<_>>>()?,
+                );
+                function = functions::lookup_function(self, span, func)?;
+            }
             // Comparison
             parser::Expr::Cmp { exprs, cmps } => {
                 args = Args::from(
@@ -335,29 +787,126 @@ impl UserFunction {
     }
 
     /// JIT compiles this function and returns an executable function.
-    pub fn compile(&self, compiler: &mut Compiler) -> LangResult<CompiledFunction> {
-        compiler.begin_extern_function(
-            &self.name,
+    ///
+    /// `error_points` is the list of possible runtime errors of helper
+    /// functions that have already been compiled using compile_intern(); the
+    /// error points of this function are appended to it.
+    pub fn compile(
+        &self,
+        compiler: &mut Compiler,
+        mut error_points: Vec<LangError>,
+    ) -> LangResult<CompiledFunction> {
+        // Only the transition function is affected by the rule's symmetry.
+        let symmetry_permutations = if self.is_transition_function {
+            self.rule_meta.symmetry_permutations()
+        } else {
+            vec![]
+        };
+        let extern_fn_name = Self::extern_fn_name(&self.name);
+        if symmetry_permutations.is_empty() {
+            compiler.begin_extern_function(
+                &extern_fn_name,
+                self.return_type(),
+                &self.arg_names,
+                &self.variables,
+                error_points.len(),
+            )?;
+            self.compile_body(compiler)?;
+        } else {
+            // Compile the body under another name, and wrap it in a function
+            // that tries each permutation of the neighborhood.
+            compiler.begin_extern_function(
+                &format!("{}.asymmetric", extern_fn_name),
+                self.return_type(),
+                &self.arg_names,
+                &self.variables,
+                error_points.len(),
+            )?;
+            self.compile_body(compiler)?;
+            compiler.build_symmetry_wrapper(
+                &extern_fn_name,
+                &symmetry_permutations,
+                self.rule_meta.layers,
+            )?;
+        }
+        error_points.extend_from_slice(&self.error_points);
+        CompiledFunction::try_new(
+            self.rule_meta.source_code.clone(),
+            error_points,
+            self.rule_meta.ndim,
+            self.rule_meta.layers,
+            compiler,
+        )
+    }
+    /// JIT compiles this function so that it can be called from other
+    /// functions, offsetting the index of any error that it returns by the
+    /// given amount.
+    pub fn compile_intern(
+        &self,
+        compiler: &mut Compiler,
+        error_index_offset: usize,
+    ) -> LangResult<()> {
+        compiler.begin_intern_function(
+            &Self::intern_fn_name(&self.name),
             self.return_type(),
             &self.arg_names,
             &self.variables,
+            error_index_offset,
         )?;
-
+        self.compile_body(compiler)?;
+        if !compiler.llvm_fn().verify(true) {
+            Err(InternalError(
+                format!("LLVM function for {:?} is invalid", self.name).into(),
+            ))?;
+        }
+        Ok(())
+    }
+    /// Returns the name of the LLVM function built by compile_intern() for the
+    /// user function with the given name.
+    pub fn intern_fn_name(name: &str) -> String {
+        format!("helper_{}", name)
+    }
+    /// Returns the name of the LLVM function built by compile() for the user
+    /// function with the given name, which is also its symbol name in object
+    /// files emitted by Compiler::compile_to_object().
+    pub fn extern_fn_name(name: &str) -> String {
+        format!("ndca_{}", name)
+    }
+    /// Compiles the statements of this function into the function currently
+    /// being built.
+    fn compile_body(&self, compiler: &mut Compiler) -> LangResult<()> {
         // Compile the statements.
         self.compile_statement_block(compiler, &self.top_level_statements)?;
 
         if compiler.needs_terminator() {
-            // If necessary, add an implicit `return #0` at the end of the
-            // transition function. TODO: change this to `remain` once that's
-            // implemented, and handle other types as well.
-            let default_return_value = compiler.get_default_var_value(self.return_type()).unwrap();
-            compiler.build_return_ok(default_return_value)?;
+            if self.is_transition_function {
+                let return_value = match self.rule_meta.default_state {
+                    // If necessary, return the state declared by the
+                    // `@default_state` directive at the end of the transition
+                    // function.
+                    Some(default_state) => compiler
+                        .cell_state_type()
+                        .const_int(default_state as u64, false),
+                    // Otherwise, add an implicit `remain` (e.g. if there is no
+                    // `@transition` directive, or after a loop that never
+                    // exits).
+                    None => {
+                        let center_cell_index = compiler
+                            .int_type()
+                            .const_int(self.rule_meta.center_cell_index() as u64, false);
+                        compiler.build_load_neighbor(center_cell_index)?
+                    }
+                };
+                compiler.build_return_ok(Value::CellState(return_value))?;
+            } else {
+                // If necessary, add an implicit return of the default value at
+                // the end of a helper function.
+                let default_return_value =
+                    compiler.get_default_var_value(self.return_type()).unwrap();
+                compiler.build_return_ok(default_return_value)?;
+            }
         }
-        CompiledFunction::try_new(
-            self.rule_meta.source_code.clone(),
-            self.error_points.clone(),
-            compiler,
-        )
+        Ok(())
     }
 
     /// Compiles a block of statements into LLVM IR, stopping if a terminator
@@ -396,6 +945,42 @@ impl UserFunction {
     }
 }
 
+/// Returns true if every control-flow path through the given block reaches a
+/// statement that exits it.
+pub(super) fn block_always_diverges(block: &parser::StatementBlock) -> bool {
+    block.iter().any(|s| always_diverges(&s.inner))
+}
+
+/// Returns true if control flow can never continue past the given statement
+/// (e.g. because it returns from the function on every path).
+fn always_diverges(statement: &parser::Statement) -> bool {
+    match statement {
+        parser::Statement::Become(_)
+        | parser::Statement::Remain
+        | parser::Statement::Return(_)
+        | parser::Statement::Break
+        | parser::Statement::Continue => true,
+        parser::Statement::If { arms, else_block } => {
+            arms.iter().all(|(_, block)| block_always_diverges(block))
+                && block_always_diverges(else_block)
+        }
+        // A `match` without an `else` arm must cover every cell state.
+        parser::Statement::Match { arms, else_arm, .. } => {
+            arms.iter().all(|(_, block)| block_always_diverges(block))
+                && else_arm.as_ref().map_or(true, block_always_diverges)
+        }
+        // Loops may run zero times, and `break` can exit them.
+        parser::Statement::SetVar { .. }
+        | parser::Statement::SetVars { .. }
+        | parser::Statement::SetVarChain { .. }
+        | parser::Statement::Debug(_)
+        | parser::Statement::Assert { .. }
+        | parser::Statement::For { .. }
+        | parser::Statement::ForNeighbors { .. }
+        | parser::Statement::While { .. } => false,
+    }
+}
+
 /// A newtype of usize that refers to an expression AST node of a user function.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct ExprRef(usize);
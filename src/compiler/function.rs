@@ -4,7 +4,8 @@ use inkwell::execution_engine::JitFunction;
 use std::rc::Rc;
 
 use super::super::errors::*;
-use super::super::{ConstValue, Type};
+use super::super::types::{LangCellState, LangInt};
+use super::super::{ConstValue, Type, NEIGHBORHOOD_RADIUS};
 use super::Compiler;
 use LangErrorMsg::InternalError;
 /// Compiled user function with allocated space for arguments, return value, and
@@ -17,17 +18,27 @@ pub struct CompiledFunction {
     /// Immutable data that is the same, even if this struct is cloned.
     meta: Rc<CompiledFunctionMeta>,
     /// The JIT function to run. (This has an Rc internally.)
-    jit_fn: JitFunction<'static, unsafe extern "C" fn(*mut u8, *mut u8) -> u32>,
+    jit_fn: JitFunction<
+        'static,
+        unsafe extern "C" fn(*mut u8, *mut u8, *const LangCellState, *mut u64) -> u32,
+    >,
     /// Bytes used to store arguments and optionally debug values.
     inout_bytes: Vec<u8>,
     /// Bytes used to store return value.
     out_bytes: Vec<u8>,
+    /// Cell data of the neighborhood, one layer after another, starting with
+    /// cell states.
+    neighborhood: Vec<LangCellState>,
+    /// State of the random number generator.
+    rng_state: u64,
 }
 impl CompiledFunction {
     /// Completes the compilation process and returns a compiled function.
     pub fn try_new(
         source_code: Rc<String>,
         error_points: Vec<LangError>,
+        ndim: u8,
+        layers: usize,
         compiler: &mut Compiler,
     ) -> LangResult<Self> {
         // Make sure that the LLVM code is valid.
@@ -59,20 +70,41 @@ impl CompiledFunction {
             }
         }
         inout_values.sort_by_key(|v| v.byte_offset);
+        // Find the debug trace, which is at the end of the inout struct.
+        let inout_struct_type = compiler.function().inout_struct_type.unwrap();
+        let debug_trace_byte_offset = if compiler.function().debug_trace_ptr.is_some() {
+            let len_element_idx = inout_struct_type.count_fields() - 2;
+            let target_data = compiler.execution_engine.get_target_data();
+            Some((
+                target_data
+                    .offset_of_element(&inout_struct_type, len_element_idx)
+                    .unwrap() as usize,
+                target_data
+                    .offset_of_element(&inout_struct_type, len_element_idx + 1)
+                    .unwrap() as usize,
+            ))
+        } else {
+            None
+        };
+        let debug_value_types = compiler.debug_value_types().to_vec();
         // Allocate space for all the inout values.
         let inout_bytes = vec![
             0u8;
             compiler
                 .execution_engine
                 .get_target_data()
-                .get_store_size(&compiler.function().inout_struct_type.unwrap())
-                as usize
+                .get_store_size(&inout_struct_type) as usize
         ];
 
         // Allocate space for the return value.
         let out_type = compiler.function().return_type;
         let out_bytes = vec![0u8; out_type.size_of().unwrap()];
 
+        // Allocate space for every layer of the neighborhood.
+        let diameter = 2 * NEIGHBORHOOD_RADIUS as usize + 1;
+        let neighborhood_len = diameter.pow(ndim as u32);
+        let neighborhood = vec![0; neighborhood_len * layers];
+
         Ok(Self {
             meta: Rc::new(CompiledFunctionMeta {
                 source_code,
@@ -82,10 +114,17 @@ impl CompiledFunction {
 
                 inout_values,
                 arg_count,
+
+                debug_trace_byte_offset,
+                debug_value_types,
+
+                neighborhood_len,
             }),
             jit_fn,
             inout_bytes,
             out_bytes,
+            neighborhood,
+            rng_state: 0,
         })
     }
 
@@ -101,11 +140,54 @@ impl CompiledFunction {
         }
     }
 
+    /// Sets the cell states of the neighborhood, which must have a length of
+    /// `(2r+1)^ndim` (where `r` is the neighborhood radius) and be ordered with
+    /// the X axis varying fastest. See the `compiler` module for details.
+    ///
+    /// Panics if given a neighborhood of the wrong size.
+    pub fn set_neighborhood(&mut self, cells: &[LangCellState]) {
+        self.set_layer(0, cells);
+    }
+    /// Sets the data of one layer of the neighborhood, declared by the
+    /// `@layers` directive, in the same format as set_neighborhood(). Layer 0
+    /// is the cell states.
+    ///
+    /// Panics if given a neighborhood of the wrong size or a layer that does
+    /// not exist.
+    pub fn set_layer(&mut self, layer: usize, cells: &[LangCellState]) {
+        let len = self.meta.neighborhood_len;
+        if cells.len() != len {
+            panic!("Wrong neighborhood size passed to JIT function");
+        }
+        if (layer + 1) * len > self.neighborhood.len() {
+            panic!("Invalid layer index passed to JIT function");
+        }
+        self.neighborhood[layer * len..(layer + 1) * len].copy_from_slice(cells);
+    }
+
+    /// Seeds the random number generator. Calls with the same seed, arguments,
+    /// and neighborhood produce the same sequence of random numbers. The seed
+    /// defaults to zero.
+    ///
+    /// The state of the random number generator is preserved between calls,
+    /// and cloning this struct also clones the state.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng_state = seed;
+    }
+
     /// Calls this compiled function and returns its return value.
+    ///
+    /// If this is the transition function of a rule with symmetry, the
+    /// function itself tries each permutation of the neighborhood (see
+    /// Compiler::build_symmetry_wrapper()).
     pub fn call(&mut self) -> LangResult<ConstValue> {
         let ret: u32 = unsafe {
-            self.jit_fn
-                .call(self.inout_bytes.as_mut_ptr(), self.out_bytes.as_mut_ptr())
+            self.jit_fn.call(
+                self.inout_bytes.as_mut_ptr(),
+                self.out_bytes.as_mut_ptr(),
+                self.neighborhood.as_ptr(),
+                &mut self.rng_state,
+            )
         };
         if ret == u32::MAX {
             // No error occurred; get the return value from self.out_bytes.
@@ -123,6 +205,23 @@ impl CompiledFunction {
                 .clone())
         }
     }
+    /// Calls this compiled transition function on each of the given
+    /// neighborhoods in turn (see set_neighborhood()) and returns the new
+    /// state of each center cell, stopping at the first error. Other layers
+    /// of the neighborhood are left unchanged between calls.
+    ///
+    /// Panics if given a neighborhood of the wrong size.
+    pub fn call_batch(
+        &mut self,
+        neighborhoods: &[&[LangCellState]],
+    ) -> LangResult<Vec<LangCellState>> {
+        let mut ret = Vec::with_capacity(neighborhoods.len());
+        for cells in neighborhoods {
+            self.set_neighborhood(cells);
+            ret.push(self.call()?.as_cell_state()?);
+        }
+        Ok(ret)
+    }
 
     /// Returns a mutable reference to the raw bytes used for arguments and
     /// debug values.
@@ -133,6 +232,10 @@ impl CompiledFunction {
         &mut self.inout_bytes
     }
 
+    /// Returns the number of cells in the neighborhood.
+    pub fn neighborhood_len(&self) -> usize {
+        self.meta.neighborhood_len
+    }
     /// Returns the number of argument that this function takes.
     pub fn arg_count(&self) -> usize {
         self.meta.arg_count
@@ -142,6 +245,54 @@ impl CompiledFunction {
     pub fn value_count(&self) -> usize {
         self.meta.inout_values.len()
     }
+    /// Returns the name and current value of every in/out value of this
+    /// function (including arguments).
+    ///
+    /// If the function was compiled in debug mode (see
+    /// Compiler::with_debug_mode()), this includes every variable, holding its
+    /// value at the end of the most recent call.
+    pub fn vars(&self) -> Vec<(String, ConstValue)> {
+        self.meta
+            .inout_values
+            .iter()
+            .map(|value| {
+                let start = value.byte_offset;
+                let end = start + value.ty.size_of().unwrap();
+                let bytes = &self.inout_bytes[start..end];
+                (value.name.clone(), ConstValue::from_bytes(value.ty, bytes))
+            })
+            .collect()
+    }
+    /// Returns the value of each `debug` statement executed during the most
+    /// recent call, in order.
+    ///
+    /// This is always empty unless the function was compiled in debug mode
+    /// (see Compiler::with_debug_mode()). Values that do not fit in the debug
+    /// trace (see DEBUG_TRACE_CAPACITY) are dropped.
+    pub fn debug_values(&self) -> Vec<ConstValue> {
+        let (len_offset, trace_offset) = match self.meta.debug_trace_byte_offset {
+            Some(offsets) => offsets,
+            None => return vec![],
+        };
+        let word_at = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&self.inout_bytes[offset..offset + 8]);
+            LangInt::from_ne_bytes(bytes)
+        };
+        let len = word_at(len_offset) as usize;
+        let mut words = (0..len).map(|i| word_at(trace_offset + i * 8));
+        let mut ret = vec![];
+        while let Some(entry_idx) = words.next() {
+            let value = match self.meta.debug_value_types[entry_idx as usize] {
+                Type::Int => ConstValue::Int(words.next().unwrap()),
+                Type::Bool => ConstValue::Bool(words.next().unwrap() != 0),
+                Type::CellState => ConstValue::CellState(words.next().unwrap() as LangCellState),
+                Type::Vector(len) => ConstValue::Vector(words.by_ref().take(len).collect()),
+            };
+            ret.push(value);
+        }
+        ret
+    }
     /// Returns a mutable reference to an in/out value of this function.
     pub fn value_mut<'a>(&'a mut self, idx: usize) -> InOutValueMut<'a> {
         let value = self
@@ -175,6 +326,15 @@ struct CompiledFunctionMeta {
     inout_values: Vec<InOutValue>,
     /// The number of arguments.
     arg_count: usize,
+
+    /// Byte offsets in inout_bytes of the length of the debug trace and the
+    /// debug trace itself, if the function was compiled in debug mode.
+    debug_trace_byte_offset: Option<(usize, usize)>,
+    /// Type of the value recorded by each `debug` statement.
+    debug_value_types: Vec<Type>,
+
+    /// The number of cells in each layer of the neighborhood.
+    neighborhood_len: usize,
 }
 impl CompiledFunctionMeta {
     fn arg_values(&self) -> &[InOutValue] {
@@ -16,17 +16,33 @@
 //! Actual function arguments only matter as inputs, but when debugging a
 //! function we can pass variable values as "in/out" values, and read the value
 //! after executing part of the function.
+//!
+//! The third argument is a pointer to the cell states of the neighborhood,
+//! stored as a flat array of `LangCellState` with a length of `(2r+1)^ndim`
+//! (where `r` is the neighborhood radius). The X axis varies fastest, so in 2D
+//! the cell at offset `(dx, dy)` is at index `(dy+r) * (2r+1) + (dx+r)`. If the
+//! rule declares more than one layer of cell data using the `@layers`
+//! directive, each additional layer follows immediately after the previous one
+//! in the same format.
+//!
+//! The fourth argument is a pointer to the 64-bit state of the pseudorandom
+//! number generator, which is advanced each time a random number is generated.
+//! Seeding it with the same value gives the same sequence of random numbers.
 
 use std::collections::HashMap;
+use std::path::Path;
 use thread_local::ThreadLocal;
 
 use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::execution_engine::{ExecutionEngine, JitFunction, UnsafeFunctionPointer};
-use inkwell::module::Module;
+use inkwell::module::{Linkage, Module};
+use inkwell::targets::{
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+};
 use inkwell::types::{BasicType, BasicTypeEnum, FunctionType, IntType, StructType, VectorType};
-use inkwell::values::{FunctionValue, IntValue, PointerValue};
+use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
 use inkwell::{AddressSpace, IntPredicate, OptimizationLevel};
 
 mod function;
@@ -37,14 +53,19 @@ pub use value::Value;
 
 use super::errors::*;
 use super::types::{CELL_STATE_BITS, INT_BITS};
-use super::{ConstValue, Type};
+use super::{ConstValue, Type, NEIGHBORHOOD_RADIUS};
 use LangErrorMsg::InternalError;
 
 /// Name of the LLVM module.
 const MODULE_NAME: &'static str = "ndca";
 
-/// Whether to enable debug mode. TODO: move this to CompilerConfig
-const DEBUG_MODE: bool = false;
+/// Default maximum number of loop iterations that a single function call may
+/// execute before returning an error.
+pub const DEFAULT_STEP_LIMIT: u64 = 10_000_000;
+
+/// Number of 64-bit words in the buffer that records the values of `debug`
+/// statements in debug mode. Values that do not fit are dropped.
+pub const DEBUG_TRACE_CAPACITY: usize = 4096;
 
 lazy_static! {
     /// Per-thread LLVM context.
@@ -69,6 +90,15 @@ pub struct Compiler {
     execution_engine: ExecutionEngine<'static>,
     /// Function currently being built.
     function: Option<FunctionInProgress>,
+    /// Maximum number of loop iterations that a single function call may
+    /// execute before returning an error, if set using with_step_limit().
+    step_limit: Option<u64>,
+    /// Step limit declared by the rule being compiled, which is used if no
+    /// step limit is set using with_step_limit().
+    rule_step_limit: Option<u64>,
+    /// Whether to store all variables as in/out values, so that they can be
+    /// inspected after calling a function.
+    debug_mode: bool,
 }
 impl Compiler {
     /// Constructs a new compiler with a blank module and "main" function.
@@ -87,55 +117,166 @@ impl Compiler {
             module,
             execution_engine,
             function: None,
+            step_limit: None,
+            rule_step_limit: None,
+            debug_mode: false,
         })
     }
+    /// Sets the maximum number of loop iterations that a single call to any
+    /// function compiled afterwards may execute before returning an error.
+    ///
+    /// This takes precedence over any step limit declared by a rule.
+    pub fn with_step_limit(mut self, step_limit: u64) -> Self {
+        self.step_limit = Some(step_limit);
+        self
+    }
+    /// Sets the step limit declared by the rule whose functions are compiled
+    /// afterwards, or None to use the default. This is ignored if a step limit
+    /// has been set using with_step_limit().
+    pub fn set_rule_step_limit(&mut self, rule_step_limit: Option<u64>) {
+        self.rule_step_limit = rule_step_limit;
+    }
+    /// Sets whether to enable debug mode for functions compiled afterwards.
+    ///
+    /// In debug mode, every variable of a function that can be called from
+    /// Rust code is stored as an in/out value rather than on the stack, so its
+    /// value can be inspected after calling the function (see
+    /// CompiledFunction::vars()). The value of each `debug` statement executed
+    /// is also recorded (see CompiledFunction::debug_values()).
+    pub fn with_debug_mode(mut self, debug_mode: bool) -> Self {
+        self.debug_mode = debug_mode;
+        self
+    }
+
+    /// Returns the LLVM function with the given name that can be called only
+    /// from LLVM, declaring it if it has not yet been declared.
+    ///
+    /// The function takes each of its arguments by value, followed by a pointer
+    /// to the neighborhood cell states and a pointer to the random number
+    /// generator state, and returns a struct containing the error index (see
+    /// get_llvm_return_type()) and the actual return value.
+    pub fn get_or_declare_intern_function(
+        &mut self,
+        name: &str,
+        return_type: Type,
+        arg_types: &[Type],
+    ) -> LangResult<FunctionValue<'static>> {
+        if let Some(llvm_fn) = self.module.get_function(name) {
+            return Ok(llvm_fn);
+        }
+        // Determine the LLVM function type (signature).
+        let llvm_return_type = get_ctx().struct_type(
+            &[
+                self.get_llvm_return_type().into(),
+                self.get_llvm_type(return_type)?,
+            ],
+            false,
+        );
+        let mut llvm_arg_types = arg_types
+            .iter()
+            .map(|&ty| self.get_llvm_type(ty))
+            .collect::<LangResult<Vec<_>>>()?;
+        llvm_arg_types.push(
+            self.cell_state_type()
+                .ptr_type(AddressSpace::Generic)
+                .as_basic_type_enum(),
+        );
+        llvm_arg_types.push(
+            self.int_type()
+                .ptr_type(AddressSpace::Generic)
+                .as_basic_type_enum(),
+        );
+        let fn_type = llvm_return_type.fn_type(&llvm_arg_types, false);
+        Ok(self.module.add_function(name, fn_type, None))
+    }
 
     /// Begins building a new LLVM function that can be called only from LLVM,
     /// initializing variables and positioning the instruction pointer
     /// accordingly.
+    ///
+    /// Any error index returned from this function is offset by the given
+    /// amount.
     pub fn begin_intern_function(
         &mut self,
         name: &str,
         return_type: Type,
         arg_names: &[String],
         var_types: &HashMap<String, Type>,
+        error_index_offset: usize,
     ) -> LangResult<()> {
-        // Determine the LLVM function type (signature).
-        let llvm_return_type = self.get_llvm_type(return_type)?;
-        let llvm_arg_types = arg_names
-            .iter()
-            .map(|name| self.get_llvm_type(var_types[name]))
-            .collect::<LangResult<Vec<_>>>()?;
-        let fn_type = llvm_return_type.fn_type(&llvm_arg_types, false);
+        let arg_types: Vec<Type> = arg_names.iter().map(|name| var_types[name]).collect();
+        let llvm_fn = self.get_or_declare_intern_function(name, return_type, &arg_types)?;
         // Construct the FunctionInProgress.
         self.function = Some(FunctionInProgress {
-            llvm_fn: self.module.add_function(name, fn_type, None),
+            llvm_fn,
             builder: get_ctx().create_builder(),
 
             return_type,
             return_value_ptr: None,
+            error_index_offset,
 
             inout_struct_type: None,
+            neighborhood_ptr: None,
+            rng_state_ptr: None,
+            step_count_ptr: None,
+            debug_trace_len_ptr: None,
+            debug_trace_ptr: None,
+            debug_value_types: vec![],
             vars_by_name: HashMap::new(),
+            loops: vec![],
         });
+        let entry_bb = self.append_basic_block("entry");
+        self.builder().position_at_end(entry_bb);
+        self.alloca_step_counter();
+
+        // The last two parameters are pointers to the neighborhood and the
+        // random number generator state.
+        self.function_mut().neighborhood_ptr = Some(
+            self.llvm_fn()
+                .get_nth_param(arg_names.len() as u32)
+                .unwrap()
+                .into_pointer_value(),
+        );
+        self.function_mut().rng_state_ptr = Some(
+            self.llvm_fn()
+                .get_nth_param(arg_names.len() as u32 + 1)
+                .unwrap()
+                .into_pointer_value(),
+        );
+
         // Allocate and initialize variables and add them to the HashMap of all
         // variables.
         for (name, &ty) in var_types {
             let var = self.alloca_and_init_var(name.clone(), ty)?;
             self.function_mut().vars_by_name.insert(name.clone(), var);
         }
+        // Store the arguments in their variables.
+        for (idx, name) in arg_names.iter().enumerate() {
+            let param = self.llvm_fn().get_nth_param(idx as u32).unwrap();
+            let ptr = self.vars()[name].ptr;
+            self.builder().build_store(ptr, param);
+            self.function_mut()
+                .vars_by_name
+                .get_mut(name)
+                .unwrap()
+                .is_arg = true;
+        }
 
         Ok(())
     }
     /// Begins building a new LLVM function that can be called from Rust code,
     /// initializing variables and positioning the instruction builder
     /// accordingly.
+    ///
+    /// Any error index returned from this function is offset by the given
+    /// amount.
     pub fn begin_extern_function(
         &mut self,
         name: &str,
         return_type: Type,
         arg_names: &[String],
         var_types: &HashMap<String, Type>,
+        error_index_offset: usize,
     ) -> LangResult<()> {
         // TODO: maybe sort variables (and arguments?) by alignment to reduce
         // unnecessary padding
@@ -143,7 +284,7 @@ impl Compiler {
         let mut alloca_var_names: Vec<&String> = vec![];
         for (name, _ty) in var_types {
             if !arg_names.contains(name) {
-                if DEBUG_MODE {
+                if self.debug_mode {
                     inout_var_names.push(name);
                 } else {
                     alloca_var_names.push(name);
@@ -154,11 +295,18 @@ impl Compiler {
         // Determine the LLVM function type (signature).
         // The first parameter is a pointer to a struct containing all of the
         // inout parameters.
-        let inout_var_types = inout_var_names
+        let mut inout_var_types = inout_var_names
             .iter()
             .map(|&name| var_types[name])
             .map(|ty| self.get_llvm_type(ty))
             .collect::<LangResult<Vec<_>>>()?;
+        // In debug mode, the struct ends with the length of the debug trace
+        // followed by the debug trace itself.
+        if self.debug_mode {
+            let int_type = self.int_type();
+            inout_var_types.push(int_type.into());
+            inout_var_types.push(int_type.array_type(DEBUG_TRACE_CAPACITY as u32).into());
+        }
         let inout_struct_type = get_ctx().struct_type(&inout_var_types, false);
         let inout_struct_ptr_type = inout_struct_type
             .ptr_type(AddressSpace::Generic)
@@ -168,10 +316,27 @@ impl Compiler {
             .get_llvm_type(return_type)?
             .ptr_type(AddressSpace::Generic)
             .as_basic_type_enum();
+        // The third parameter is a pointer to the neighborhood cell states.
+        let neighborhood_ptr_type = self
+            .cell_state_type()
+            .ptr_type(AddressSpace::Generic)
+            .as_basic_type_enum();
+        // The fourth parameter is a pointer to the random number generator
+        // state.
+        let rng_state_ptr_type = self
+            .int_type()
+            .ptr_type(AddressSpace::Generic)
+            .as_basic_type_enum();
         // The actual LLVM return value just signals whether there was an error.
-        let fn_type = self
-            .get_llvm_return_type()
-            .fn_type(&[inout_struct_ptr_type, return_ptr_type], false);
+        let fn_type = self.get_llvm_return_type().fn_type(
+            &[
+                inout_struct_ptr_type,
+                return_ptr_type,
+                neighborhood_ptr_type,
+                rng_state_ptr_type,
+            ],
+            false,
+        );
 
         // Construct the FunctionInProgress.
         self.function = Some(FunctionInProgress {
@@ -180,12 +345,21 @@ impl Compiler {
 
             return_type,
             return_value_ptr: None,
+            error_index_offset,
 
             inout_struct_type: Some(inout_struct_type),
+            neighborhood_ptr: None,
+            rng_state_ptr: None,
+            step_count_ptr: None,
+            debug_trace_len_ptr: None,
+            debug_trace_ptr: None,
+            debug_value_types: vec![],
             vars_by_name: HashMap::new(),
+            loops: vec![],
         });
         let entry_bb = self.append_basic_block("entry");
         self.builder().position_at_end(entry_bb);
+        self.alloca_step_counter();
 
         // Get pointers to the arguments.
         let shared_data_ptr = self
@@ -199,6 +373,18 @@ impl Compiler {
                 .unwrap()
                 .into_pointer_value(),
         );
+        self.function_mut().neighborhood_ptr = Some(
+            self.llvm_fn()
+                .get_nth_param(2)
+                .unwrap()
+                .into_pointer_value(),
+        );
+        self.function_mut().rng_state_ptr = Some(
+            self.llvm_fn()
+                .get_nth_param(3)
+                .unwrap()
+                .into_pointer_value(),
+        );
 
         // Add inout variables to the HashMap of all variables.
         for (element_idx, &name) in inout_var_names.iter().enumerate() {
@@ -225,6 +411,29 @@ impl Compiler {
                     inout_byte_offset: Some(byte_offset),
                 },
             );
+            // Initialize variables that are not arguments to a default value,
+            // so that values from a previous call do not leak into this one.
+            if !arg_names.contains(name) {
+                let ty = var_types[name];
+                let default_value = self.get_default_var_value(ty).unwrap().into_basic_value()?;
+                self.builder().build_store(ptr, default_value);
+            }
+        }
+        // Get pointers to the debug trace and clear it.
+        if self.debug_mode {
+            let element_idx = inout_var_names.len() as u32;
+            let len_ptr = self
+                .builder()
+                .build_struct_gep(shared_data_ptr, element_idx, "debugTraceLen")
+                .unwrap();
+            let trace_ptr = self
+                .builder()
+                .build_struct_gep(shared_data_ptr, element_idx + 1, "debugTrace")
+                .unwrap();
+            let zero = self.int_type().const_zero();
+            self.builder().build_store(len_ptr, zero);
+            self.function_mut().debug_trace_len_ptr = Some(len_ptr);
+            self.function_mut().debug_trace_ptr = Some(trace_ptr);
         }
         // Allocate and initialize alloca'd variables and add them to the
         // HashMap of all variables.
@@ -236,6 +445,204 @@ impl Compiler {
 
         Ok(())
     }
+    /// Finishes the function currently being built, which must be callable
+    /// from Rust code, and begins building a function with the given name and
+    /// the same signature that applies the rule's symmetry by calling it on
+    /// each of the given permutations of the neighborhood in turn (see
+    /// RuleMeta::symmetry_permutations()), until one returns something other
+    /// than the state of the center cell. Every layer of the neighborhood is
+    /// permuted the same way.
+    ///
+    /// This is built into the function itself so that functions written to an
+    /// object file using compile_to_object() also respect the symmetry.
+    pub fn build_symmetry_wrapper(
+        &mut self,
+        name: &str,
+        permutations: &[Vec<usize>],
+        layers: usize,
+    ) -> LangResult<()> {
+        let inner_fn = self.llvm_fn();
+        if !inner_fn.verify(true) {
+            Err(InternalError(
+                "LLVM function is invalid! This is a big problem".into(),
+            ))?;
+        }
+        inner_fn.set_linkage(Linkage::Private);
+        let wrapper_fn = self.module.add_function(name, inner_fn.get_type(), None);
+        self.function_mut().llvm_fn = wrapper_fn;
+        let entry_bb = self.append_basic_block("entry");
+        self.builder().position_at_end(entry_bb);
+
+        let int_type = self.int_type();
+        let zero = int_type.const_zero();
+        let one = int_type.const_int(1, false);
+        let neighborhood_len = permutations[0].len();
+        let len = int_type.const_int(neighborhood_len as u64, false);
+        let total_len = int_type.const_int((neighborhood_len * layers) as u64, false);
+        let permutation_count = int_type.const_int(permutations.len() as u64, false);
+        let ok_status = self.get_llvm_return_type().const_int(u64::MAX, true);
+
+        // Get the parameters, which are passed through to the inner function
+        // except for the neighborhood.
+        let params = wrapper_fn.get_params();
+        let return_value_ptr = params[1].into_pointer_value();
+        let neighborhood_ptr = params[2].into_pointer_value();
+        let cell_state_type = self.cell_state_type();
+        let permuted_ptr =
+            self.builder()
+                .build_array_alloca(cell_state_type, total_len, "permutedNeighborhood");
+        let flat_permutations: Vec<u64> =
+            permutations.iter().flatten().map(|&i| i as u64).collect();
+        let permutations_ptr =
+            self.build_const_int_array("symmetryPermutations", &flat_permutations);
+        let center_ptr = unsafe {
+            self.builder().build_in_bounds_gep(
+                neighborhood_ptr,
+                &[int_type.const_int(neighborhood_len as u64 / 2, false)],
+                "centerPtr",
+            )
+        };
+        let center = self
+            .builder()
+            .build_load(center_ptr, "centerCellState")
+            .into_int_value();
+
+        // Build the destination blocks.
+        let permutation_cond_bb = self.append_basic_block("symmetryCond");
+        let permute_cond_bb = self.append_basic_block("permuteCond");
+        let permute_body_bb = self.append_basic_block("permuteBody");
+        let call_bb = self.append_basic_block("callSymmetric");
+        let check_bb = self.append_basic_block("checkSymmetricResult");
+        let return_bb = self.append_basic_block("returnSymmetricResult");
+        let next_permutation_bb = self.append_basic_block("symmetryNext");
+        let end_bb = self.append_basic_block("endSymmetry");
+        self.builder()
+            .build_unconditional_branch(permutation_cond_bb);
+
+        // Loop over the permutations.
+        self.builder().position_at_end(permutation_cond_bb);
+        let permutation_idx = self.builder().build_phi(int_type, "permutationIdx");
+        let permutation_idx_value = permutation_idx.as_basic_value().into_int_value();
+        let is_in_range = self.builder().build_int_compare(
+            IntPredicate::ULT,
+            permutation_idx_value,
+            permutation_count,
+            "isInRange",
+        );
+        let permutation_start =
+            self.builder()
+                .build_int_mul(permutation_idx_value, len, "permutationStart");
+        self.builder()
+            .build_conditional_branch(is_in_range, permute_cond_bb, end_bb);
+
+        // Loop over the cells of every layer of the neighborhood, storing each
+        // one in its permuted position.
+        self.builder().position_at_end(permute_cond_bb);
+        let cell_idx = self.builder().build_phi(int_type, "cellIdx");
+        let cell_idx_value = cell_idx.as_basic_value().into_int_value();
+        let is_in_range = self.builder().build_int_compare(
+            IntPredicate::ULT,
+            cell_idx_value,
+            total_len,
+            "isInRange",
+        );
+        self.builder()
+            .build_conditional_branch(is_in_range, permute_body_bb, call_bb);
+
+        self.builder().position_at_end(permute_body_bb);
+        let idx_in_layer = self
+            .builder()
+            .build_int_unsigned_rem(cell_idx_value, len, "idxInLayer");
+        let layer_start = self
+            .builder()
+            .build_int_sub(cell_idx_value, idx_in_layer, "layerStart");
+        let permutation_element_idx =
+            self.builder()
+                .build_int_add(permutation_start, idx_in_layer, "permutationElementIdx");
+        let source_idx_in_layer =
+            self.build_load_const_array_element(permutations_ptr, permutation_element_idx);
+        let source_idx =
+            self.builder()
+                .build_int_add(layer_start, source_idx_in_layer, "sourceIdx");
+        let (source_ptr, dest_ptr) = unsafe {
+            (
+                self.builder()
+                    .build_in_bounds_gep(neighborhood_ptr, &[source_idx], "sourcePtr"),
+                self.builder()
+                    .build_in_bounds_gep(permuted_ptr, &[cell_idx_value], "destPtr"),
+            )
+        };
+        let cell = self.builder().build_load(source_ptr, "cell");
+        self.builder().build_store(dest_ptr, cell);
+        let next_cell_idx = self
+            .builder()
+            .build_int_add(cell_idx_value, one, "nextCellIdx");
+        self.builder().build_unconditional_branch(permute_cond_bb);
+        cell_idx.add_incoming(&[
+            (&zero, permutation_cond_bb),
+            (&next_cell_idx, permute_body_bb),
+        ]);
+
+        // Call the inner function on the permuted neighborhood.
+        self.builder().position_at_end(call_bb);
+        let args: Vec<BasicValueEnum<'static>> =
+            vec![params[0], params[1], permuted_ptr.into(), params[3]];
+        let status = self
+            .builder()
+            .build_call(inner_fn, &args, "status")
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                InternalError("Function call did not return a value".into()).without_span()
+            })?
+            .into_int_value();
+        let is_ok = self
+            .builder()
+            .build_int_compare(IntPredicate::EQ, status, ok_status, "isOk");
+        self.builder()
+            .build_conditional_branch(is_ok, check_bb, return_bb);
+
+        // Return any error, or any result other than the center cell state.
+        self.builder().position_at_end(check_bb);
+        let result = self
+            .builder()
+            .build_load(return_value_ptr, "result")
+            .into_int_value();
+        let is_unchanged =
+            self.builder()
+                .build_int_compare(IntPredicate::EQ, result, center, "isUnchanged");
+        self.builder()
+            .build_conditional_branch(is_unchanged, next_permutation_bb, return_bb);
+        self.builder().position_at_end(return_bb);
+        self.builder().build_return(Some(&status));
+
+        // Move on to the next permutation.
+        self.builder().position_at_end(next_permutation_bb);
+        let next_permutation_idx =
+            self.builder()
+                .build_int_add(permutation_idx_value, one, "nextPermutationIdx");
+        self.builder()
+            .build_unconditional_branch(permutation_cond_bb);
+        permutation_idx.add_incoming(&[
+            (&zero, entry_bb),
+            (&next_permutation_idx, next_permutation_bb),
+        ]);
+
+        // Every permutation left the center cell unchanged.
+        self.builder().position_at_end(end_bb);
+        self.builder().build_store(return_value_ptr, center);
+        self.builder().build_return(Some(&ok_status));
+
+        Ok(())
+    }
+    /// Allocate space on the stack for the loop step counter and initialize it
+    /// to zero.
+    fn alloca_step_counter(&mut self) {
+        let int_type = self.int_type();
+        let ptr = self.builder().build_alloca(int_type, "stepCount");
+        self.builder().build_store(ptr, int_type.const_zero());
+        self.function_mut().step_count_ptr = Some(ptr);
+    }
     /// Allocate space on the stack for the given variable and initialize it to a default value.
     fn alloca_and_init_var(&mut self, name: String, ty: Type) -> LangResult<Variable> {
         let llvm_type = self.get_llvm_type(ty)?;
@@ -269,10 +676,68 @@ impl Compiler {
         })
     }
 
+    /// Returns the textual LLVM IR of every function compiled so far.
+    pub fn llvm_ir(&self) -> String {
+        self.module.print_to_string().to_string_lossy().into_owned()
+    }
+    /// Returns the textual LLVM IR of the function currently being built (or
+    /// the one most recently built).
+    pub fn llvm_fn_ir(&self) -> String {
+        self.llvm_fn()
+            .print_to_string()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Writes every function compiled so far to a native object file at the
+    /// given path, so that it can be linked without keeping an LLVM context
+    /// alive.
+    ///
+    /// If no target triple is given, the host's target triple is used. Each
+    /// function that can be called from Rust code keeps the signature
+    /// described in the module documentation; see
+    /// Rule::transition_function_symbol() for the name of the transition
+    /// function, which applies the rule's symmetry itself (see
+    /// build_symmetry_wrapper()).
+    pub fn compile_to_object(&self, path: &Path, target_triple: Option<&str>) -> LangResult<()> {
+        Target::initialize_all(&InitializationConfig::default());
+        let triple = match target_triple {
+            Some(s) => TargetTriple::create(s),
+            None => TargetMachine::get_default_triple(),
+        };
+        let target = Target::from_triple(&triple).map_err(|e| {
+            InternalError(format!("Unknown target {:?}: {}", triple, e.to_string_lossy()).into())
+                .without_span()
+        })?;
+        let target_machine = target
+            .create_target_machine(
+                &triple,
+                "generic",
+                "",
+                OptimizationLevel::Default,
+                RelocMode::PIC,
+                CodeModel::Default,
+            )
+            .ok_or_else(|| {
+                InternalError(format!("Error creating target machine for {:?}", triple).into())
+                    .without_span()
+            })?;
+        target_machine
+            .write_to_file(&self.module, FileType::Object, path)
+            .map_err(|e| {
+                InternalError(format!("Error writing object file: {}", e.to_string_lossy()).into())
+                    .without_span()
+            })
+    }
+
     /// Returns the LLVM type used to represent an integer.
     pub fn int_type(&self) -> IntType<'static> {
         get_ctx().custom_width_int_type(INT_BITS)
     }
+    /// Returns the LLVM type used to represent a boolean.
+    pub fn bool_type(&self) -> IntType<'static> {
+        get_ctx().bool_type()
+    }
     /// Returns the LLVM type used to represent a cell state.
     pub fn cell_state_type(&self) -> IntType<'static> {
         get_ctx().custom_width_int_type(CELL_STATE_BITS)
@@ -293,6 +758,23 @@ impl Compiler {
     pub fn builder(&mut self) -> &Builder<'static> {
         &self.function().builder
     }
+    /// Returns a pointer to the first cell state of the neighborhood, or an
+    /// InternalError if the current function has no access to the
+    /// neighborhood.
+    pub fn neighborhood_ptr(&self) -> LangResult<PointerValue<'static>> {
+        self.function().neighborhood_ptr.ok_or_else(|| {
+            InternalError("Function being built has no access to the neighborhood".into())
+                .without_span()
+        })
+    }
+    /// Returns a pointer to the state of the random number generator, or an
+    /// InternalError if the current function has no access to it.
+    pub fn rng_state_ptr(&self) -> LangResult<PointerValue<'static>> {
+        self.function().rng_state_ptr.ok_or_else(|| {
+            InternalError("Function being built has no random number generator".into())
+                .without_span()
+        })
+    }
     /// Returns a HashMap of variables, indexed by name.
     pub fn vars(&self) -> &HashMap<String, Variable> {
         &self.function().vars_by_name
@@ -374,20 +856,155 @@ impl Compiler {
         Ok(())
     }
 
+    /// Builds the body of a loop, in which `continue` jumps to `continue_bb`
+    /// and `break` jumps to `break_bb`.
+    pub fn build_loop_body(
+        &mut self,
+        continue_bb: BasicBlock<'static>,
+        break_bb: BasicBlock<'static>,
+        build_body: impl FnOnce(&mut Self) -> LangResult<()>,
+    ) -> LangResult<()> {
+        self.function_mut().loops.push(Loop {
+            continue_bb,
+            break_bb,
+        });
+        let ret = build_body(self);
+        self.function_mut().loops.pop();
+        ret
+    }
+    /// Builds instructions to count one loop iteration, calling
+    /// `on_limit_exceeded` if the step limit has been exceeded.
+    pub fn build_step(
+        &mut self,
+        on_limit_exceeded: impl FnOnce(&mut Self) -> LangResult<()>,
+    ) -> LangResult<()> {
+        let ptr = self.function().step_count_ptr.ok_or_else(|| {
+            InternalError("Function being built has no step counter".into()).without_span()
+        })?;
+        let int_type = self.int_type();
+        let step_count = self.builder().build_load(ptr, "stepCount").into_int_value();
+        let next_step_count =
+            self.builder()
+                .build_int_add(step_count, int_type.const_int(1, false), "nextStepCount");
+        self.builder().build_store(ptr, next_step_count);
+        let limit = self
+            .step_limit
+            .or(self.rule_step_limit)
+            .unwrap_or(DEFAULT_STEP_LIMIT);
+        let limit = int_type.const_int(limit, false);
+        let is_limit_exceeded = self.builder().build_int_compare(
+            IntPredicate::UGT,
+            next_step_count,
+            limit,
+            "isStepLimitExceeded",
+        );
+        self.build_conditional(is_limit_exceeded, on_limit_exceeded, |_| Ok(()))
+    }
+    /// Builds instructions to exit the innermost loop.
+    pub fn build_break(&mut self) -> LangResult<()> {
+        let break_bb = self.innermost_loop()?.break_bb;
+        self.builder().build_unconditional_branch(break_bb);
+        Ok(())
+    }
+    /// Builds instructions to skip to the next iteration of the innermost
+    /// loop.
+    pub fn build_continue(&mut self) -> LangResult<()> {
+        let continue_bb = self.innermost_loop()?.continue_bb;
+        self.builder().build_unconditional_branch(continue_bb);
+        Ok(())
+    }
+    /// Returns the innermost loop, or an InternalError if there is none.
+    fn innermost_loop(&self) -> LangResult<Loop> {
+        self.function().loops.last().copied().ok_or_else(|| {
+            InternalError("Loop control statement outside of loop".into()).without_span()
+        })
+    }
+
     /// Builds instructions to return a value.
     pub fn build_return_ok(&mut self, value: Value) -> LangResult<()> {
-        let ptr = self.function().return_value_ptr.unwrap();
-        self.builder().build_store(ptr, value.into_basic_value()?);
         let llvm_return_value = self.get_llvm_return_type().const_int(u64::MAX, true);
-        self.builder().build_return(Some(&llvm_return_value));
-        Ok(())
+        self.build_return_with_status(llvm_return_value, Some(value))
     }
     /// Builds instructions to return an error.
     pub fn build_return_err(&mut self, error_index: usize) {
+        let error_index = error_index + self.function().error_index_offset;
         let llvm_return_value = self
             .get_llvm_return_type()
             .const_int(error_index as u64, false);
-        self.builder().build_return(Some(&llvm_return_value));
+        self.build_return_with_status(llvm_return_value, None)
+            .expect("Failed to build return of error");
+    }
+    /// Builds instructions to return the given error index (see
+    /// get_llvm_return_type()) and optionally a value.
+    fn build_return_with_status(
+        &mut self,
+        status: IntValue<'static>,
+        value: Option<Value>,
+    ) -> LangResult<()> {
+        if let Some(ptr) = self.function().return_value_ptr {
+            // This function can be called from Rust code, so store the return
+            // value and then return the status.
+            if let Some(value) = value {
+                self.builder().build_store(ptr, value.into_basic_value()?);
+            }
+            self.builder().build_return(Some(&status));
+        } else {
+            // This function can only be called from LLVM, so return the status
+            // and the return value together.
+            let value = match value {
+                Some(v) => v,
+                None => self
+                    .get_default_var_value(self.function().return_type)
+                    .unwrap(),
+            };
+            self.builder()
+                .build_aggregate_return(&[status.into(), value.into_basic_value()?]);
+        }
+        Ok(())
+    }
+    /// Builds instructions to call a function built using
+    /// begin_intern_function(), returning an error from the current function
+    /// if the callee returns one.
+    pub fn build_intern_call(
+        &mut self,
+        llvm_fn: FunctionValue<'static>,
+        return_type: Type,
+        args: &[BasicValueEnum<'static>],
+    ) -> LangResult<Value> {
+        let mut llvm_args = args.to_vec();
+        llvm_args.push(self.neighborhood_ptr()?.into());
+        llvm_args.push(self.rng_state_ptr()?.into());
+        let call_result = self
+            .builder()
+            .build_call(llvm_fn, &llvm_args, "call")
+            .try_as_basic_value()
+            .left()
+            .ok_or_else(|| {
+                InternalError("Function call did not return a value".into()).without_span()
+            })?
+            .into_struct_value();
+        let status = self
+            .builder()
+            .build_extract_value(call_result, 0, "callStatus")
+            .unwrap()
+            .into_int_value();
+        let return_value = self
+            .builder()
+            .build_extract_value(call_result, 1, "callReturnValue")
+            .unwrap();
+
+        // If the callee returned an error, return that same error.
+        let ok_status = self.get_llvm_return_type().const_int(u64::MAX, true);
+        let is_err =
+            self.builder()
+                .build_int_compare(IntPredicate::NE, status, ok_status, "isCallErr");
+        self.build_conditional(
+            is_err,
+            |c| c.build_return_with_status(status, None),
+            |_| Ok(()),
+        )?;
+
+        Ok(Value::from_basic_value(return_type, return_value))
     }
 
     /// Builds instructions to perform checked integer arithmetic using an LLVM
@@ -399,6 +1016,29 @@ impl Compiler {
         name: &str,
         on_overflow: impl FnOnce(&mut Self) -> LangResult<()>,
     ) -> LangResult<IntValue<'static>> {
+        let (result_value, is_overflow) =
+            self.build_int_arithmetic_with_overflow(lhs, rhs, name)?;
+
+        // Branch based on whether there is overflow.
+        self.build_conditional(
+            is_overflow,
+            // Return an error if there is overflow.
+            on_overflow,
+            // Otherwise proceed.
+            |_| Ok(()),
+        )?;
+
+        Ok(result_value)
+    }
+    /// Builds instructions to perform integer arithmetic using an LLVM
+    /// intrinsic and returns the result along with a boolean value that is
+    /// true if overflow occurred.
+    pub fn build_int_arithmetic_with_overflow(
+        &mut self,
+        lhs: IntValue<'static>,
+        rhs: IntValue<'static>,
+        name: &str,
+    ) -> LangResult<(IntValue<'static>, IntValue<'static>)> {
         let intrinsic_name = format!(
             "llvm.{}.with.overflow.i{}",
             name,
@@ -440,16 +1080,300 @@ impl Compiler {
             .unwrap()
             .into_int_value();
 
-        // Branch based on whether there is overflow.
-        self.build_conditional(
-            is_overflow,
-            // Return an error if there is overflow.
-            on_overflow,
-            // Otherwise proceed.
-            |_| Ok(()),
-        )?;
+        Ok((result_value, is_overflow))
+    }
+    /// Builds instructions to raise an integer to a non-negative integer power
+    /// using exponentiation by squaring, returning an error if overflow occurs
+    /// or if the exponent is negative.
+    pub fn build_checked_int_pow(
+        &mut self,
+        base: IntValue<'static>,
+        exponent: IntValue<'static>,
+        on_overflow: impl FnOnce(&mut Self) -> LangResult<()>,
+        on_negative_exponent: impl FnOnce(&mut Self) -> LangResult<()>,
+    ) -> LangResult<IntValue<'static>> {
+        let int_type = self.int_type();
+        let zero = int_type.const_zero();
+        let one = int_type.const_int(1, false);
 
-        Ok(result_value)
+        // If the exponent is negative, that's a NegativeExponent error.
+        let is_negative =
+            self.builder()
+                .build_int_compare(IntPredicate::SLT, exponent, zero, "isNegExponent");
+        self.build_conditional(is_negative, on_negative_exponent, |_| Ok(()))?;
+
+        let entry_bb = self.builder().get_insert_block().unwrap();
+        let loop_bb = self.append_basic_block("powLoop");
+        let body_bb = self.append_basic_block("powBody");
+        let exit_bb = self.append_basic_block("powExit");
+        self.builder().build_unconditional_branch(loop_bb);
+
+        // Each iteration of the loop consumes one bit of the exponent, starting
+        // with the least significant bit.
+        self.builder().position_at_end(loop_bb);
+        let result_phi = self.builder().build_phi(int_type, "powResult");
+        let base_phi = self.builder().build_phi(int_type, "powBase");
+        let exp_phi = self.builder().build_phi(int_type, "powExp");
+        result_phi.add_incoming(&[(&one, entry_bb)]);
+        base_phi.add_incoming(&[(&base, entry_bb)]);
+        exp_phi.add_incoming(&[(&exponent, entry_bb)]);
+        let result = result_phi.as_basic_value().into_int_value();
+        let base = base_phi.as_basic_value().into_int_value();
+        let exp = exp_phi.as_basic_value().into_int_value();
+        let is_done = self
+            .builder()
+            .build_int_compare(IntPredicate::EQ, exp, zero, "powIsDone");
+        self.builder()
+            .build_conditional_branch(is_done, exit_bb, body_bb);
+
+        self.builder().position_at_end(body_bb);
+        // If the lowest bit of the exponent is set, multiply the result by the
+        // base.
+        let exp_bit = self.builder().build_and(exp, one, "powExpBit");
+        let exp_bit_is_set =
+            self.builder()
+                .build_int_compare(IntPredicate::NE, exp_bit, zero, "powExpBitIsSet");
+        let (product, product_overflow) =
+            self.build_int_arithmetic_with_overflow(result, base, "smul")?;
+        let product_overflow =
+            self.builder()
+                .build_and(exp_bit_is_set, product_overflow, "powResultOverflow");
+        let next_result = self
+            .builder()
+            .build_select(exp_bit_is_set, product, result, "powNextResult")
+            .into_int_value();
+        // Shift the exponent and square the base, but only check the square
+        // for overflow if it will actually be used.
+        let next_exp = self
+            .builder()
+            .build_right_shift(exp, one, false, "powNextExp");
+        let has_more_bits =
+            self.builder()
+                .build_int_compare(IntPredicate::NE, next_exp, zero, "powHasMoreBits");
+        let (next_base, square_overflow) =
+            self.build_int_arithmetic_with_overflow(base, base, "smul")?;
+        let square_overflow =
+            self.builder()
+                .build_and(has_more_bits, square_overflow, "powBaseOverflow");
+        let is_overflow = self
+            .builder()
+            .build_or(product_overflow, square_overflow, "powOverflow");
+        self.build_conditional(is_overflow, on_overflow, |_| Ok(()))?;
+        let body_end_bb = self.builder().get_insert_block().unwrap();
+        result_phi.add_incoming(&[(&next_result, body_end_bb)]);
+        base_phi.add_incoming(&[(&next_base, body_end_bb)]);
+        exp_phi.add_incoming(&[(&next_exp, body_end_bb)]);
+        self.builder().build_unconditional_branch(loop_bb);
+
+        self.builder().position_at_end(exit_bb);
+        Ok(result)
+    }
+    /// Builds instructions to raise an integer to a non-negative integer power
+    /// modulo a positive integer, and returns the result (which is never
+    /// negative). Intermediate products are computed with double the bit width
+    /// of an integer, so this never overflows.
+    ///
+    /// The exponent and modulus must already have been checked.
+    pub fn build_powmod(
+        &mut self,
+        base: IntValue<'static>,
+        exponent: IntValue<'static>,
+        modulus: IntValue<'static>,
+    ) -> IntValue<'static> {
+        let int_type = self.int_type();
+        let wide_int_type = get_ctx().custom_width_int_type(INT_BITS * 2);
+        let zero = int_type.const_zero();
+        let one = int_type.const_int(1, false);
+
+        // Reduce the base to the range `0..modulus`.
+        let b = self.builder();
+        let rem = b.build_int_signed_rem(base, modulus, "powmodBaseRem");
+        let rem_is_negative = b.build_int_compare(IntPredicate::SLT, rem, zero, "remIsNeg");
+        let rem_plus_modulus = b.build_int_add(rem, modulus, "tmp_remPlusModulus");
+        let base = b
+            .build_select(rem_is_negative, rem_plus_modulus, rem, "powmodBase")
+            .into_int_value();
+        // Start with `1 % modulus` so that the result is zero if the modulus is
+        // one.
+        let initial_result = b.build_int_unsigned_rem(one, modulus, "powmodInitial");
+        let wide_modulus = b.build_int_z_extend(modulus, wide_int_type, "powmodWideModulus");
+        // Multiply two integers in the range `0..modulus`, modulo `modulus`.
+        let build_mulmod =
+            |b: &Builder<'static>, lhs: IntValue<'static>, rhs: IntValue<'static>, name: &str| {
+                let lhs = b.build_int_z_extend(lhs, wide_int_type, "powmodWideLhs");
+                let rhs = b.build_int_z_extend(rhs, wide_int_type, "powmodWideRhs");
+                let product = b.build_int_mul(lhs, rhs, "powmodWideProduct");
+                let rem = b.build_int_unsigned_rem(product, wide_modulus, "powmodWideRem");
+                b.build_int_truncate(rem, int_type, name)
+            };
+
+        let entry_bb = self.builder().get_insert_block().unwrap();
+        let loop_bb = self.append_basic_block("powmodLoop");
+        let body_bb = self.append_basic_block("powmodBody");
+        let exit_bb = self.append_basic_block("powmodExit");
+        self.builder().build_unconditional_branch(loop_bb);
+
+        // Each iteration of the loop consumes one bit of the exponent, starting
+        // with the least significant bit.
+        self.builder().position_at_end(loop_bb);
+        let result_phi = self.builder().build_phi(int_type, "powmodResult");
+        let base_phi = self.builder().build_phi(int_type, "powmodBase");
+        let exp_phi = self.builder().build_phi(int_type, "powmodExp");
+        result_phi.add_incoming(&[(&initial_result, entry_bb)]);
+        base_phi.add_incoming(&[(&base, entry_bb)]);
+        exp_phi.add_incoming(&[(&exponent, entry_bb)]);
+        let result = result_phi.as_basic_value().into_int_value();
+        let base = base_phi.as_basic_value().into_int_value();
+        let exp = exp_phi.as_basic_value().into_int_value();
+        let is_done = self
+            .builder()
+            .build_int_compare(IntPredicate::EQ, exp, zero, "powmodIsDone");
+        self.builder()
+            .build_conditional_branch(is_done, exit_bb, body_bb);
+
+        self.builder().position_at_end(body_bb);
+        let b = self.builder();
+        // If the lowest bit of the exponent is set, multiply the result by the
+        // base.
+        let exp_bit = b.build_and(exp, one, "powmodExpBit");
+        let exp_bit_is_set = b.build_int_compare(IntPredicate::NE, exp_bit, zero, "expBitIsSet");
+        let product = build_mulmod(b, result, base, "powmodProduct");
+        let next_result = b
+            .build_select(exp_bit_is_set, product, result, "powmodNextResult")
+            .into_int_value();
+        // Shift the exponent and square the base.
+        let next_exp = b.build_right_shift(exp, one, false, "powmodNextExp");
+        let next_base = build_mulmod(b, base, base, "powmodNextBase");
+        result_phi.add_incoming(&[(&next_result, body_bb)]);
+        base_phi.add_incoming(&[(&next_base, body_bb)]);
+        exp_phi.add_incoming(&[(&next_exp, body_bb)]);
+        b.build_unconditional_branch(loop_bb);
+
+        self.builder().position_at_end(exit_bb);
+        result
+    }
+    /// Builds instructions to advance the random number generator and return a
+    /// uniformly distributed random integer from zero (inclusive) to the given
+    /// upper bound (exclusive).
+    ///
+    /// The upper bound must already have been checked to be positive.
+    pub fn build_random(
+        &mut self,
+        upper_bound: IntValue<'static>,
+    ) -> LangResult<IntValue<'static>> {
+        let int_type = self.int_type();
+        let wide_int_type = get_ctx().custom_width_int_type(INT_BITS * 2);
+        let rng_state_ptr = self.rng_state_ptr()?;
+        let b = self.builder();
+
+        // Advance the state using SplitMix64.
+        let state = b.build_load(rng_state_ptr, "rngState").into_int_value();
+        let next_state = b.build_int_add(
+            state,
+            int_type.const_int(0x9E37_79B9_7F4A_7C15, false),
+            "nextRngState",
+        );
+        b.build_store(rng_state_ptr, next_state);
+        let mut z = next_state;
+        for &(shift, multiplier) in &[(30, 0xBF58_476D_1CE4_E5B9), (27, 0x94D0_49BB_1331_11EB)] {
+            let shifted = b.build_right_shift(z, int_type.const_int(shift, false), false, "tmp_z");
+            let mixed = b.build_xor(z, shifted, "tmp_z");
+            z = b.build_int_mul(mixed, int_type.const_int(multiplier, false), "tmp_z");
+        }
+        let shifted = b.build_right_shift(z, int_type.const_int(31, false), false, "tmp_z");
+        let random_bits = b.build_xor(z, shifted, "randomBits");
+
+        // Scale the random bits to the range `0..upper_bound` by taking the
+        // high half of their product with the upper bound.
+        let wide_bits = b.build_int_z_extend(random_bits, wide_int_type, "tmp_wideBits");
+        let wide_bound = b.build_int_z_extend(upper_bound, wide_int_type, "tmp_wideBound");
+        let wide_product = b.build_int_mul(wide_bits, wide_bound, "tmp_wideProduct");
+        let high_half = b.build_right_shift(
+            wide_product,
+            wide_int_type.const_int(INT_BITS as u64, false),
+            false,
+            "tmp_highHalf",
+        );
+        Ok(b.build_int_truncate(high_half, int_type, "randomInt"))
+    }
+    /// Builds instructions to compute the absolute value of an integer,
+    /// treating the result as unsigned so that the absolute value of the
+    /// minimum integer value does not overflow.
+    pub fn build_unsigned_abs(&mut self, value: IntValue<'static>) -> IntValue<'static> {
+        let zero = self.int_type().const_zero();
+        let b = self.builder();
+        let is_negative = b.build_int_compare(IntPredicate::SLT, value, zero, "isNeg");
+        let negated = b.build_int_neg(value, "tmp_neg");
+        b.build_select(is_negative, negated, value, "tmp_abs")
+            .into_int_value()
+    }
+    /// Builds instructions to compute the greatest common divisor of two
+    /// unsigned integers using the Euclidean algorithm, and returns the result
+    /// as an unsigned integer.
+    pub fn build_unsigned_gcd(
+        &mut self,
+        a: IntValue<'static>,
+        b: IntValue<'static>,
+    ) -> IntValue<'static> {
+        let int_type = self.int_type();
+        let zero = int_type.const_zero();
+
+        let entry_bb = self.builder().get_insert_block().unwrap();
+        let loop_bb = self.append_basic_block("gcdLoop");
+        let body_bb = self.append_basic_block("gcdBody");
+        let exit_bb = self.append_basic_block("gcdExit");
+        self.builder().build_unconditional_branch(loop_bb);
+
+        // Each iteration of the loop replaces `(a, b)` with `(b, a % b)` until
+        // `b` is zero.
+        self.builder().position_at_end(loop_bb);
+        let a_phi = self.builder().build_phi(int_type, "gcdA");
+        let b_phi = self.builder().build_phi(int_type, "gcdB");
+        a_phi.add_incoming(&[(&a, entry_bb)]);
+        b_phi.add_incoming(&[(&b, entry_bb)]);
+        let a = a_phi.as_basic_value().into_int_value();
+        let b = b_phi.as_basic_value().into_int_value();
+        let is_done = self
+            .builder()
+            .build_int_compare(IntPredicate::EQ, b, zero, "gcdIsDone");
+        self.builder()
+            .build_conditional_branch(is_done, exit_bb, body_bb);
+
+        self.builder().position_at_end(body_bb);
+        let rem = self.builder().build_int_unsigned_rem(a, b, "gcdRem");
+        a_phi.add_incoming(&[(&b, body_bb)]);
+        b_phi.add_incoming(&[(&rem, body_bb)]);
+        self.builder().build_unconditional_branch(loop_bb);
+
+        self.builder().position_at_end(exit_bb);
+        a
+    }
+    /// Builds a division-by-zero check for arguments to a wrapping division
+    /// operation (but does not actually perform the division), and returns a
+    /// divisor that is safe to use for the division.
+    ///
+    /// Dividing the minimum integer value by -1 overflows, which LLVM does not
+    /// allow, so the returned divisor is 1 in that case; dividing by 1 gives
+    /// the same result as wrapping division.
+    pub fn build_wrapping_div_check(
+        &mut self,
+        dividend: IntValue<'static>,
+        divisor: IntValue<'static>,
+        on_div_by_zero: impl FnOnce(&mut Self) -> LangResult<()>,
+    ) -> LangResult<IntValue<'static>> {
+        self.build_div_check(dividend, divisor, |_| Ok(()), on_div_by_zero)?;
+
+        let min_value = self.get_min_int_value();
+        let negative_one = self.int_type().const_int(-1i64 as u64, true);
+        let one = self.int_type().const_int(1, false);
+        let b = self.builder();
+        let num_is_min_value =
+            b.build_int_compare(IntPredicate::EQ, dividend, min_value, "isMinValue");
+        let denom_is_neg_one =
+            b.build_int_compare(IntPredicate::EQ, divisor, negative_one, "isNegOne");
+        let is_overflow = b.build_and(num_is_min_value, denom_is_neg_one, "isOverflow");
+        Ok(b.build_select(is_overflow, one, divisor, "wrappingDivisor")
+            .into_int_value())
     }
     /// Builds an overflow and division-by-zero check for arguments to a
     /// division operation (but does not actually perform the division).
@@ -506,6 +1430,202 @@ impl Compiler {
         )
     }
 
+    /// Builds instructions to load the cell state at the given index in the
+    /// flat neighborhood array (without any bounds checking). Indices past the
+    /// end of the cell states refer to later layers.
+    pub fn build_load_neighbor(
+        &mut self,
+        index: IntValue<'static>,
+    ) -> LangResult<IntValue<'static>> {
+        let neighborhood_ptr = self.neighborhood_ptr()?;
+        let neighbor_ptr = unsafe {
+            self.builder()
+                .build_in_bounds_gep(neighborhood_ptr, &[index], "neighborPtr")
+        };
+        Ok(self
+            .builder()
+            .build_load(neighbor_ptr, "neighbor")
+            .into_int_value())
+    }
+    /// Builds instructions to append a value to the debug trace, if the
+    /// function being built is compiled in debug mode and can be called from
+    /// Rust code. Otherwise this does nothing.
+    ///
+    /// Each entry in the trace is the index of the `debug` statement (see
+    /// debug_value_types()) followed by the components of the value, each
+    /// extended to 64 bits.
+    pub fn build_debug_trace_entry(&mut self, value: &Value) -> LangResult<()> {
+        let (len_ptr, trace_ptr) = match (
+            self.function().debug_trace_len_ptr,
+            self.function().debug_trace_ptr,
+        ) {
+            (Some(len_ptr), Some(trace_ptr)) => (len_ptr, trace_ptr),
+            _ => return Ok(()),
+        };
+        let int_type = self.int_type();
+
+        // Assemble the words of this entry.
+        let entry_idx = self.function().debug_value_types.len();
+        self.function_mut().debug_value_types.push(value.ty());
+        let mut words = vec![int_type.const_int(entry_idx as u64, false)];
+        match value {
+            Value::Int(i) => words.push(*i),
+            Value::Bool(i) | Value::CellState(i) => {
+                words.push(self.builder().build_int_z_extend(*i, int_type, "debugWord"));
+            }
+            Value::Vector(v) => {
+                for component_idx in 0..v.get_type().get_size() {
+                    let component_idx = int_type.const_int(component_idx as u64, false);
+                    words.push(
+                        self.builder()
+                            .build_extract_element(*v, component_idx, "debugWord")
+                            .into_int_value(),
+                    );
+                }
+            }
+        }
+
+        // Only record the entry if there is room for all of it.
+        let old_len = self
+            .builder()
+            .build_load(len_ptr, "debugTraceLen")
+            .into_int_value();
+        let entry_len = int_type.const_int(words.len() as u64, false);
+        let new_len = self
+            .builder()
+            .build_int_add(old_len, entry_len, "newDebugTraceLen");
+        let capacity = int_type.const_int(DEBUG_TRACE_CAPACITY as u64, false);
+        let has_room =
+            self.builder()
+                .build_int_compare(IntPredicate::ULE, new_len, capacity, "hasRoom");
+        let record_bb = self.append_basic_block("recordDebugValue");
+        let end_bb = self.append_basic_block("endRecordDebugValue");
+        self.builder()
+            .build_conditional_branch(has_room, record_bb, end_bb);
+
+        self.builder().position_at_end(record_bb);
+        let zero = int_type.const_zero();
+        for (offset, word) in words.into_iter().enumerate() {
+            let offset = int_type.const_int(offset as u64, false);
+            let index = self.builder().build_int_add(old_len, offset, "debugIndex");
+            let word_ptr = unsafe {
+                self.builder()
+                    .build_in_bounds_gep(trace_ptr, &[zero, index], "debugWordPtr")
+            };
+            self.builder().build_store(word_ptr, word);
+        }
+        self.builder().build_store(len_ptr, new_len);
+        self.builder().build_unconditional_branch(end_bb);
+
+        self.builder().position_at_end(end_bb);
+        Ok(())
+    }
+    /// Returns the type of the value recorded by each `debug` statement in
+    /// the function being built, in the order that they were compiled.
+    pub fn debug_value_types(&self) -> &[Type] {
+        &self.function().debug_value_types
+    }
+
+    /// Builds a constant array of integers in the module and returns a pointer
+    /// to it, for use with build_load_const_array_element().
+    pub fn build_const_int_array(&mut self, name: &str, values: &[u64]) -> PointerValue<'static> {
+        let int_type = self.int_type();
+        let values: Vec<IntValue<'static>> = values
+            .iter()
+            .map(|&v| int_type.const_int(v, false))
+            .collect();
+        let array_type = int_type.array_type(values.len() as u32);
+        let global = self
+            .module
+            .add_global(array_type, Some(AddressSpace::Generic), name);
+        global.set_initializer(&int_type.const_array(&values));
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.as_pointer_value()
+    }
+    /// Builds instructions to load the integer at the given index in a
+    /// constant array built by build_const_int_array() (without any bounds
+    /// checking).
+    pub fn build_load_const_array_element(
+        &mut self,
+        array_ptr: PointerValue<'static>,
+        index: IntValue<'static>,
+    ) -> IntValue<'static> {
+        let zero = self.int_type().const_zero();
+        let element_ptr = unsafe {
+            self.builder()
+                .build_in_bounds_gep(array_ptr, &[zero, index], "elementPtr")
+        };
+        self.builder()
+            .build_load(element_ptr, "element")
+            .into_int_value()
+    }
+    /// Builds a range check for one component of the offset of a neighbor cell
+    /// (but does not actually access the neighbor), returning the component
+    /// shifted into the range from 0 to `2 * NEIGHBORHOOD_RADIUS` (inclusive).
+    pub fn build_neighbor_offset_check(
+        &mut self,
+        offset: IntValue<'static>,
+        on_out_of_range: impl FnOnce(&mut Self) -> LangResult<()>,
+    ) -> LangResult<IntValue<'static>> {
+        // Add the radius so that the lowest valid offset is zero, then treat
+        // the result as an unsigned integer. (A negative number will be
+        // interpreted as a very large positive number, which will be too
+        // large.)
+        let int_type = self.int_type();
+        let radius = int_type.const_int(NEIGHBORHOOD_RADIUS as u64, false);
+        let diameter = int_type.const_int(2 * NEIGHBORHOOD_RADIUS as u64 + 1, false);
+        let shifted_offset = self
+            .builder()
+            .build_int_add(offset, radius, "shiftedOffset");
+        let is_out_of_range = self.builder().build_int_compare(
+            IntPredicate::UGE, // Unsigned Greater-Than or Equal
+            shifted_offset,
+            diameter,
+            "isNeighborOutOfRange",
+        );
+
+        // Branch based on whether the offset is out of range.
+        self.build_conditional(
+            is_out_of_range,
+            // The offset is out of range.
+            on_out_of_range,
+            // The offset is in range.
+            |_| Ok(()),
+        )?;
+        Ok(shifted_offset)
+    }
+
+    /// Builds a range check for the shift amount of a bitshift operation (but
+    /// does not actually perform the bitshift).
+    pub fn build_shift_check(
+        &mut self,
+        shift_amount: IntValue<'static>,
+        on_out_of_range: impl FnOnce(&mut Self) -> LangResult<()>,
+    ) -> LangResult<()> {
+        // Treat the signed integer as an unsigned integer, and build a
+        // condition testing whether that value is at least the number of bits
+        // in an integer. (A negative number will be interpreted as a very large
+        // positive number, which will be too large.)
+        let int_type = self.int_type();
+        let bit_width = int_type.const_int(int_type.get_bit_width() as u64, false);
+        let is_out_of_range = self.builder().build_int_compare(
+            IntPredicate::UGE, // Unsigned Greater-Than or Equal
+            shift_amount,
+            bit_width,
+            "isShiftOutOfRange",
+        );
+
+        // Branch based on whether the shift amount is out of range.
+        self.build_conditional(
+            is_out_of_range,
+            // The shift amount is out of range.
+            on_out_of_range,
+            // The shift amount is in range.
+            |_| Ok(()),
+        )
+    }
+
     /// Returns the minimum value representable by signed integers of NDCA's
     /// signed integer type.
     fn get_min_int_value(&self) -> IntValue<'static> {
@@ -519,6 +1639,7 @@ impl Compiler {
     pub fn value_from_const(&self, const_value: ConstValue) -> Value {
         match const_value {
             ConstValue::Int(i) => Value::Int(self.int_type().const_int(i as u64, true)),
+            ConstValue::Bool(b) => Value::Bool(self.bool_type().const_int(b as u64, false)),
             ConstValue::CellState(i) => {
                 Value::CellState(self.cell_state_type().const_int(i as u64, false))
             }
@@ -543,6 +1664,7 @@ impl Compiler {
     pub fn get_llvm_type(&self, ty: Type) -> LangResult<BasicTypeEnum<'static>> {
         match ty {
             Type::Int => Ok(self.int_type().into()),
+            Type::Bool => Ok(self.bool_type().into()),
             Type::CellState => Ok(self.cell_state_type().into()),
             Type::Vector(len) => Ok(self.int_type().vec_type(len as u32).into()),
             _ => Err(InternalError(
@@ -569,14 +1691,40 @@ struct FunctionInProgress {
     /// Struct type used to input arguments, output a return value, and debug
     /// variables if debugging is enabled.
     inout_struct_type: Option<StructType<'static>>,
+    /// Pointer to the first cell state of the neighborhood.
+    neighborhood_ptr: Option<PointerValue<'static>>,
+    /// Pointer to the state of the random number generator.
+    rng_state_ptr: Option<PointerValue<'static>>,
+    /// Pointer to the number of loop iterations executed so far.
+    step_count_ptr: Option<PointerValue<'static>>,
+    /// Pointer to the number of words in the debug trace, if debugging is
+    /// enabled.
+    debug_trace_len_ptr: Option<PointerValue<'static>>,
+    /// Pointer to the debug trace, if debugging is enabled.
+    debug_trace_ptr: Option<PointerValue<'static>>,
+    /// Type of the value recorded by each `debug` statement.
+    debug_value_types: Vec<Type>,
 
     /// Return type of this function.
     return_type: Type,
     /// Pointer to the place to put the return value.
     return_value_ptr: Option<PointerValue<'static>>,
+    /// Offset added to the index of any error returned from this function.
+    error_index_offset: usize,
 
     /// Variables, indexed by name.
     vars_by_name: HashMap<String, Variable>,
+    /// Stack of loops enclosing the current instruction, innermost last.
+    loops: Vec<Loop>,
+}
+
+/// Basic blocks to jump to from inside a loop.
+#[derive(Debug, Copy, Clone)]
+struct Loop {
+    /// Basic block to jump to for `continue`.
+    continue_bb: BasicBlock<'static>,
+    /// Basic block to jump to for `break`.
+    break_bb: BasicBlock<'static>,
 }
 
 /// Compiled variable.
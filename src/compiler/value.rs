@@ -10,6 +10,8 @@ use super::super::Type;
 pub enum Value {
     /// Integer.
     Int(IntValue<'static>),
+    /// Boolean (1-bit integer).
+    Bool(IntValue<'static>),
     /// Cell state.
     CellState(IntValue<'static>),
     /// Vector of a specific length (from 1 to 6).
@@ -20,14 +22,21 @@ impl Value {
     pub fn ty(&self) -> Type {
         match self {
             Self::Int(_) => Type::Int,
+            Self::Bool(_) => Type::Bool,
             Self::CellState(_) => Type::CellState,
             Self::Vector(v) => Type::Vector(v.get_type().get_size() as usize),
         }
     }
+    /// Returns a short human-readable name for the type of this value, such as
+    /// "integer" or "vector".
+    pub fn type_name(&self) -> &'static str {
+        self.ty().name()
+    }
     /// Constructs a value of the given type from an LLVM basic value.
     pub fn from_basic_value(ty: Type, basic_value: BasicValueEnum<'static>) -> Self {
         match ty {
             Type::Int => Self::Int(basic_value.into_int_value()),
+            Type::Bool => Self::Bool(basic_value.into_int_value()),
             Type::CellState => Self::CellState(basic_value.into_int_value()),
             Type::Vector(len) => {
                 let ret = Self::Vector(basic_value.into_vector_value());
@@ -41,7 +50,15 @@ impl Value {
     pub fn as_int(self) -> LangResult<IntValue<'static>> {
         match self {
             Value::Int(i) => Ok(i),
-            _ => Err(UNCAUGHT_TYPE_ERROR),
+            _ => Err(uncaught_type_error("integer", self.type_name())),
+        }
+    }
+    /// Returns the LLVM 1-bit integer value inside if this is Value::Bool;
+    /// otherwise a TypeError.
+    pub fn as_bool(self) -> LangResult<IntValue<'static>> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            _ => Err(uncaught_type_error("boolean", self.type_name())),
         }
     }
     /// Returns the LLVM integer value inside if this is Value::CellState;
@@ -49,7 +66,7 @@ impl Value {
     pub fn as_cell_state(self) -> LangResult<IntValue<'static>> {
         match self {
             Value::CellState(i) => Ok(i),
-            _ => Err(UNCAUGHT_TYPE_ERROR),
+            _ => Err(uncaught_type_error("cell state", self.type_name())),
         }
     }
     /// Returns the LLVM vector value inside if this is Value::Vector; otherwise
@@ -57,7 +74,7 @@ impl Value {
     pub fn as_vector(self) -> LangResult<VectorValue<'static>> {
         match self {
             Value::Vector(v) => Ok(v),
-            _ => Err(UNCAUGHT_TYPE_ERROR),
+            _ => Err(uncaught_type_error("vector", self.type_name())),
         }
     }
     /// Returns this value as an LLVM basic value if it is representable as one;
@@ -65,6 +82,7 @@ impl Value {
     pub fn into_basic_value(self) -> LangResult<BasicValueEnum<'static>> {
         match self {
             Value::Int(i) => Ok(i.into()),
+            Value::Bool(b) => Ok(b.into()),
             Value::CellState(i) => Ok(i.into()),
             Value::Vector(v) => Ok(v.into()),
             // Value::Pattern => Err(InternalError(format!("{} has no BasicValue representation", self).into())),
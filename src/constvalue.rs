@@ -1,5 +1,6 @@
 //! Values used by the interpreter for NDCA.
 
+use std::cmp::Ordering;
 use std::convert::TryInto;
 
 use super::errors::*;
@@ -18,26 +19,47 @@ const INTERNAL_VAR_USE_ERROR: LangError = InternalError(std::borrow::Cow::Borrow
 pub enum ConstValue {
     /// Integer
     Int(LangInt),
+    /// Boolean
+    Bool(bool),
     /// Cell state
     CellState(LangCellState),
     /// Vector of a specific length from 1 to 256 (extra components are zero).
     Vector(Vec<LangInt>),
     // Pattern(crate::automaton::ArrayView2D<u8>),
 }
+impl PartialOrd for ConstValue {
+    /// Compares two values the same way that NDCA code can: integers are
+    /// ordered, but other values can only be tested for equality, and values
+    /// of different types are never equal.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(b)),
+            _ if self == other => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
 impl ConstValue {
     /// Returns the type of this value.
     pub fn ty(&self) -> Type {
         match self {
             Self::Int(_) => Type::Int,
+            Self::Bool(_) => Type::Bool,
             Self::CellState(_) => Type::CellState,
             Self::Vector(values) => Type::Vector(values.len()),
             // Self::Pattern(_) => Type::Pattern,
         }
     }
+    /// Returns a short human-readable name for the type of this value, such as
+    /// "integer" or "vector".
+    pub fn type_name(&self) -> &'static str {
+        self.ty().name()
+    }
     /// Constructs a default value of the given type.
     pub fn default(ty: Type) -> Option<Self> {
         match ty {
             Type::Int => Some(Self::Int(0)),
+            Type::Bool => Some(Self::Bool(false)),
             Type::CellState => Some(Self::CellState(0)),
             Type::Vector(len) => Some(Self::Vector(vec![0; len as usize])),
         }
@@ -47,7 +69,15 @@ impl ConstValue {
     pub fn as_int(self) -> LangResult<LangInt> {
         match self {
             Self::Int(i) => Ok(i),
-            _ => Err(UNCAUGHT_TYPE_ERROR),
+            _ => Err(uncaught_type_error("integer", self.type_name())),
+        }
+    }
+    /// Returns the boolean value inside if this is a ConstValue::Bool;
+    /// otherwise a TypeError.
+    pub fn as_bool(self) -> LangResult<bool> {
+        match self {
+            Self::Bool(b) => Ok(b),
+            _ => Err(uncaught_type_error("boolean", self.type_name())),
         }
     }
     /// Returns the integer value inside if this is a ConstValue::CellState;
@@ -55,7 +85,7 @@ impl ConstValue {
     pub fn as_cell_state(self) -> LangResult<LangCellState> {
         match self {
             Self::CellState(i) => Ok(i),
-            _ => Err(UNCAUGHT_TYPE_ERROR),
+            _ => Err(uncaught_type_error("cell state", self.type_name())),
         }
     }
     /// Constructs a value of the given type from raw bytes. Panics if given an
@@ -77,6 +107,7 @@ impl ConstValue {
         );
         match ty {
             Type::Int => Self::Int(LangInt::from_ne_bytes(bytes.try_into().unwrap())),
+            Type::Bool => Self::Bool(bytes[0] != 0),
             Type::CellState => {
                 Self::CellState(LangCellState::from_ne_bytes(bytes.try_into().unwrap()))
             }
@@ -126,6 +157,7 @@ impl ConstValue {
                 let bytes: &mut [u8; SIZE] = bytes.try_into().unwrap();
                 *bytes = i.to_ne_bytes()
             }
+            Self::Bool(b) => bytes[0] = *b as u8,
             Self::CellState(i) => {
                 const SIZE: usize = std::mem::size_of::<LangCellState>();
                 let bytes: &mut [u8; SIZE] = bytes.try_into().unwrap();
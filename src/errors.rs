@@ -6,55 +6,195 @@ use std::fmt;
 
 use super::ast::ArgTypes;
 use super::lexer::ComparisonToken;
-use super::{Span, Type, MAX_NDIM, MAX_STATES};
+use super::types::{LangCellState, INT_BITS, MAX_VECTOR_LEN};
+use super::{
+    LineIndex, Span, Type, MAX_EXPR_DEPTH, MAX_EXPR_TREE_DEPTH, MAX_LAYERS, MAX_NDIM, MAX_STATES,
+    MAX_TABLE_SIZE, NDCA_VERSION, NEIGHBORHOOD_RADIUS,
+};
 
-pub const UNCAUGHT_TYPE_ERROR: LangError =
-    LangErrorMsg::InternalError(Cow::Borrowed("Uncaught type error")).without_span();
+/// Returns an InternalError for a value of the wrong type that was not caught
+/// by the type checker, such as "Uncaught type error: expected cell state but
+/// got integer".
+pub fn uncaught_type_error(expected: &str, got: &str) -> LangError {
+    LangErrorMsg::InternalError(
+        format!("Uncaught type error: expected {} but got {}", expected, got).into(),
+    )
+    .without_span()
+}
 
 /// A Result of a LangError and an accompanying line of source code.
 pub type CompleteLangResult<T> = Result<T, LangErrorWithSource>;
 /// A Result of a LangError.
 pub type LangResult<T> = Result<T, LangError>;
+/// A Result of one or more LangErrors, in the order that they were encountered.
+pub type MultiLangResult<T> = Result<T, Vec<LangError>>;
 
-/// An error type and an accompanying line and span of source code.
+/// An error type and the accompanying lines and span of source code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LangErrorWithSource {
-    /// The string of source code of the error location (if any).
-    pub source_line: Option<String>,
-    /// The 1-indexed line number of the error location (if any).
+    /// The lines of source code covered by the error location (if any).
+    pub source_lines: Vec<String>,
+    /// The 1-indexed line number of the start of the error location (if any).
     pub line_num: Option<usize>,
-    /// The span of the error location (if any).
+    /// The 1-indexed columns of the start of the error location on the first
+    /// line and the end of the error location on the last line (if any).
     pub span: Option<(usize, usize)>,
     /// The type of error.
     pub msg: LangErrorMsg,
 }
 impl fmt::Display for LangErrorWithSource {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if let (Some(line), Some(line_num), Some((start, end))) =
-            (&self.source_line, self.line_num, self.span)
+        self.write(f, false)
+    }
+}
+impl Error for LangErrorWithSource {}
+impl LangErrorWithSource {
+    /// Returns a wrapper around this error that is displayed with ANSI color
+    /// codes (red arrows and a bold error message), for use in a terminal.
+    pub fn colored(&self) -> ColoredLangErrorWithSource<'_> {
+        ColoredLangErrorWithSource(self)
+    }
+    /// Returns a machine-readable JSON representation of this error, of the
+    /// form `{"code": ..., "line": ..., "span": [start, end], "message": ...}`.
+    /// `line` and `span` are `null` if the error has no location.
+    pub fn to_json(&self) -> String {
+        let line = match self.line_num {
+            Some(line_num) => line_num.to_string(),
+            None => "null".to_owned(),
+        };
+        let span = match self.span {
+            Some((start, end)) => format!("[{}, {}]", start, end),
+            None => "null".to_owned(),
+        };
+        format!(
+            "{{\"code\": {}, \"line\": {}, \"span\": {}, \"message\": {}}}",
+            json_string(self.msg.code()),
+            line,
+            span,
+            json_string(&self.msg.to_string()),
+        )
+    }
+    /// Writes this error, optionally with ANSI color codes.
+    fn write(&self, f: &mut fmt::Formatter, colored: bool) -> fmt::Result {
+        if let (Some(line_num), Some((start, end)), false) =
+            (self.line_num, self.span, self.source_lines.is_empty())
         {
             // Write line and column numbers.
-            writeln!(f, "Error at line {}; column {}", line_num, start)?;
-            // Remove initial whitespace.
-            let trimmed_len = line.len() - line.trim_start().len();
-            let trimmed_start = start - trimmed_len;
-            // Write line of source code.
-            writeln!(f, "{}", line.trim())?;
-            for _ in 0..(trimmed_start - 1) {
-                write!(f, " ")?;
-            }
-            // Write arrows pointing to the part with the error.
-            for _ in start..end {
-                write!(f, "^")?;
+            let kind = if self.msg.is_warning() {
+                "Warning"
+            } else {
+                "Error"
+            };
+            writeln!(f, "{} at line {}; column {}", kind, line_num, start)?;
+            // Write each line of source code, with arrows pointing to the part
+            // with the error.
+            let last_idx = self.source_lines.len().saturating_sub(1);
+            for (i, line) in self.source_lines.iter().enumerate() {
+                let line_start = if i == 0 { start } else { 1 };
+                let line_end = if i == last_idx {
+                    end
+                } else {
+                    line.chars().count() + 1
+                };
+                if i != 0 {
+                    writeln!(f)?;
+                }
+                write_source_line(f, line, line_start, line_end, colored)?;
             }
             write!(f, "   ")?;
         }
-        // Write the error message.
-        write!(f, "{}", self.msg)?;
+        // Write the error code and message.
+        if colored {
+            write!(
+                f,
+                "{}[{}] {}{}",
+                ANSI_BOLD,
+                self.msg.code(),
+                self.msg,
+                ANSI_RESET
+            )?;
+        } else {
+            write!(f, "[{}] {}", self.msg.code(), self.msg)?;
+        }
         Ok(())
     }
 }
-impl Error for LangErrorWithSource {}
+
+/// A LangErrorWithSource that is displayed with ANSI color codes.
+#[derive(Debug, Copy, Clone)]
+pub struct ColoredLangErrorWithSource<'a>(&'a LangErrorWithSource);
+impl fmt::Display for ColoredLangErrorWithSource<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.write(f, true)
+    }
+}
+
+/// ANSI escape code for red text.
+const ANSI_RED: &str = "\x1b[31m";
+/// ANSI escape code for bold text.
+const ANSI_BOLD: &str = "\x1b[1m";
+/// ANSI escape code to reset all text attributes.
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// Returns a JSON string literal containing the given string.
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if c.is_control() => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+/// Writes a line of source code without initial whitespace, followed by a line
+/// of arrows pointing from the 1-indexed column `start` up to (but not
+/// including) `end`, where columns count characters rather than bytes. At
+/// least one arrow is always written, even for a zero-length span. If
+/// `colored` is true, the arrows are red.
+fn write_source_line(
+    f: &mut fmt::Formatter,
+    line: &str,
+    start: usize,
+    end: usize,
+    colored: bool,
+) -> fmt::Result {
+    // Remove initial whitespace.
+    let trimmed_len = line.chars().count() - line.trim_start().chars().count();
+    // Don't point at whitespace at the start or end of the line.
+    let start = std::cmp::max(start, trimmed_len + 1);
+    let end = std::cmp::min(end, line.trim_end().chars().count() + 1);
+    // Write line of source code.
+    writeln!(f, "{}", line.trim())?;
+    // Pad up to the start of the span, copying tabs so that the arrows line up
+    // in a terminal.
+    for (_, c) in line
+        .chars()
+        .enumerate()
+        .skip_while(|&(i, _)| i < trimmed_len)
+        .take_while(|&(i, _)| i + 1 < start)
+    {
+        write!(f, "{}", if c == '\t' { '\t' } else { ' ' })?;
+    }
+    // Write arrows pointing to the part with the error.
+    if colored {
+        write!(f, "{}", ANSI_RED)?;
+    }
+    for _ in start..std::cmp::max(end, start + 1) {
+        write!(f, "^")?;
+    }
+    if colored {
+        write!(f, "{}", ANSI_RESET)?;
+    }
+    Ok(())
+}
 
 /// An error type and an accompanying span.
 #[derive(Debug, Clone)]
@@ -64,6 +204,14 @@ pub struct LangError {
     /// The type of error.
     pub msg: LangErrorMsg,
 }
+impl fmt::Display for LangError {
+    /// Writes the error code and message without any source code context. Use
+    /// with_source() to include the location of the error.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.msg.code(), self.msg)
+    }
+}
+impl Error for LangError {}
 impl LangError {
     /// Attaches a span to this LangError, if it does not already have one.
     pub fn with_span(mut self, span: impl Into<Span>) -> Self {
@@ -72,31 +220,48 @@ impl LangError {
         }
         self
     }
-    /// Provides a line of source code as context to this error, returning a
+    /// Provides the lines of source code as context to this error, returning a
     /// LangErrorWithSource.
     pub fn with_source(self, src: &str) -> LangErrorWithSource {
+        self.with_line_index(src, &LineIndex::new(src))
+    }
+    /// Provides the lines of source code as context to this error, using a
+    /// precomputed LineIndex of the source code, returning a
+    /// LangErrorWithSource.
+    ///
+    /// This is faster than with_source() when reporting many errors from the
+    /// same source code.
+    pub fn with_line_index(self, src: &str, line_index: &LineIndex) -> LangErrorWithSource {
         if let Some(span) = self.span {
-            let (start_tp, end_tp) = span.textpoints(src);
+            let (start_tp, end_tp) = span.line_col(line_index);
+            let mut source_lines: Vec<String> = (start_tp.line()..=end_tp.line())
+                .map(|line_number| line_index.line(src, line_number).to_owned())
+                .collect();
+            let mut end = end_tp.column();
+            // If the error ends at the very beginning of a line, stop at the
+            // end of the previous line instead.
+            if source_lines.len() > 1 && end == 1 {
+                source_lines.pop();
+                end = source_lines
+                    .last()
+                    .map(|line| line.chars().count() + 1)
+                    .unwrap_or(1);
+            }
+            // An error that ends before it starts on the same line uses a
+            // zero-length span.
             let start = start_tp.column();
-            // If the error spans multiple lines, use a zero-length span on the
-            // first line.
-            let mut end = start;
-            if start_tp.line() == end_tp.line() && end_tp.column() > start_tp.column() {
-                end = end_tp.column();
+            if source_lines.len() <= 1 && end < start {
+                end = start;
             }
             LangErrorWithSource {
-                source_line: src
-                    .lines()
-                    .skip(start_tp.line() - 1)
-                    .next()
-                    .map(str::to_owned),
+                source_lines,
                 line_num: Some(start_tp.line()),
                 span: Some((start, end)),
                 msg: self.msg,
             }
         } else {
             LangErrorWithSource {
-                source_line: None,
+                source_lines: vec![],
                 line_num: None,
                 span: None,
                 msg: self.msg,
@@ -116,6 +281,8 @@ pub enum LangErrorMsg {
     // Compile errors
     UnknownSymbol,
     Unterminated(&'static str),
+    InvalidDigitSeparator,
+    InvalidEscapeSequence,
     Unmatched(char, char),
     Expected(&'static str),
     ExpectedGot {
@@ -124,13 +291,18 @@ pub enum LangErrorMsg {
     },
     ReservedWord(Cow<'static, str>),
     ElseWithoutIf,
+    MatchWithoutElse(Vec<LangCellState>),
+    DuplicateMatchArm,
     MissingSetKeyword,
     TopLevelNonDirective,
     InvalidDirectiveName,
     RepeatDirective(&'static str),
     FunctionNameConflict,
+    UnknownFunction,
     InvalidDimensionCount,
     InvalidStateCount,
+    DuplicateStateName,
+    InvalidVectorLength,
     TypeError {
         expected: Type,
         got: Type,
@@ -140,6 +312,10 @@ pub enum LangErrorMsg {
         cmp: ComparisonToken,
         rhs: Type,
     },
+    UnorderedType {
+        ty: Type,
+        cmp: ComparisonToken,
+    },
     InvalidArguments {
         name: String,
         omit_first: bool,
@@ -149,14 +325,44 @@ pub enum LangErrorMsg {
     CannotAssignTypeToVariable(Type),
     UseOfUninitializedVariable,
     BecomeInHelperFunction,
+    RemainInHelperFunction,
+    BreakOutsideLoop,
     ReturnInTransitionFunction,
     CannotEvalAsConst,
+    MissingReturn,
+    InvalidNeighborhoodOffset(u8),
+    UnsupportedSymmetry,
+    AssignmentCountMismatch {
+        vars: usize,
+        values: usize,
+    },
+    DuplicateConstant,
+    ConstantUsedBeforeDefinition,
+    AssignToConstant,
+    InvalidLayerCount,
+    ExpressionTooDeep,
+    InvalidStepLimit,
+    TableTooLarge,
+    UnsupportedTableExport(&'static str),
+    UnsupportedVersion(u32, u32),
+
+    // Warnings
+    UnreachableCode,
 
     // Runtime errors
     IntegerOverflow,
     DivideByZero,
     NegativeExponent,
+    ShiftOutOfRange,
+    NeighborOutOfRange,
+    VectorIndexOutOfRange,
     CellStateOutOfRange,
+    StepLimitExceeded,
+    InvalidClampRange,
+    InvalidModulus,
+    InvalidRandomBound,
+    AssertionFailed(Option<String>),
+    LayerOutOfRange,
 }
 impl<T: 'static + std::error::Error> From<T> for LangErrorMsg {
     fn from(error: T) -> Self {
@@ -182,6 +388,15 @@ impl fmt::Display for LangErrorMsg {
             Self::Unterminated(s) => {
                 write!(f, "This {} never ends", s)?;
             }
+            Self::InvalidDigitSeparator => {
+                write!(
+                    f,
+                    "Digit separators must be single underscores between digits"
+                )?;
+            }
+            Self::InvalidEscapeSequence => {
+                write!(f, "Invalid escape sequence")?;
+            }
             Self::Unmatched(char1, char2) => {
                 write!(f, "This '{}' has no matching '{}'", char1, char2)?;
             }
@@ -197,6 +412,17 @@ impl fmt::Display for LangErrorMsg {
             Self::ElseWithoutIf => {
                 write!(f, "This 'else' has no matching 'if'")?;
             }
+            Self::MatchWithoutElse(missing) => {
+                let missing: Vec<String> = missing.iter().map(|id| format!("#{}", id)).collect();
+                write!(
+                    f,
+                    "This 'match' has no 'else' arm and does not cover {}",
+                    missing.join(", "),
+                )?;
+            }
+            Self::DuplicateMatchArm => {
+                write!(f, "This value is already matched by an earlier arm")?;
+            }
             Self::MissingSetKeyword => {
                 write!(f, "Variable assignment requires the 'set' keyword")?;
             }
@@ -212,12 +438,21 @@ impl fmt::Display for LangErrorMsg {
             Self::FunctionNameConflict => {
                 write!(f, "There is already a function with this name")?;
             }
+            Self::UnknownFunction => {
+                write!(f, "There is no function with this name")?;
+            }
             Self::InvalidDimensionCount => {
                 write!(f, "Number of dimensions must range from 1 to {}", MAX_NDIM)?;
             }
             Self::InvalidStateCount => {
                 write!(f, "Number of states must range from 1 to {}", MAX_STATES)?;
             }
+            Self::DuplicateStateName => {
+                write!(f, "There is already a cell state with this name")?;
+            }
+            Self::InvalidVectorLength => {
+                write!(f, "Vector length must range from 1 to {}", MAX_VECTOR_LEN)?;
+            }
 
             Self::TypeError { expected, got } => {
                 write!(f, "Type error: expected {} but got {}", expected, got)?;
@@ -231,7 +466,14 @@ impl fmt::Display for LangErrorMsg {
                     "Type error: cannot compare {} to {} using '{}'",
                     lhs, rhs, cmp
                 )?;
-                if *lhs == Type::CellState && *rhs == Type::CellState {
+            }
+            Self::UnorderedType { ty, cmp } => {
+                write!(
+                    f,
+                    "Type error: cannot compare {} values using '{}' because they have no ordering",
+                    ty, cmp
+                )?;
+                if *ty == Type::CellState {
                     write!(f, "; convert them to integers first using the '#id' tag")?;
                 }
             }
@@ -272,6 +514,12 @@ impl fmt::Display for LangErrorMsg {
                     "Use 'return' instead of 'become' outside of transition functions"
                 )?;
             }
+            Self::RemainInHelperFunction => {
+                write!(f, "Cannot use 'remain' outside of transition functions")?;
+            }
+            Self::BreakOutsideLoop => {
+                write!(f, "Cannot use 'break' or 'continue' outside of a loop")?;
+            }
             Self::ReturnInTransitionFunction => {
                 write!(
                     f,
@@ -281,6 +529,74 @@ impl fmt::Display for LangErrorMsg {
             Self::CannotEvalAsConst => {
                 write!(f, "Cannot evaluate this expression as a constant")?;
             }
+            Self::MissingReturn => {
+                write!(
+                    f,
+                    "Not every path through the transition function ends in 'become' or 'remain'"
+                )?;
+            }
+            Self::InvalidNeighborhoodOffset(ndim) => {
+                write!(
+                    f,
+                    "Neighborhood offset must be a vector of length {} to match the number of \
+                     dimensions",
+                    ndim
+                )?;
+            }
+            Self::UnsupportedSymmetry => {
+                write!(f, "Symmetry is only supported in 2D")?;
+            }
+            Self::AssignmentCountMismatch { vars, values } => {
+                write!(
+                    f,
+                    "Wrong number of values: expected {} but got {}",
+                    vars, values
+                )?;
+            }
+            Self::DuplicateConstant => {
+                write!(f, "There is already a constant with this name")?;
+            }
+            Self::ConstantUsedBeforeDefinition => {
+                write!(f, "This constant is used before it is defined")?;
+            }
+            Self::AssignToConstant => {
+                write!(f, "Cannot assign to a constant")?;
+            }
+            Self::InvalidLayerCount => {
+                write!(f, "Number of layers must range from 1 to {}", MAX_LAYERS)?;
+            }
+            Self::InvalidStepLimit => {
+                write!(f, "Step limit must be positive")?;
+            }
+            Self::ExpressionTooDeep => {
+                write!(
+                    f,
+                    "Expression is nested too deeply (maximum depth is {}, or {} including \
+                     chained operators)",
+                    MAX_EXPR_DEPTH, MAX_EXPR_TREE_DEPTH,
+                )?;
+            }
+            Self::TableTooLarge => {
+                write!(
+                    f,
+                    "Transition table is too large to export (maximum is {} neighborhoods)",
+                    MAX_TABLE_SIZE,
+                )?;
+            }
+            Self::UnsupportedTableExport(s) => {
+                write!(f, "Cannot export a transition table for {}", s)?;
+            }
+            Self::UnsupportedVersion(major, minor) => {
+                write!(
+                    f,
+                    "NDCA version {}.{} is not supported (maximum is {}.x)",
+                    major, minor, NDCA_VERSION.0,
+                )?;
+            }
+
+            Self::UnreachableCode => {
+                write!(f, "This code is unreachable")?;
+            }
 
             Self::IntegerOverflow => {
                 write!(f, "Integer overflow")?;
@@ -291,14 +607,127 @@ impl fmt::Display for LangErrorMsg {
             Self::NegativeExponent => {
                 write!(f, "Negative exponent")?;
             }
+            Self::ShiftOutOfRange => {
+                write!(f, "Bitshift amount must range from 0 to {}", INT_BITS - 1)?;
+            }
+            Self::NeighborOutOfRange => {
+                write!(
+                    f,
+                    "Neighbor offset must range from {} to {}",
+                    -NEIGHBORHOOD_RADIUS, NEIGHBORHOOD_RADIUS
+                )?;
+            }
+            Self::VectorIndexOutOfRange => {
+                write!(f, "Vector index out of range")?;
+            }
             Self::CellStateOutOfRange => {
                 write!(f, "Cell state out of range")?;
             }
+            Self::StepLimitExceeded => {
+                write!(f, "Step limit exceeded; this loop may never end")?;
+            }
+            Self::InvalidClampRange => {
+                write!(f, "Lower bound of clamp is greater than upper bound")?;
+            }
+            Self::InvalidModulus => {
+                write!(f, "Modulus must be positive")?;
+            }
+            Self::InvalidRandomBound => {
+                write!(f, "Upper bound of random number must be positive")?;
+            }
+            Self::AssertionFailed(None) => {
+                write!(f, "Assertion failed")?;
+            }
+            Self::AssertionFailed(Some(msg)) => {
+                write!(f, "Assertion failed: {}", msg)?;
+            }
+            Self::LayerOutOfRange => {
+                write!(f, "Layer index out of range")?;
+            }
         }
         Ok(())
     }
 }
 impl LangErrorMsg {
+    /// Returns the stable error code of this error message, such as `E0001`.
+    ///
+    /// Compile errors start at `E0001`, runtime errors start at `E1001`, and
+    /// miscellaneous errors start at `E9001`. Warnings start at `W0001`. Codes
+    /// must never be reused or reassigned.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownSymbol => "E0001",
+            Self::Unterminated(_) => "E0002",
+            Self::InvalidDigitSeparator => "E0003",
+            Self::Unmatched(_, _) => "E0004",
+            Self::Expected(_) => "E0005",
+            Self::ExpectedGot { .. } => "E0006",
+            Self::ReservedWord(_) => "E0007",
+            Self::ElseWithoutIf => "E0008",
+            Self::MatchWithoutElse(_) => "E0009",
+            Self::DuplicateMatchArm => "E0010",
+            Self::MissingSetKeyword => "E0011",
+            Self::TopLevelNonDirective => "E0012",
+            Self::InvalidDirectiveName => "E0013",
+            Self::RepeatDirective(_) => "E0014",
+            Self::FunctionNameConflict => "E0015",
+            Self::UnknownFunction => "E0016",
+            Self::InvalidDimensionCount => "E0017",
+            Self::InvalidStateCount => "E0018",
+            Self::InvalidVectorLength => "E0019",
+            Self::TypeError { .. } => "E0020",
+            Self::CmpError { .. } => "E0021",
+            Self::InvalidArguments { .. } => "E0022",
+            Self::CannotAssignTypeToVariable(_) => "E0023",
+            Self::UseOfUninitializedVariable => "E0024",
+            Self::BecomeInHelperFunction => "E0025",
+            Self::RemainInHelperFunction => "E0026",
+            Self::BreakOutsideLoop => "E0027",
+            Self::ReturnInTransitionFunction => "E0028",
+            Self::CannotEvalAsConst => "E0029",
+            Self::DuplicateStateName => "E0030",
+            Self::MissingReturn => "E0031",
+            Self::UnorderedType { .. } => "E0032",
+            Self::InvalidNeighborhoodOffset(_) => "E0033",
+            Self::UnsupportedSymmetry => "E0034",
+            Self::AssignmentCountMismatch { .. } => "E0035",
+            Self::InvalidEscapeSequence => "E0036",
+            Self::DuplicateConstant => "E0037",
+            Self::ConstantUsedBeforeDefinition => "E0038",
+            Self::AssignToConstant => "E0039",
+            Self::InvalidLayerCount => "E0040",
+            Self::ExpressionTooDeep => "E0041",
+            Self::InvalidStepLimit => "E0042",
+            Self::TableTooLarge => "E0043",
+            Self::UnsupportedTableExport(_) => "E0044",
+            Self::UnsupportedVersion(_, _) => "E0045",
+
+            Self::UnreachableCode => "W0001",
+
+            Self::IntegerOverflow => "E1001",
+            Self::DivideByZero => "E1002",
+            Self::NegativeExponent => "E1003",
+            Self::ShiftOutOfRange => "E1004",
+            Self::NeighborOutOfRange => "E1005",
+            Self::VectorIndexOutOfRange => "E1006",
+            Self::CellStateOutOfRange => "E1007",
+            Self::StepLimitExceeded => "E1008",
+            Self::InvalidClampRange => "E1009",
+            Self::InvalidModulus => "E1010",
+            Self::InvalidRandomBound => "E1011",
+            Self::AssertionFailed(_) => "E1012",
+            Self::LayerOutOfRange => "E1013",
+
+            Self::Unimplemented => "E9001",
+            Self::UnknownError => "E9002",
+            Self::InternalError(_) => "E9003",
+        }
+    }
+    /// Returns true if this is a warning, which does not prevent compilation,
+    /// rather than an error.
+    pub fn is_warning(&self) -> bool {
+        self.code().starts_with('W')
+    }
     /// Attaches a span to this error message, returning a LangError.
     pub fn with_span(self, span: impl Into<Span>) -> LangError {
         LangError {
@@ -315,8 +744,8 @@ impl LangErrorMsg {
     }
 }
 
-impl<T: Into<LangErrorMsg>> From<T> for LangError {
-    fn from(msg: T) -> Self {
-        msg.into().without_span()
+impl From<LangErrorMsg> for LangError {
+    fn from(msg: LangErrorMsg) -> Self {
+        msg.without_span()
     }
 }
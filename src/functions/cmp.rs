@@ -8,7 +8,7 @@ use super::super::compiler::{Compiler, Value};
 use super::super::errors::*;
 use super::super::lexer::ComparisonToken;
 use super::super::{ConstValue, Span, Spanned, Type};
-use LangErrorMsg::CmpError;
+use LangErrorMsg::{CmpError, UnorderedType};
 
 /// Built-in function that performs some fixed number of comparisons.
 #[derive(Debug)]
@@ -28,7 +28,7 @@ impl Cmp {
     pub fn try_new(
         userfunc: &mut UserFunction,
         args: &Args,
-        comparisons: Vec<ComparisonToken>,
+        comparisons: Vec<Spanned<ComparisonToken>>,
     ) -> LangResult<Self> {
         let types: Vec<Spanned<Type>> = args
             .iter(userfunc)
@@ -57,7 +57,7 @@ impl Function for Cmp {
         FunctionKind::Operator
     }
     fn signatures(&self) -> Vec<FnSignature> {
-        vec![FnSignature::new(self.types.clone(), Type::Int)]
+        vec![FnSignature::new(self.types.clone(), Type::Bool)]
     }
     fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
         let old_bb = compiler.builder().get_insert_block().unwrap();
@@ -68,8 +68,8 @@ impl Function for Cmp {
         compiler.builder().position_at_end(merge_bb);
 
         // Create a phi node for the final result.
-        let int_type = compiler.int_type();
-        let phi = compiler.builder().build_phi(int_type, "multiCompareMerge");
+        let bool_type = compiler.bool_type();
+        let phi = compiler.builder().build_phi(bool_type, "multiCompareMerge");
 
         compiler.builder().position_at_end(old_bb);
         // Compile the first argument.
@@ -81,14 +81,14 @@ impl Function for Cmp {
             // Compare the arguments.
             let compare_result = (comparator.compile)(compiler, lhs, rhs.clone())?;
             // If the condition is false, skip ahead to the merge and give the
-            // phi node a value of 0. If it is true, continue on to check the
-            // next condition.
+            // phi node a value of false. If it is true, continue on to check
+            // the next condition.
             let next_bb = compiler.append_basic_block("compare");
             compiler
                 .builder()
                 .build_conditional_branch(compare_result, next_bb, merge_bb);
             phi.add_incoming(&[(
-                &int_type.const_zero(),
+                &bool_type.const_zero(),
                 compiler.builder().get_insert_block().unwrap(),
             )]);
             compiler.builder().position_at_end(next_bb);
@@ -97,11 +97,11 @@ impl Function for Cmp {
         }
 
         // After the last comparison, unconditionally jump directly to the merge
-        // block and give the phi node a value of 1 because all conditions were
-        // true.
+        // block and give the phi node a value of true because all conditions
+        // were true.
         compiler.builder().build_unconditional_branch(merge_bb);
         phi.add_incoming(&[(
-            &int_type.const_int(1, false),
+            &bool_type.const_int(1, false),
             compiler.builder().get_insert_block().unwrap(),
         )]);
 
@@ -109,9 +109,9 @@ impl Function for Cmp {
         // instructions.
         compiler.builder().position_at_end(merge_bb);
 
-        // This phi node now contains 1 if all conditions were true and 0 if all
-        // conditions were false.
-        Ok(Value::Int(phi.as_basic_value().into_int_value()))
+        // This phi node now contains true if all conditions were true and false
+        // otherwise.
+        Ok(Value::Bool(phi.as_basic_value().into_int_value()))
     }
     fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
         let mut lhs = args.const_eval(0)?;
@@ -121,7 +121,7 @@ impl Function for Cmp {
                 // It is possible to evaluate this comparison at compile time.
                 if !(const_eval_fn)(lhs, rhs.clone())? {
                     // Short-circuit if any comparison returns false.
-                    return Ok(Some(ConstValue::Int(0)));
+                    return Ok(Some(ConstValue::Bool(false)));
                 }
             } else {
                 // It is not possible to evaluate this comparison at compile
@@ -132,7 +132,7 @@ impl Function for Cmp {
             lhs = rhs;
         }
         // If all comparisons returned true, then return true.
-        Ok(Some(ConstValue::Int(1)))
+        Ok(Some(ConstValue::Bool(true)))
     }
 }
 
@@ -151,19 +151,27 @@ impl fmt::Debug for Comparator {
 impl Comparator {
     /// Constructs a new comparator that compares the given types using
     /// the given comparison token (if possible).
-    fn try_new(lhs: Spanned<Type>, cmp: ComparisonToken, rhs: Spanned<Type>) -> LangResult<Self> {
+    fn try_new(
+        lhs: Spanned<Type>,
+        cmp: Spanned<ComparisonToken>,
+        rhs: Spanned<Type>,
+    ) -> LangResult<Self> {
         // TODO: convert integers to vectors for comparison
         let span = Span::merge(lhs, rhs);
         let lhs = lhs.inner;
         let rhs = rhs.inner;
+        let cmp_span = cmp.span;
+        let cmp = cmp.inner;
         if lhs != rhs {
             return Err(CmpError { lhs, cmp, rhs }.with_span(span));
         }
         let ty = lhs;
-        let eq_only = cmp == ComparisonToken::Eql || cmp == ComparisonToken::Neq;
         match ty {
             Type::Int => Ok(Self::int_cmp(ty, cmp, true)),
-            Type::CellState if eq_only => Ok(Self::int_cmp(ty, cmp, false)),
+            Type::Bool | Type::CellState if cmp.is_eq_only() => Ok(Self::int_cmp(ty, cmp, false)),
+            // Booleans and cell states have no ordering, so point at the
+            // comparison operator itself.
+            Type::Bool | Type::CellState => Err(UnorderedType { ty, cmp }.with_span(cmp_span)),
             _ => Err(CmpError { lhs, cmp, rhs }.with_span(span)),
         }
     }
@@ -184,6 +192,9 @@ impl Comparator {
             }),
             const_eval: Some(match ty {
                 Type::Int => Box::new(move |lhs, rhs| Ok(cmp.eval(lhs.as_int()?, rhs.as_int()?))),
+                Type::Bool => {
+                    Box::new(move |lhs, rhs| Ok(cmp.eval(lhs.as_bool()?, rhs.as_bool()?)))
+                }
                 Type::CellState => Box::new(move |lhs, rhs| {
                     Ok(cmp.eval(lhs.as_cell_state()?, rhs.as_cell_state()?))
                 }),
@@ -82,3 +82,204 @@ impl Function for IntToCellState {
         }
     }
 }
+
+/// Built-in function that returns the cell state with the given ID modulo the
+/// number of cell states, so that any integer maps to a valid cell state. This
+/// never returns an error.
+#[derive(Debug)]
+pub struct WrapIntToCellState {
+    /// Rule metadata (used to determine the number of cell states).
+    rule_meta: Rc<RuleMeta>,
+}
+impl WrapIntToCellState {
+    /// Constructs a new WrapIntToCellState instance.
+    pub fn new(userfunc: &UserFunction) -> Self {
+        Self {
+            rule_meta: userfunc.rule_meta().clone(),
+        }
+    }
+}
+impl Function for WrapIntToCellState {
+    fn name(&self) -> String {
+        "state_wrap".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int], Type::CellState)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_int()?;
+        let int_type = compiler.int_type();
+        let zero = int_type.const_zero();
+        let cell_state_count_value = int_type.const_int(self.rule_meta.states.len() as u64, false);
+        let cell_state_type = compiler.cell_state_type();
+        let b = compiler.builder();
+        // The number of cell states is always positive, so this cannot divide
+        // by zero or overflow.
+        let rem = b.build_int_signed_rem(arg, cell_state_count_value, "tmp_rem");
+        // If the remainder is negative, add the number of cell states to it.
+        let rem_is_negative = b.build_int_compare(IntPredicate::SLT, rem, zero, "remIsNeg");
+        let adjusted_rem = b.build_int_add(rem, cell_state_count_value, "tmp_remPlusCount");
+        let id = b
+            .build_select(rem_is_negative, adjusted_rem, rem, "tmp_wrappedId")
+            .into_int_value();
+        Ok(Value::CellState(b.build_int_cast(
+            id,
+            cell_state_type,
+            "tmp_cellStateFromInt",
+        )))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let arg = args.const_eval(0)?.as_int()?;
+        let id = arg.rem_euclid(self.rule_meta.states.len() as LangInt);
+        Ok(Some(ConstValue::CellState(id as LangCellState)))
+    }
+}
+
+/// Built-in function that returns the cell state with the given ID clamped to
+/// the range of valid cell states, so that any integer maps to a valid cell
+/// state. This never returns an error.
+#[derive(Debug)]
+pub struct ClampIntToCellState {
+    /// Rule metadata (used to determine maximum cell state ID).
+    rule_meta: Rc<RuleMeta>,
+}
+impl ClampIntToCellState {
+    /// Constructs a new ClampIntToCellState instance.
+    pub fn new(userfunc: &UserFunction) -> Self {
+        Self {
+            rule_meta: userfunc.rule_meta().clone(),
+        }
+    }
+}
+impl Function for ClampIntToCellState {
+    fn name(&self) -> String {
+        format!("unary {:?} operator", OperatorToken::TagClamp.to_string())
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int], Type::CellState)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_int()?;
+        let int_type = compiler.int_type();
+        let zero = int_type.const_zero();
+        let max_id_value = int_type.const_int(self.rule_meta.states.len() as u64 - 1, false);
+        let cell_state_type = compiler.cell_state_type();
+        let b = compiler.builder();
+        // Clamp the value to at least zero.
+        let is_negative = b.build_int_compare(IntPredicate::SLT, arg, zero, "isNeg");
+        let id = b
+            .build_select(is_negative, zero, arg, "tmp_clampedLow")
+            .into_int_value();
+        // Clamp the value to at most the maximum cell state ID.
+        let is_too_large = b.build_int_compare(IntPredicate::SGT, id, max_id_value, "isTooLarge");
+        let id = b
+            .build_select(is_too_large, max_id_value, id, "tmp_clampedId")
+            .into_int_value();
+        Ok(Value::CellState(b.build_int_cast(
+            id,
+            cell_state_type,
+            "tmp_cellStateFromInt",
+        )))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let arg = args.const_eval(0)?.as_int()?;
+        let max_id = self.rule_meta.states.len() as LangInt - 1;
+        let id = std::cmp::min(std::cmp::max(arg, 0), max_id);
+        Ok(Some(ConstValue::CellState(id as LangCellState)))
+    }
+}
+
+/// Built-in function that returns the ID of a cell state as an integer. This
+/// struct can be constructed directly.
+#[derive(Debug, Clone)]
+pub struct CellStateToInt;
+impl Function for CellStateToInt {
+    fn name(&self) -> String {
+        "state_id".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::CellState], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        // Cell states are stored as unsigned integers, so just zero-extend.
+        let arg = args.compile(compiler, 0)?.as_cell_state()?;
+        let int_type = compiler.int_type();
+        Ok(Value::Int(compiler.builder().build_int_z_extend(
+            arg,
+            int_type,
+            "tmp_intFromCellState",
+        )))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let arg = args.const_eval(0)?.as_cell_state()?;
+        Ok(Some(ConstValue::Int(arg as LangInt)))
+    }
+}
+
+/// Built-in function that converts a boolean to an integer, returning 1 for
+/// true and 0 for false. This struct can be constructed directly.
+#[derive(Debug, Clone)]
+pub struct BoolToInt;
+impl Function for BoolToInt {
+    fn name(&self) -> String {
+        "int".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Bool], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_bool()?;
+        let int_type = compiler.int_type();
+        Ok(Value::Int(compiler.builder().build_int_z_extend(
+            arg,
+            int_type,
+            "tmp_intFromBool",
+        )))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let arg = args.const_eval(0)?.as_bool()?;
+        Ok(Some(ConstValue::Int(arg as LangInt)))
+    }
+}
+
+/// Built-in function that converts an integer to a boolean, returning true if
+/// the integer is nonzero and false otherwise. This struct can be constructed
+/// directly.
+#[derive(Debug, Clone)]
+pub struct IntToBool;
+impl Function for IntToBool {
+    fn name(&self) -> String {
+        "bool".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int], Type::Bool)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_int()?;
+        Ok(Value::Bool(compiler.builder().build_int_compare(
+            IntPredicate::NE,
+            arg,
+            arg.get_type().const_zero(),
+            "tmp_boolFromInt",
+        )))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let arg = args.const_eval(0)?.as_int()?;
+        Ok(Some(ConstValue::Bool(arg != 0)))
+    }
+}
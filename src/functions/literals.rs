@@ -3,7 +3,7 @@
 use super::super::ast::{ArgValues, FnSignature, Function, FunctionKind};
 use super::super::compiler::{Compiler, Value};
 use super::super::errors::*;
-use super::super::types::LangInt;
+use super::super::types::{LangCellState, LangInt};
 use super::super::{ConstValue, Type};
 
 /// Built-in function that returns a fixed integer. This struct can be
@@ -29,3 +29,51 @@ impl Function for Int {
         Ok(Some(ConstValue::Int(self.0)))
     }
 }
+
+/// Built-in function that returns a fixed boolean. This struct can be
+/// constructed directly.
+#[derive(Debug, Clone)]
+pub struct Bool(pub bool);
+impl Function for Bool {
+    fn name(&self) -> String {
+        "constant boolean".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Atom
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::atom(Type::Bool)]
+    }
+    fn compile(&self, compiler: &mut Compiler, _args: ArgValues) -> LangResult<Value> {
+        Ok(Value::Bool(
+            compiler.bool_type().const_int(self.0 as u64, false),
+        ))
+    }
+    fn const_eval(&self, _args: ArgValues) -> LangResult<Option<ConstValue>> {
+        Ok(Some(ConstValue::Bool(self.0)))
+    }
+}
+
+/// Built-in function that returns a fixed cell state. This struct can be
+/// constructed directly.
+#[derive(Debug, Clone)]
+pub struct CellState(pub LangCellState);
+impl Function for CellState {
+    fn name(&self) -> String {
+        "constant cell state".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Atom
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::atom(Type::CellState)]
+    }
+    fn compile(&self, compiler: &mut Compiler, _args: ArgValues) -> LangResult<Value> {
+        Ok(Value::CellState(
+            compiler.cell_state_type().const_int(self.0 as u64, false),
+        ))
+    }
+    fn const_eval(&self, _args: ArgValues) -> LangResult<Option<ConstValue>> {
+        Ok(Some(ConstValue::CellState(self.0)))
+    }
+}
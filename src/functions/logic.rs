@@ -0,0 +1,222 @@
+//! Logical functions.
+
+use super::super::ast::{ArgValues, Args, FnSignature, Function, FunctionKind, UserFunction};
+use super::super::compiler::{Compiler, Value};
+use super::super::errors::*;
+use super::super::lexer::KeywordToken;
+use super::super::{ConstValue, Span, Type};
+use LangErrorMsg::{InternalError, TypeError};
+
+/// Built-in function that performs logical NOT on a boolean. This struct can
+/// be constructed directly.
+#[derive(Debug, Clone)]
+pub struct LogicalNot;
+impl Function for LogicalNot {
+    fn name(&self) -> String {
+        format!("unary {:?} operator", KeywordToken::Not.to_string())
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Bool], Type::Bool)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_bool()?;
+        Ok(Value::Bool(
+            compiler.builder().build_not(arg, "tmp_logicalNot"),
+        ))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let arg = args.const_eval(0)?.as_bool()?;
+        Ok(Some(ConstValue::Bool(!arg)))
+    }
+}
+
+/// Built-in function that performs a fixed two-input logical operation on
+/// booleans.
+///
+/// `and` and `or` short-circuit; i.e. the second argument is only evaluated if
+/// the first one does not determine the result.
+#[derive(Debug)]
+pub struct LogicalBinaryOp {
+    /// Keyword signifying what operation to perform.
+    op: KeywordToken,
+}
+impl LogicalBinaryOp {
+    /// Constructs a new LogicalBinaryOp instance that performs the given
+    /// operation.
+    pub fn try_new(span: Span, op: KeywordToken) -> LangResult<Self> {
+        match op {
+            KeywordToken::And | KeywordToken::Or | KeywordToken::Xor => Ok(Self { op }),
+            _ => Err(InternalError("Invalid logical operator".into()).with_span(span)),
+        }
+    }
+}
+impl Function for LogicalBinaryOp {
+    fn name(&self) -> String {
+        format!("binary {:?} operator", self.op.to_string())
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Bool, Type::Bool], Type::Bool)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let bool_type = compiler.bool_type();
+
+        // Compile the first argument.
+        let lhs = args.compile(compiler, 0)?.as_bool()?;
+
+        if self.op == KeywordToken::Xor {
+            // XOR cannot short-circuit, so just compile the second argument.
+            let rhs = args.compile(compiler, 1)?.as_bool()?;
+            return Ok(Value::Bool(compiler.builder().build_xor(
+                lhs,
+                rhs,
+                "tmp_logicalXor",
+            )));
+        }
+
+        let lhs_bb = compiler.builder().get_insert_block().unwrap();
+        let rhs_bb = compiler.append_basic_block("logicalRhs");
+        // Build a basic block to skip to if the first argument determines the
+        // result.
+        let merge_bb = compiler.append_basic_block("logicalShortCircuit");
+
+        // The result if the operator short-circuits.
+        let short_circuit_value = match self.op {
+            KeywordToken::And => {
+                // If the first argument is false, skip the second one.
+                compiler
+                    .builder()
+                    .build_conditional_branch(lhs, rhs_bb, merge_bb);
+                bool_type.const_zero()
+            }
+            KeywordToken::Or => {
+                // If the first argument is true, skip the second one.
+                compiler
+                    .builder()
+                    .build_conditional_branch(lhs, merge_bb, rhs_bb);
+                bool_type.const_int(1, false)
+            }
+            _ => Err(InternalError("Uncaught invalid operator".into()).without_span())?,
+        };
+
+        // Compile the second argument, which determines the result if the
+        // operator did not short-circuit.
+        compiler.builder().position_at_end(rhs_bb);
+        let rhs = args.compile(compiler, 1)?.as_bool()?;
+        compiler.builder().build_unconditional_branch(merge_bb);
+        let rhs_end_bb = compiler.builder().get_insert_block().unwrap();
+
+        // Create a phi node for the final result.
+        compiler.builder().position_at_end(merge_bb);
+        let phi = compiler.builder().build_phi(bool_type, "logicalMerge");
+        phi.add_incoming(&[(&short_circuit_value, lhs_bb), (&rhs, rhs_end_bb)]);
+        Ok(Value::Bool(phi.as_basic_value().into_int_value()))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let lhs = args.const_eval(0)?.as_bool()?;
+        // Short-circuit if possible.
+        let result = match self.op {
+            KeywordToken::And if !lhs => false,
+            KeywordToken::Or if lhs => true,
+            KeywordToken::And | KeywordToken::Or => args.const_eval(1)?.as_bool()?,
+            KeywordToken::Xor => lhs != args.const_eval(1)?.as_bool()?,
+            _ => Err(InternalError("Uncaught invalid operator".into()).without_span())?,
+        };
+        Ok(Some(ConstValue::Bool(result)))
+    }
+}
+
+/// Built-in function that evaluates one of two values depending on whether a
+/// condition is true. Only the value that is selected is evaluated.
+#[derive(Debug)]
+pub struct Conditional {
+    /// Type of both possible values.
+    ty: Type,
+}
+impl Conditional {
+    /// Constructs a new Conditional instance that selects between the second
+    /// and third of the given arguments based on the first.
+    ///
+    /// This method checks the types of the arguments and returns an error if
+    /// the condition is not a boolean or the two values have different types.
+    pub fn try_new(userfunc: &mut UserFunction, args: &Args) -> LangResult<Self> {
+        let mut exprs = args.iter(userfunc);
+        let (cond_expr, if_true, if_false) = match (exprs.next(), exprs.next(), exprs.next()) {
+            (Some(c), Some(t), Some(f)) => (c, t, f),
+            _ => Err(InternalError("Invalid conditional expression".into()).without_span())?,
+        };
+        if cond_expr.return_type() != Type::Bool {
+            return Err(TypeError {
+                expected: Type::Bool,
+                got: cond_expr.return_type(),
+            }
+            .with_span(cond_expr.span()));
+        }
+        let ty = if_true.return_type();
+        if if_false.return_type() != ty {
+            return Err(TypeError {
+                expected: ty,
+                got: if_false.return_type(),
+            }
+            .with_span(if_false.span()));
+        }
+        Ok(Self { ty })
+    }
+}
+impl Function for Conditional {
+    fn name(&self) -> String {
+        "conditional expression".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(
+            vec![Type::Bool, self.ty, self.ty],
+            self.ty,
+        )]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let llvm_type = compiler.get_llvm_type(self.ty)?;
+        let condition = args.compile(compiler, 0)?.as_bool()?;
+
+        // Build the destination blocks.
+        let if_true_bb = compiler.append_basic_block("condTrue");
+        let if_false_bb = compiler.append_basic_block("condFalse");
+        let merge_bb = compiler.append_basic_block("endCond");
+        compiler
+            .builder()
+            .build_conditional_branch(condition, if_true_bb, if_false_bb);
+
+        // Compile each value in its own basic block, so that only the selected
+        // one is evaluated.
+        compiler.builder().position_at_end(if_true_bb);
+        let true_value = args.compile(compiler, 1)?.into_basic_value()?;
+        compiler.builder().build_unconditional_branch(merge_bb);
+        let true_end_bb = compiler.builder().get_insert_block().unwrap();
+
+        compiler.builder().position_at_end(if_false_bb);
+        let false_value = args.compile(compiler, 2)?.into_basic_value()?;
+        compiler.builder().build_unconditional_branch(merge_bb);
+        let false_end_bb = compiler.builder().get_insert_block().unwrap();
+
+        // Create a phi node for the final result.
+        compiler.builder().position_at_end(merge_bb);
+        let phi = compiler.builder().build_phi(llvm_type, "condMerge");
+        phi.add_incoming(&[(&true_value, true_end_bb), (&false_value, false_end_bb)]);
+        Ok(Value::from_basic_value(self.ty, phi.as_basic_value()))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        // Only evaluate the selected value.
+        if args.const_eval(0)?.as_bool()? {
+            args.const_eval(1).map(Some)
+        } else {
+            args.const_eval(2).map(Some)
+        }
+    }
+}
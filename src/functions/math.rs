@@ -1,20 +1,27 @@
 //! Math functions.
 
+use inkwell::IntPredicate;
 use std::convert::TryInto;
 
 use super::super::ast::{
-    ArgValues, ErrorPointRef, FnSignature, Function, FunctionKind, UserFunction,
+    ArgTypes, ArgValues, ArithmeticMode, ErrorPointRef, FnSignature, Function, FunctionKind,
+    UserFunction,
 };
 use super::super::compiler::{Compiler, Value};
 use super::super::errors::*;
 use super::super::lexer::OperatorToken;
-use super::super::types::LangInt;
+use super::super::types::{LangInt, INT_BITS};
 use super::super::{ConstValue, Span, Type};
-use LangErrorMsg::{DivideByZero, IntegerOverflow, InternalError, NegativeExponent};
+use LangErrorMsg::{
+    DivideByZero, IntegerOverflow, InternalError, InvalidClampRange, InvalidModulus,
+    NegativeExponent, ShiftOutOfRange,
+};
 
 /// Built-in function that negates an integer.
 #[derive(Debug)]
 pub struct NegInt {
+    /// Whether overflow wraps around instead of returning an error.
+    wrapping: bool,
     /// Error returned if overflow occurs.
     overflow_error: ErrorPointRef,
 }
@@ -22,6 +29,7 @@ impl NegInt {
     /// Returns a new NegInt instance.
     pub fn try_new(userfunc: &mut UserFunction, span: Span) -> LangResult<Self> {
         Ok(Self {
+            wrapping: userfunc.rule_meta().arithmetic == ArithmeticMode::Wrapping,
             overflow_error: userfunc.add_error_point(IntegerOverflow.with_span(span)),
         })
     }
@@ -38,44 +46,522 @@ impl Function for NegInt {
     }
     fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
         let arg = args.compile(compiler, 0)?.as_int()?;
+        let zero = compiler.int_type().const_zero();
         // To negate an integer, subtract it from zero.
+        if self.wrapping {
+            return Ok(Value::Int(
+                compiler.builder().build_int_sub(zero, arg, "tmp_neg"),
+            ));
+        }
         Ok(Value::Int(compiler.build_checked_int_arithmetic(
-            compiler.int_type().const_zero(),
+            zero,
             arg,
             "ssub",
             |c| Ok(self.overflow_error.compile(c)),
         )?))
     }
     fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
-        match args.const_eval(0)?.as_int()?.checked_neg() {
+        let arg = args.const_eval(0)?.as_int()?;
+        if self.wrapping {
+            return Ok(Some(ConstValue::Int(arg.wrapping_neg())));
+        }
+        match arg.checked_neg() {
             Some(result) => Ok(Some(ConstValue::Int(result))),
             None => self.overflow_error.err(),
         }
     }
 }
 
+/// Built-in function that returns an integer unchanged (unary plus). This
+/// struct can be constructed directly.
+#[derive(Debug, Clone)]
+pub struct PosInt;
+impl Function for PosInt {
+    fn name(&self) -> String {
+        format!("unary {:?} operator", OperatorToken::Plus.to_string())
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        args.compile(compiler, 0)
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        Ok(Some(ConstValue::Int(args.const_eval(0)?.as_int()?)))
+    }
+}
+
+/// Built-in function that performs bitwise NOT on an integer. This struct can
+/// be constructed directly.
+#[derive(Debug, Clone)]
+pub struct BitwiseNotInt;
+impl Function for BitwiseNotInt {
+    fn name(&self) -> String {
+        format!("unary {:?} operator", OperatorToken::Tilde.to_string())
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_int()?;
+        Ok(Value::Int(compiler.builder().build_not(arg, "tmp_not")))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        Ok(Some(ConstValue::Int(!args.const_eval(0)?.as_int()?)))
+    }
+}
+
+/// Built-in function that returns the sign of an integer (-1, 0, or 1). This
+/// struct can be constructed directly.
+#[derive(Debug, Clone)]
+pub struct Sign;
+impl Function for Sign {
+    fn name(&self) -> String {
+        "sign".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg = args.compile(compiler, 0)?.as_int()?;
+        let int_type = compiler.int_type();
+        let zero = int_type.const_zero();
+        let b = compiler.builder();
+        // Compute `int(x > 0) - int(x < 0)`, which cannot overflow.
+        let is_positive = b.build_int_compare(IntPredicate::SGT, arg, zero, "isPos");
+        let is_negative = b.build_int_compare(IntPredicate::SLT, arg, zero, "isNeg");
+        let is_positive = b.build_int_z_extend(is_positive, int_type, "tmp_intFromIsPos");
+        let is_negative = b.build_int_z_extend(is_negative, int_type, "tmp_intFromIsNeg");
+        Ok(Value::Int(b.build_int_sub(
+            is_positive,
+            is_negative,
+            "tmp_sign",
+        )))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        Ok(Some(ConstValue::Int(
+            args.const_eval(0)?.as_int()?.signum(),
+        )))
+    }
+}
+
+/// Built-in function that returns the minimum or maximum of two or more
+/// integers. This enum can be constructed directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinMax {
+    /// Returns the smallest argument.
+    Min,
+    /// Returns the largest argument.
+    Max,
+}
+impl Function for MinMax {
+    fn name(&self) -> String {
+        match self {
+            Self::Min => "min".to_owned(),
+            Self::Max => "max".to_owned(),
+        }
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int, Type::Int], Type::Int)]
+    }
+    fn get_signature(&self, args: &ArgTypes) -> Option<FnSignature> {
+        // Accept any number of integers, as long as there are at least two.
+        if args.iter().count() >= 2 && args.iter().all(|ty| *ty == Type::Int) {
+            Some(FnSignature::new(args.clone(), Type::Int))
+        } else {
+            None
+        }
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let predicate = match self {
+            Self::Min => IntPredicate::SLT,
+            Self::Max => IntPredicate::SGT,
+        };
+        let mut result = args.compile(compiler, 0)?.as_int()?;
+        for i in 1..args.len() {
+            let arg = args.compile(compiler, i)?.as_int()?;
+            let keep_old = compiler
+                .builder()
+                .build_int_compare(predicate, result, arg, "keepOld");
+            result = compiler
+                .builder()
+                .build_select(keep_old, result, arg, "tmp_minMax")
+                .into_int_value();
+        }
+        Ok(Value::Int(result))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let mut result = args.const_eval(0)?.as_int()?;
+        for i in 1..args.len() {
+            let arg = args.const_eval(i)?.as_int()?;
+            result = match self {
+                Self::Min => result.min(arg),
+                Self::Max => result.max(arg),
+            };
+        }
+        Ok(Some(ConstValue::Int(result)))
+    }
+}
+
+/// Built-in function that clamps an integer to a range.
+#[derive(Debug)]
+pub struct Clamp {
+    /// Error returned if the lower bound is greater than the upper bound.
+    invalid_range_error: ErrorPointRef,
+}
+impl Clamp {
+    /// Returns a new Clamp instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span) -> LangResult<Self> {
+        Ok(Self {
+            invalid_range_error: userfunc.add_error_point(InvalidClampRange.with_span(span)),
+        })
+    }
+}
+impl Function for Clamp {
+    fn name(&self) -> String {
+        "clamp".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(
+            vec![Type::Int, Type::Int, Type::Int],
+            Type::Int,
+        )]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let x = args.compile(compiler, 0)?.as_int()?;
+        let lo = args.compile(compiler, 1)?.as_int()?;
+        let hi = args.compile(compiler, 2)?.as_int()?;
+        // Check that the range is valid.
+        let is_invalid_range =
+            compiler
+                .builder()
+                .build_int_compare(IntPredicate::SGT, lo, hi, "isInvalidClampRange");
+        compiler.build_conditional(
+            is_invalid_range,
+            |c| Ok(self.invalid_range_error.compile(c)),
+            |_| Ok(()),
+        )?;
+        let b = compiler.builder();
+        let is_below = b.build_int_compare(IntPredicate::SLT, x, lo, "isBelow");
+        let is_above = b.build_int_compare(IntPredicate::SGT, x, hi, "isAbove");
+        let upper_clamped = b.build_select(is_above, hi, x, "tmp_clampHi");
+        Ok(Value::Int(
+            b.build_select(is_below, lo.into(), upper_clamped, "tmp_clamp")
+                .into_int_value(),
+        ))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let x = args.const_eval(0)?.as_int()?;
+        let lo = args.const_eval(1)?.as_int()?;
+        let hi = args.const_eval(2)?.as_int()?;
+        if lo > hi {
+            self.invalid_range_error.err()
+        } else {
+            Ok(Some(ConstValue::Int(x.max(lo).min(hi))))
+        }
+    }
+}
+
+/// Built-in function that adds, subtracts, or multiplies two integers,
+/// clamping the result to the range of integers instead of overflowing. This
+/// enum can be constructed directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaturatingOp {
+    /// Saturating addition.
+    Add,
+    /// Saturating subtraction.
+    Sub,
+    /// Saturating multiplication.
+    Mul,
+}
+impl Function for SaturatingOp {
+    fn name(&self) -> String {
+        match self {
+            Self::Add => "saturating_add".to_owned(),
+            Self::Sub => "saturating_sub".to_owned(),
+            Self::Mul => "saturating_mul".to_owned(),
+        }
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int, Type::Int], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let lhs = args.compile(compiler, 0)?.as_int()?;
+        let rhs = args.compile(compiler, 1)?.as_int()?;
+        let intrinsic = match self {
+            Self::Add => "sadd",
+            Self::Sub => "ssub",
+            Self::Mul => "smul",
+        };
+        let (result, is_overflow) =
+            compiler.build_int_arithmetic_with_overflow(lhs, rhs, intrinsic)?;
+
+        let int_type = compiler.int_type();
+        let zero = int_type.const_zero();
+        let min_value = int_type.const_int(LangInt::min_value() as u64, true);
+        let max_value = int_type.const_int(LangInt::max_value() as u64, true);
+        let b = compiler.builder();
+        // Determine which way the result overflows, if it does.
+        let is_negative_overflow = match self {
+            // Adding a negative number can only overflow downward.
+            Self::Add => b.build_int_compare(IntPredicate::SLT, rhs, zero, "rhsIsNeg"),
+            // Subtracting a positive number can only overflow downward.
+            Self::Sub => b.build_int_compare(IntPredicate::SGT, rhs, zero, "rhsIsPos"),
+            // Multiplying numbers with different signs can only overflow
+            // downward.
+            Self::Mul => {
+                let sign_bits = b.build_xor(lhs, rhs, "signBits");
+                b.build_int_compare(IntPredicate::SLT, sign_bits, zero, "signsDiffer")
+            }
+        };
+        let saturated = b.build_select(is_negative_overflow, min_value, max_value, "saturated");
+        Ok(Value::Int(
+            b.build_select(
+                is_overflow,
+                saturated.into_int_value(),
+                result,
+                "tmp_saturating",
+            )
+            .into_int_value(),
+        ))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let lhs = args.const_eval(0)?.as_int()?;
+        let rhs = args.const_eval(1)?.as_int()?;
+        Ok(Some(ConstValue::Int(match self {
+            Self::Add => lhs.saturating_add(rhs),
+            Self::Sub => lhs.saturating_sub(rhs),
+            Self::Mul => lhs.saturating_mul(rhs),
+        })))
+    }
+}
+
+/// Built-in function that computes the greatest common divisor or least common
+/// multiple of two integers. The result is never negative.
+#[derive(Debug)]
+pub struct GcdLcm {
+    /// Whether to compute the least common multiple instead of the greatest
+    /// common divisor.
+    lcm: bool,
+    /// Error returned if overflow occurs.
+    overflow_error: ErrorPointRef,
+}
+impl GcdLcm {
+    /// Returns a new GcdLcm instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span, lcm: bool) -> LangResult<Self> {
+        Ok(Self {
+            lcm,
+            overflow_error: userfunc.add_error_point(IntegerOverflow.with_span(span)),
+        })
+    }
+}
+impl Function for GcdLcm {
+    fn name(&self) -> String {
+        if self.lcm {
+            "lcm".to_owned()
+        } else {
+            "gcd".to_owned()
+        }
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::Int, Type::Int], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let lhs = args.compile(compiler, 0)?.as_int()?;
+        let rhs = args.compile(compiler, 1)?.as_int()?;
+        // Work with unsigned absolute values, since the absolute value of the
+        // minimum integer does not fit in a signed integer.
+        let lhs = compiler.build_unsigned_abs(lhs);
+        let rhs = compiler.build_unsigned_abs(rhs);
+        let gcd = compiler.build_unsigned_gcd(lhs, rhs);
+        let zero = compiler.int_type().const_zero();
+        let one = compiler.int_type().const_int(1, false);
+        let result = if self.lcm {
+            // Divide before multiplying to avoid unnecessary overflow. The GCD
+            // is only zero if both inputs are zero, in which case the result
+            // is zero no matter what we divide by.
+            let b = compiler.builder();
+            let gcd_is_zero = b.build_int_compare(IntPredicate::EQ, gcd, zero, "gcdIsZero");
+            let divisor = b
+                .build_select(gcd_is_zero, one, gcd, "lcmDivisor")
+                .into_int_value();
+            let quotient = b.build_int_unsigned_div(lhs, divisor, "lcmQuotient");
+            let (product, is_overflow) =
+                compiler.build_int_arithmetic_with_overflow(quotient, rhs, "umul")?;
+            compiler.build_conditional(
+                is_overflow,
+                |c| Ok(self.overflow_error.compile(c)),
+                |_| Ok(()),
+            )?;
+            product
+        } else {
+            gcd
+        };
+        // The result is unsigned, so it overflows if it is too large to be a
+        // signed integer.
+        let is_overflow =
+            compiler
+                .builder()
+                .build_int_compare(IntPredicate::SLT, result, zero, "isOverflow");
+        compiler.build_conditional(
+            is_overflow,
+            |c| Ok(self.overflow_error.compile(c)),
+            |_| Ok(()),
+        )?;
+        Ok(Value::Int(result))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let lhs = args.const_eval(0)?.as_int()?.wrapping_abs() as u64;
+        let rhs = args.const_eval(1)?.as_int()?.wrapping_abs() as u64;
+        let gcd = unsigned_gcd(lhs, rhs);
+        let result = if self.lcm {
+            if gcd == 0 {
+                Some(0)
+            } else {
+                (lhs / gcd).checked_mul(rhs)
+            }
+        } else {
+            Some(gcd)
+        };
+        result
+            .and_then(|i| i.try_into().ok())
+            .ok_or_else(|| self.overflow_error.error())
+            .map(ConstValue::Int)
+            .map(Some)
+    }
+}
+
+/// Built-in function that raises an integer to a non-negative integer power
+/// modulo a positive integer. The result is never negative.
+#[derive(Debug)]
+pub struct PowMod {
+    /// Error returned if the exponent is negative.
+    negative_exponent_error: ErrorPointRef,
+    /// Error returned if the modulus is not positive.
+    invalid_modulus_error: ErrorPointRef,
+}
+impl PowMod {
+    /// Returns a new PowMod instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span) -> LangResult<Self> {
+        Ok(Self {
+            negative_exponent_error: userfunc.add_error_point(NegativeExponent.with_span(span)),
+            invalid_modulus_error: userfunc.add_error_point(InvalidModulus.with_span(span)),
+        })
+    }
+}
+impl Function for PowMod {
+    fn name(&self) -> String {
+        "powmod".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(
+            vec![Type::Int, Type::Int, Type::Int],
+            Type::Int,
+        )]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let base = args.compile(compiler, 0)?.as_int()?;
+        let exponent = args.compile(compiler, 1)?.as_int()?;
+        let modulus = args.compile(compiler, 2)?.as_int()?;
+        let zero = compiler.int_type().const_zero();
+        // Check the exponent and modulus.
+        let is_negative_exponent = compiler.builder().build_int_compare(
+            IntPredicate::SLT,
+            exponent,
+            zero,
+            "isNegExponent",
+        );
+        compiler.build_conditional(
+            is_negative_exponent,
+            |c| Ok(self.negative_exponent_error.compile(c)),
+            |_| Ok(()),
+        )?;
+        let is_invalid_modulus = compiler.builder().build_int_compare(
+            IntPredicate::SLE,
+            modulus,
+            zero,
+            "isInvalidModulus",
+        );
+        compiler.build_conditional(
+            is_invalid_modulus,
+            |c| Ok(self.invalid_modulus_error.compile(c)),
+            |_| Ok(()),
+        )?;
+        Ok(Value::Int(compiler.build_powmod(base, exponent, modulus)))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let base = args.const_eval(0)?.as_int()?;
+        let exponent = args.const_eval(1)?.as_int()?;
+        let modulus = args.const_eval(2)?.as_int()?;
+        if exponent < 0 {
+            self.negative_exponent_error.err()
+        } else if modulus <= 0 {
+            self.invalid_modulus_error.err()
+        } else {
+            Ok(Some(ConstValue::Int(powmod(base, exponent, modulus))))
+        }
+    }
+}
+
 /// Built-in function that performs a fixed two-input integer math operation.
 #[derive(Debug)]
 pub struct BinaryIntOp {
     /// Token signifying what operation to perform.
     op: OperatorToken,
+    /// Whether overflow wraps around instead of returning an error.
+    wrapping: bool,
     /// Error returned if overflow occurs.
     overflow_error: Option<ErrorPointRef>,
     /// Error returned if the divisor of an operation is negative.
     div_by_zero_error: Option<ErrorPointRef>,
     /// Error returned if the exponent is negative.
     negative_exponent_error: Option<ErrorPointRef>,
+    /// Error returned if the shift amount is negative or too large.
+    shift_out_of_range_error: Option<ErrorPointRef>,
 }
 impl BinaryIntOp {
     /// Constructs a new BinaryIntOp instance that performs the given operation.
     pub fn try_new(userfunc: &mut UserFunction, span: Span, op: OperatorToken) -> LangResult<Self> {
         use OperatorToken::*;
-        let overflow_error = if matches!(op, Plus | Minus | Asterisk | Slash | Percent) {
+        let overflow_error = if matches!(
+            op,
+            Plus | Minus
+                | Asterisk
+                | Slash
+                | Percent
+                | DoublePercent
+                | SlashPercent
+                | DoubleAsterisk
+        ) {
             Some(userfunc.add_error_point(IntegerOverflow.with_span(span)))
         } else {
             None
         };
-        let div_by_zero_error = if matches!(op, Slash | Percent) {
+        let div_by_zero_error = if matches!(op, Slash | Percent | DoublePercent | SlashPercent) {
             Some(userfunc.add_error_point(DivideByZero.with_span(span)))
         } else {
             None
@@ -85,11 +571,19 @@ impl BinaryIntOp {
         } else {
             None
         };
+        let shift_out_of_range_error =
+            if matches!(op, DoubleLessThan | DoubleGreaterThan | TripleGreaterThan) {
+                Some(userfunc.add_error_point(ShiftOutOfRange.with_span(span)))
+            } else {
+                None
+            };
         Ok(Self {
             op,
+            wrapping: userfunc.rule_meta().arithmetic == ArithmeticMode::Wrapping,
             overflow_error,
             div_by_zero_error,
             negative_exponent_error,
+            shift_out_of_range_error,
         })
     }
     /// Returns the OverflowError error point; panics if this function cannot
@@ -107,6 +601,11 @@ impl BinaryIntOp {
     fn negative_exponent_error(&self) -> &ErrorPointRef {
         self.negative_exponent_error.as_ref().unwrap()
     }
+    /// Returns the ShiftOutOfRange error point; panics if this function cannot
+    /// return an Err(ShiftOutOfRange).
+    fn shift_out_of_range_error(&self) -> &ErrorPointRef {
+        self.shift_out_of_range_error.as_ref().unwrap()
+    }
 }
 impl Function for BinaryIntOp {
     fn name(&self) -> String {
@@ -126,6 +625,12 @@ impl Function for BinaryIntOp {
         // Perform the operation.
         Ok(Value::Int(match self.op {
             // Addition, subtraction, and multiplication
+            Plus | Minus | Asterisk if self.wrapping => match self.op {
+                Plus => b.build_int_add(lhs, rhs, "tmp_add"),
+                Minus => b.build_int_sub(lhs, rhs, "tmp_sub"),
+                Asterisk => b.build_int_mul(lhs, rhs, "tmp_mul"),
+                _ => unreachable!(),
+            },
             Plus | Minus | Asterisk => {
                 let intrinsic = match self.op {
                     Plus => "sadd",     // Addition
@@ -137,31 +642,106 @@ impl Function for BinaryIntOp {
                     Ok(self.overflow_error().compile(c))
                 })?
             }
-            // Division and remainder (TODO: use euclidean div and modulo)
-            Slash | Percent => {
+            // Division, remainder, modulo, and floored division
+            Slash | Percent | DoublePercent | SlashPercent => {
                 // Check for overflow and division by zero.
-                compiler.build_div_check(
-                    lhs,
-                    rhs,
-                    |c| Ok(self.overflow_error().compile(c)),
-                    |c| Ok(self.div_by_zero_error().compile(c)),
-                )?;
+                let rhs = if self.wrapping {
+                    compiler.build_wrapping_div_check(lhs, rhs, |c| {
+                        Ok(self.div_by_zero_error().compile(c))
+                    })?
+                } else {
+                    compiler.build_div_check(
+                        lhs,
+                        rhs,
+                        |c| Ok(self.overflow_error().compile(c)),
+                        |c| Ok(self.div_by_zero_error().compile(c)),
+                    )?;
+                    rhs
+                };
                 match self.op {
                     // Division
                     Slash => compiler.builder().build_int_signed_div(lhs, rhs, "tmp_div"),
                     // Remainder
                     Percent => compiler.builder().build_int_signed_rem(lhs, rhs, "tmp_rem"),
+                    // Modulo (always nonnegative)
+                    DoublePercent => {
+                        let b = compiler.builder();
+                        let zero = lhs.get_type().const_zero();
+                        let rem = b.build_int_signed_rem(lhs, rhs, "tmp_rem");
+                        // If the remainder is negative, add the absolute value
+                        // of the divisor to it. This cannot overflow.
+                        let rem_is_negative =
+                            b.build_int_compare(IntPredicate::SLT, rem, zero, "remIsNeg");
+                        let divisor_is_negative =
+                            b.build_int_compare(IntPredicate::SLT, rhs, zero, "divisorIsNeg");
+                        let adjusted_rem = b.build_select(
+                            divisor_is_negative,
+                            b.build_int_sub(rem, rhs, "tmp_remMinusDivisor"),
+                            b.build_int_add(rem, rhs, "tmp_remPlusDivisor"),
+                            "tmp_adjustedRem",
+                        );
+                        b.build_select(rem_is_negative, adjusted_rem, rem.into(), "tmp_mod")
+                            .into_int_value()
+                    }
+                    // Floored division (rounds toward negative infinity)
+                    SlashPercent => {
+                        let b = compiler.builder();
+                        let zero = lhs.get_type().const_zero();
+                        let one = lhs.get_type().const_int(1, false);
+                        let quotient = b.build_int_signed_div(lhs, rhs, "tmp_div");
+                        let rem = b.build_int_signed_rem(lhs, rhs, "tmp_rem");
+                        // If the remainder is nonzero and has a different sign
+                        // than the divisor, then the quotient was rounded up,
+                        // so subtract one. This cannot overflow.
+                        let rem_is_nonzero =
+                            b.build_int_compare(IntPredicate::NE, rem, zero, "remIsNonzero");
+                        let signs_differ = b.build_int_compare(
+                            IntPredicate::SLT,
+                            b.build_xor(rem, rhs, "tmp_remXorDivisor"),
+                            zero,
+                            "signsDiffer",
+                        );
+                        let should_adjust = b.build_and(rem_is_nonzero, signs_differ, "adjust");
+                        b.build_select(
+                            should_adjust,
+                            b.build_int_sub(quotient, one, "tmp_adjustedDiv"),
+                            quotient,
+                            "tmp_floorDiv",
+                        )
+                        .into_int_value()
+                    }
                     _ => unreachable!(),
                 }
             }
             // Exponentiation
-            DoubleAsterisk => todo!("Exponentiation"),
-            // Bitshift left
-            DoubleLessThan => b.build_left_shift(lhs, rhs, "tmp_shl"),
-            // Bitshift right (arithmetic)
-            DoubleGreaterThan => b.build_right_shift(lhs, rhs, true, "tmp_ashr"),
-            // Bitshift right (logical)
-            TripleGreaterThan => b.build_right_shift(lhs, rhs, false, "tmp_lshr"),
+            DoubleAsterisk => compiler.build_checked_int_pow(
+                lhs,
+                rhs,
+                |c| {
+                    // The result has already wrapped around.
+                    if !self.wrapping {
+                        self.overflow_error().compile(c);
+                    }
+                    Ok(())
+                },
+                |c| Ok(self.negative_exponent_error().compile(c)),
+            )?,
+            // Bitshifts
+            DoubleLessThan | DoubleGreaterThan | TripleGreaterThan => {
+                // Check that the shift amount is in range.
+                compiler
+                    .build_shift_check(rhs, |c| Ok(self.shift_out_of_range_error().compile(c)))?;
+                let b = compiler.builder();
+                match self.op {
+                    // Bitshift left
+                    DoubleLessThan => b.build_left_shift(lhs, rhs, "tmp_shl"),
+                    // Bitshift right (arithmetic)
+                    DoubleGreaterThan => b.build_right_shift(lhs, rhs, true, "tmp_ashr"),
+                    // Bitshift right (logical)
+                    TripleGreaterThan => b.build_right_shift(lhs, rhs, false, "tmp_lshr"),
+                    _ => unreachable!(),
+                }
+            }
             // Bitwise AND
             Ampersand => b.build_and(lhs, rhs, "tmp_and"),
             // Bitwise OR
@@ -178,20 +758,34 @@ impl Function for BinaryIntOp {
         use OperatorToken::*;
         // Perform the operation.
         match self.op {
+            // Wrapping addition, subtraction, and multiplication
+            Plus if self.wrapping => Some(lhs.wrapping_add(rhs)),
+            Minus if self.wrapping => Some(lhs.wrapping_sub(rhs)),
+            Asterisk if self.wrapping => Some(lhs.wrapping_mul(rhs)),
             // Addition
             Plus => lhs.checked_add(rhs),
             // Subtraction
             Minus => lhs.checked_sub(rhs),
             // Multiplication
             Asterisk => lhs.checked_mul(rhs),
-            // Division and remainder (TODO: use euclidean div and modulo)
-            Slash | Percent => {
+            // Division, remainder, modulo, and floored division
+            Slash | Percent | DoublePercent | SlashPercent => {
                 if rhs == 0 {
                     self.div_by_zero_error().err()?
+                } else if self.wrapping {
+                    match self.op {
+                        Slash => Some(lhs.wrapping_div(rhs)),
+                        Percent => Some(lhs.wrapping_rem(rhs)),
+                        DoublePercent => Some(lhs.wrapping_rem_euclid(rhs)),
+                        SlashPercent => Some(wrapping_floor_div(lhs, rhs)),
+                        _ => unreachable!(),
+                    }
                 } else {
                     match self.op {
                         Slash => lhs.checked_div(rhs),
                         Percent => lhs.checked_rem(rhs),
+                        DoublePercent => lhs.checked_rem_euclid(rhs),
+                        SlashPercent => checked_floor_div(lhs, rhs),
                         _ => unreachable!(),
                     }
                 }
@@ -200,16 +794,29 @@ impl Function for BinaryIntOp {
             DoubleAsterisk => {
                 if rhs < 0 {
                     self.negative_exponent_error().err()?
+                } else if self.wrapping {
+                    Some(wrapping_pow(lhs, rhs))
                 } else {
-                    rhs.try_into().ok().map(|exp| lhs.pow(exp))
+                    checked_pow(lhs, rhs)
+                }
+            }
+            // Bitshifts
+            DoubleLessThan | DoubleGreaterThan | TripleGreaterThan => {
+                if rhs < 0 || rhs >= INT_BITS as LangInt {
+                    self.shift_out_of_range_error().err()?
+                } else {
+                    let rhs = rhs as u32;
+                    match self.op {
+                        // Bitshift left
+                        DoubleLessThan => lhs.checked_shl(rhs),
+                        // Bitshift right (arithmetic)
+                        DoubleGreaterThan => lhs.checked_shr(rhs),
+                        // Bitshift right (logical)
+                        TripleGreaterThan => (lhs as u64).checked_shr(rhs).map(|i| i as LangInt),
+                        _ => unreachable!(),
+                    }
                 }
             }
-            // Bitshift left
-            DoubleLessThan => Some(lhs << rhs),
-            // Bitshift right (arithmetic)
-            DoubleGreaterThan => Some(lhs >> rhs),
-            // Bitshift right (logical)
-            TripleGreaterThan => Some((lhs as u64 >> rhs) as LangInt),
             // Bitwise AND
             Ampersand => Some(lhs & rhs),
             // Bitwise OR
@@ -225,3 +832,82 @@ impl Function for BinaryIntOp {
         .map(Some)
     }
 }
+
+/// Returns `lhs / rhs` rounded toward negative infinity, or None if the
+/// result overflows or `rhs` is zero.
+fn checked_floor_div(lhs: LangInt, rhs: LangInt) -> Option<LangInt> {
+    let quotient = lhs.checked_div(rhs)?;
+    if lhs % rhs != 0 && (lhs < 0) != (rhs < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// Returns `lhs / rhs` rounded toward negative infinity, wrapping around on
+/// overflow. Panics if `rhs` is zero.
+fn wrapping_floor_div(lhs: LangInt, rhs: LangInt) -> LangInt {
+    let quotient = lhs.wrapping_div(rhs);
+    if lhs.wrapping_rem(rhs) != 0 && (lhs < 0) != (rhs < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+/// Raises an integer to a non-negative integer power, returning None if
+/// overflow occurs.
+fn checked_pow(base: LangInt, exponent: LangInt) -> Option<LangInt> {
+    match exponent.try_into() {
+        Ok(exp) => base.checked_pow(exp),
+        // The exponent is too large to fit in a u32, so the result overflows
+        // unless the base is -1, 0, or 1.
+        Err(_) => match base {
+            -1 if exponent % 2 == 0 => Some(1),
+            -1 => Some(-1),
+            0 | 1 => Some(base),
+            _ => None,
+        },
+    }
+}
+
+/// Returns the greatest common divisor of two unsigned integers using the
+/// Euclidean algorithm.
+fn unsigned_gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let rem = a % b;
+        a = b;
+        b = rem;
+    }
+    a
+}
+
+/// Raises an integer to a non-negative integer power modulo a positive integer.
+fn powmod(base: LangInt, mut exponent: LangInt, modulus: LangInt) -> LangInt {
+    // Use double-width integers so that intermediate products cannot overflow.
+    let modulus = modulus as i128;
+    let mut base = (base as i128).rem_euclid(modulus);
+    let mut result = 1 % modulus;
+    while exponent > 0 {
+        if exponent & 1 != 0 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+    result as LangInt
+}
+
+/// Raises an integer to a non-negative integer power, wrapping around on
+/// overflow.
+fn wrapping_pow(mut base: LangInt, mut exponent: LangInt) -> LangInt {
+    let mut result: LangInt = 1;
+    while exponent > 0 {
+        if exponent & 1 != 0 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
@@ -15,7 +15,7 @@ impl GetVar {
     /// Returns a new GetVar instance that returns the value of the variable
     /// with the given name.
     pub fn try_new(userfunc: &mut UserFunction, span: Span, var_name: String) -> LangResult<Self> {
-        let var_type = userfunc.try_get_var(span, &var_name)?;
+        let (var_name, var_type) = userfunc.try_get_var(span, &var_name)?;
         Ok(Self { var_name, var_type })
     }
 }
@@ -30,8 +30,59 @@ impl Function for GetVar {
         vec![FnSignature::atom(self.var_type)]
     }
     fn compile(&self, compiler: &mut Compiler, _args: ArgValues) -> LangResult<Value> {
-        let var_ptr = compiler.vars()[&self.var_name].ptr;
+        let var_ptr = compiler
+            .vars()
+            .get(&self.var_name)
+            .ok_or_else(|| LangErrorMsg::InternalError("Variable not found".into()).without_span())?
+            .ptr;
         let value = compiler.builder().build_load(var_ptr, &self.var_name);
         Ok(Value::from_basic_value(self.var_type, value))
     }
 }
+
+/// Built-in function that calls a helper function.
+#[derive(Debug, Clone)]
+pub struct CallHelperFunction {
+    /// Name of the helper function.
+    pub func_name: String,
+    /// Signature of the helper function.
+    pub signature: FnSignature,
+}
+impl CallHelperFunction {
+    /// Returns a new CallHelperFunction instance that calls the helper function
+    /// with the given name, or None if there is no such helper function.
+    pub fn try_new(userfunc: &UserFunction, func_name: &str) -> Option<Self> {
+        let signature = userfunc
+            .rule_meta()
+            .helper_function_signatures
+            .get(func_name)?
+            .clone();
+        Some(Self {
+            func_name: func_name.to_owned(),
+            signature,
+        })
+    }
+}
+impl Function for CallHelperFunction {
+    fn name(&self) -> String {
+        self.func_name.clone()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![self.signature.clone()]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let arg_types: Vec<Type> = self.signature.args.iter().copied().collect();
+        let llvm_fn = compiler.get_or_declare_intern_function(
+            &UserFunction::intern_fn_name(&self.func_name),
+            self.signature.ret,
+            &arg_types,
+        )?;
+        let arg_values = (0..args.len())
+            .map(|i| args.compile(compiler, i)?.into_basic_value())
+            .collect::<LangResult<Vec<_>>>()?;
+        compiler.build_intern_call(llvm_fn, self.signature.ret, &arg_values)
+    }
+}
@@ -1,7 +1,65 @@
 //! Built-in functions, methods/properties, and operators.
 
+use super::ast::{Function, UserFunction};
+use super::errors::*;
+use super::{Span, Spanned};
+use LangErrorMsg::UnknownFunction;
+
 pub mod cmp;
 pub mod convert;
 pub mod literals;
+pub mod logic;
 pub mod math;
 pub mod misc;
+pub mod neighborhood;
+pub mod random;
+pub mod vectors;
+
+/// Returns the function with the given name that can be called using
+/// `name(args)` syntax, or an Err(UnknownFunction) if there is none.
+pub fn lookup_function(
+    userfunc: &mut UserFunction,
+    span: Span,
+    name: &Spanned<String>,
+) -> LangResult<Box<dyn Function>> {
+    // Helper functions take priority over built-in functions.
+    if let Some(helper) = misc::CallHelperFunction::try_new(userfunc, &name.inner) {
+        return Ok(Box::new(helper));
+    }
+    match name.inner.as_ref() {
+        "int" => Ok(Box::new(convert::BoolToInt)),
+        "bool" => Ok(Box::new(convert::IntToBool)),
+        "state_id" => Ok(Box::new(convert::CellStateToInt)),
+        "state_wrap" => Ok(Box::new(convert::WrapIntToCellState::new(userfunc))),
+        "min" => Ok(Box::new(math::MinMax::Min)),
+        "max" => Ok(Box::new(math::MinMax::Max)),
+        "sign" => Ok(Box::new(math::Sign)),
+        "powmod" => Ok(Box::new(math::PowMod::try_new(userfunc, span)?)),
+        "clamp" => Ok(Box::new(math::Clamp::try_new(userfunc, span)?)),
+        "gcd" => Ok(Box::new(math::GcdLcm::try_new(userfunc, span, false)?)),
+        "lcm" => Ok(Box::new(math::GcdLcm::try_new(userfunc, span, true)?)),
+        "saturating_add" => Ok(Box::new(math::SaturatingOp::Add)),
+        "saturating_sub" => Ok(Box::new(math::SaturatingOp::Sub)),
+        "saturating_mul" => Ok(Box::new(math::SaturatingOp::Mul)),
+        "neighbor" => Ok(Box::new(neighborhood::GetNeighbor::try_new(
+            userfunc, span,
+        )?)),
+        "layer" => Ok(Box::new(neighborhood::GetLayer::try_new(userfunc, span)?)),
+        "count_neighbors_of" => Ok(Box::new(neighborhood::CountNeighborsOf::new(userfunc))),
+        "neighbor_min" => Ok(Box::new(neighborhood::NeighborReduce::new(
+            userfunc,
+            neighborhood::ReduceOp::Min,
+        ))),
+        "neighbor_max" => Ok(Box::new(neighborhood::NeighborReduce::new(
+            userfunc,
+            neighborhood::ReduceOp::Max,
+        ))),
+        "neighbor_sum" => Ok(Box::new(neighborhood::NeighborReduce::new(
+            userfunc,
+            neighborhood::ReduceOp::Sum,
+        ))),
+        "random" => Ok(Box::new(random::Random::try_new(userfunc, span, false)?)),
+        "random_chance" => Ok(Box::new(random::Random::try_new(userfunc, span, true)?)),
+        _ => Err(UnknownFunction.with_span(name.span)),
+    }
+}
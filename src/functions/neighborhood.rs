@@ -0,0 +1,347 @@
+//! Functions for accessing the neighborhood of a cell.
+
+use inkwell::values::IntValue;
+use inkwell::IntPredicate;
+use std::rc::Rc;
+
+use super::super::ast::{
+    ArgValues, ErrorPointRef, FnSignature, Function, FunctionKind, RuleMeta, UserFunction,
+};
+use super::super::compiler::{Compiler, Value};
+use super::super::errors::*;
+use super::super::{Span, Type, NEIGHBORHOOD_RADIUS};
+use LangErrorMsg::{LayerOutOfRange, NeighborOutOfRange};
+
+/// Built-in function that returns the cell state of the neighbor at the given
+/// offset, either with one integer argument per dimension or with a single
+/// vector argument whose length is the number of dimensions.
+#[derive(Debug)]
+pub struct GetNeighbor {
+    /// Rule metadata (used to determine the number of dimensions).
+    rule_meta: Rc<RuleMeta>,
+    /// Error returned if the given offset is outside the neighborhood.
+    out_of_range_error: ErrorPointRef,
+}
+impl GetNeighbor {
+    /// Constructs a new GetNeighbor instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span) -> LangResult<Self> {
+        Ok(Self {
+            rule_meta: userfunc.rule_meta().clone(),
+            out_of_range_error: userfunc.add_error_point(NeighborOutOfRange.with_span(span)),
+        })
+    }
+}
+impl Function for GetNeighbor {
+    fn name(&self) -> String {
+        "neighbor".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        let ndim = self.rule_meta.ndim as usize;
+        vec![
+            FnSignature::new(vec![Type::Int; ndim], Type::CellState),
+            FnSignature::new(vec![Type::Vector(ndim)], Type::CellState),
+        ]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let index = compile_neighbor_index(
+            compiler,
+            &args,
+            0,
+            self.rule_meta.ndim,
+            &self.out_of_range_error,
+        )?;
+        // Load the cell state.
+        Ok(Value::CellState(compiler.build_load_neighbor(index)?))
+    }
+}
+
+/// Built-in function that returns the value of the given layer (declared by
+/// the `@layers` directive) at the given neighbor offset, either with one
+/// integer argument per dimension or with a single vector argument whose
+/// length is the number of dimensions. Layer 0 is the cell state.
+#[derive(Debug)]
+pub struct GetLayer {
+    /// Rule metadata (used to determine the number of dimensions and layers).
+    rule_meta: Rc<RuleMeta>,
+    /// Error returned if the given layer does not exist.
+    layer_out_of_range_error: ErrorPointRef,
+    /// Error returned if the given offset is outside the neighborhood.
+    out_of_range_error: ErrorPointRef,
+}
+impl GetLayer {
+    /// Constructs a new GetLayer instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span) -> LangResult<Self> {
+        Ok(Self {
+            rule_meta: userfunc.rule_meta().clone(),
+            layer_out_of_range_error: userfunc.add_error_point(LayerOutOfRange.with_span(span)),
+            out_of_range_error: userfunc.add_error_point(NeighborOutOfRange.with_span(span)),
+        })
+    }
+}
+impl Function for GetLayer {
+    fn name(&self) -> String {
+        "layer".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        let ndim = self.rule_meta.ndim as usize;
+        vec![
+            FnSignature::new(vec![Type::Int; ndim + 1], Type::Int),
+            FnSignature::new(vec![Type::Int, Type::Vector(ndim)], Type::Int),
+        ]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let int_type = compiler.int_type();
+        let layer = args.compile(compiler, 0)?.as_int()?;
+
+        // Check that the layer exists. Treat the layer index as an unsigned
+        // integer so that negative numbers are out of range too.
+        let layers = int_type.const_int(self.rule_meta.layers as u64, false);
+        let is_out_of_range = compiler.builder().build_int_compare(
+            IntPredicate::UGE, // Unsigned Greater-Than or Equal
+            layer,
+            layers,
+            "isLayerOutOfRange",
+        );
+        compiler.build_conditional(
+            is_out_of_range,
+            |c| Ok(self.layer_out_of_range_error.compile(c)),
+            |_| Ok(()),
+        )?;
+
+        let index = compile_neighbor_index(
+            compiler,
+            &args,
+            1,
+            self.rule_meta.ndim,
+            &self.out_of_range_error,
+        )?;
+
+        // Skip over the earlier layers.
+        let neighborhood_len = self.rule_meta.neighborhood_len() as u64;
+        let neighborhood_len = int_type.const_int(neighborhood_len, false);
+        let b = compiler.builder();
+        let layer_start = b.build_int_mul(layer, neighborhood_len, "layerStart");
+        let index = b.build_int_add(layer_start, index, "layerIndex");
+
+        // Load the value and extend it to a full integer.
+        let value = compiler.build_load_neighbor(index)?;
+        Ok(Value::Int(compiler.builder().build_int_z_extend(
+            value,
+            int_type,
+            "layerValue",
+        )))
+    }
+}
+
+/// Builds instructions to compute the index in the flat neighborhood array of
+/// the neighbor at the offset given by the arguments starting at `first_arg`
+/// (either one integer per dimension or a single vector), checking that each
+/// component of the offset is in range.
+fn compile_neighbor_index(
+    compiler: &mut Compiler,
+    args: &ArgValues,
+    first_arg: usize,
+    ndim: u8,
+    out_of_range_error: &ErrorPointRef,
+) -> LangResult<IntValue<'static>> {
+    let int_type = compiler.int_type();
+    let diameter = int_type.const_int(2 * NEIGHBORHOOD_RADIUS as u64 + 1, false);
+
+    // Get each component of the offset.
+    let mut offsets = vec![];
+    match args.compile(compiler, first_arg)? {
+        Value::Vector(v) => {
+            for axis in 0..ndim {
+                let idx = int_type.const_int(axis as u64, false);
+                offsets.push(
+                    compiler
+                        .builder()
+                        .build_extract_element(v, idx, "neighborOffset")
+                        .into_int_value(),
+                );
+            }
+        }
+        first => {
+            offsets.push(first.as_int()?);
+            for axis in first_arg + 1..args.len() {
+                offsets.push(args.compile(compiler, axis)?.as_int()?);
+            }
+        }
+    }
+
+    // Compute the index of the neighbor in the flat neighborhood array,
+    // checking that each component of the offset is in range. The X axis
+    // varies fastest, so iterate over the axes in reverse.
+    let mut index = int_type.const_zero();
+    for offset in offsets.into_iter().rev() {
+        let offset =
+            compiler.build_neighbor_offset_check(offset, |c| Ok(out_of_range_error.compile(c)))?;
+        index = compiler
+            .builder()
+            .build_int_mul(index, diameter, "neighborIndex");
+        index = compiler
+            .builder()
+            .build_int_add(index, offset, "neighborIndex");
+    }
+    Ok(index)
+}
+
+/// Built-in function that returns the cell state of the center cell (the
+/// same state that `remain` keeps).
+#[derive(Debug)]
+pub struct GetCenterCell {
+    /// Index of the center cell in the flat neighborhood array.
+    center_cell_index: usize,
+}
+impl GetCenterCell {
+    /// Constructs a new GetCenterCell instance.
+    pub fn new(userfunc: &UserFunction) -> Self {
+        Self {
+            center_cell_index: userfunc.rule_meta().center_cell_index(),
+        }
+    }
+}
+impl Function for GetCenterCell {
+    fn name(&self) -> String {
+        "this".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Atom
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::atom(Type::CellState)]
+    }
+    fn compile(&self, compiler: &mut Compiler, _args: ArgValues) -> LangResult<Value> {
+        let index = compiler
+            .int_type()
+            .const_int(self.center_cell_index as u64, false);
+        Ok(Value::CellState(compiler.build_load_neighbor(index)?))
+    }
+}
+
+/// Built-in function that returns the number of cells in the neighborhood
+/// declared by the `@neighborhood` directive that have the given cell state.
+#[derive(Debug)]
+pub struct CountNeighborsOf {
+    /// Rule metadata (used to determine the neighborhood).
+    rule_meta: Rc<RuleMeta>,
+}
+impl CountNeighborsOf {
+    /// Constructs a new CountNeighborsOf instance.
+    pub fn new(userfunc: &UserFunction) -> Self {
+        Self {
+            rule_meta: userfunc.rule_meta().clone(),
+        }
+    }
+}
+impl Function for CountNeighborsOf {
+    fn name(&self) -> String {
+        "count_neighbors_of".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![Type::CellState], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let cell_state = args.compile(compiler, 0)?.as_cell_state()?;
+        let int_type = compiler.int_type();
+
+        // The neighborhood is known at compile time, so check each neighbor
+        // in turn, keeping a running count.
+        let mut count = int_type.const_zero();
+        for offset in &self.rule_meta.neighborhood {
+            let index = self.rule_meta.neighbor_index(offset);
+            let index = int_type.const_int(index as u64, false);
+            let neighbor = compiler.build_load_neighbor(index)?;
+            let b = compiler.builder();
+            let is_match = b.build_int_compare(IntPredicate::EQ, neighbor, cell_state, "isMatch");
+            let increment = b.build_int_z_extend(is_match, int_type, "countIncrement");
+            count = b.build_int_add(count, increment, "neighborCount");
+        }
+        Ok(Value::Int(count))
+    }
+}
+
+/// Reduction performed by NeighborReduce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReduceOp {
+    /// Returns the smallest cell state ID.
+    Min,
+    /// Returns the largest cell state ID.
+    Max,
+    /// Returns the sum of the cell state IDs.
+    Sum,
+}
+
+/// Built-in function that returns the minimum, maximum, or sum of the cell
+/// state IDs of the cells in the neighborhood declared by the `@neighborhood`
+/// directive. Returns zero if the neighborhood is empty.
+#[derive(Debug)]
+pub struct NeighborReduce {
+    /// Rule metadata (used to determine the neighborhood).
+    rule_meta: Rc<RuleMeta>,
+    /// Reduction to perform.
+    op: ReduceOp,
+}
+impl NeighborReduce {
+    /// Constructs a new NeighborReduce instance.
+    pub fn new(userfunc: &UserFunction, op: ReduceOp) -> Self {
+        Self {
+            rule_meta: userfunc.rule_meta().clone(),
+            op,
+        }
+    }
+}
+impl Function for NeighborReduce {
+    fn name(&self) -> String {
+        match self.op {
+            ReduceOp::Min => "neighbor_min".to_owned(),
+            ReduceOp::Max => "neighbor_max".to_owned(),
+            ReduceOp::Sum => "neighbor_sum".to_owned(),
+        }
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(vec![], Type::Int)]
+    }
+    fn compile(&self, compiler: &mut Compiler, _args: ArgValues) -> LangResult<Value> {
+        let int_type = compiler.int_type();
+
+        // The neighborhood is known at compile time, so combine each neighbor
+        // in turn. Cell state IDs are never negative, so unsigned comparisons
+        // are fine.
+        let mut result: Option<IntValue> = None;
+        for offset in &self.rule_meta.neighborhood {
+            let index = self.rule_meta.neighbor_index(offset);
+            let index = int_type.const_int(index as u64, false);
+            let neighbor = compiler.build_load_neighbor(index)?;
+            let b = compiler.builder();
+            let state_id = b.build_int_z_extend(neighbor, int_type, "neighborStateId");
+            result = Some(match (result, self.op) {
+                (None, _) => state_id,
+                (Some(acc), ReduceOp::Sum) => b.build_int_add(acc, state_id, "neighborSum"),
+                (Some(acc), op) => {
+                    let predicate = if op == ReduceOp::Max {
+                        IntPredicate::UGT
+                    } else {
+                        IntPredicate::ULT
+                    };
+                    let keep_old = b.build_int_compare(predicate, acc, state_id, "keepOld");
+                    b.build_select(keep_old, acc, state_id, "neighborMinMax")
+                        .into_int_value()
+                }
+            });
+        }
+        Ok(Value::Int(result.unwrap_or_else(|| int_type.const_zero())))
+    }
+}
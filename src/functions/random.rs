@@ -0,0 +1,89 @@
+//! Functions that generate random numbers.
+
+use inkwell::IntPredicate;
+
+use super::super::ast::{
+    ArgValues, ErrorPointRef, FnSignature, Function, FunctionKind, UserFunction,
+};
+use super::super::compiler::{Compiler, Value};
+use super::super::errors::*;
+use super::super::{Span, Type};
+use LangErrorMsg::InvalidRandomBound;
+
+/// Built-in function that returns either a uniformly distributed random
+/// integer from zero (inclusive) to a positive upper bound (exclusive), or a
+/// random boolean that is true with the probability given by a numerator and
+/// a positive denominator.
+///
+/// Random functions are never evaluated at compile time.
+#[derive(Debug)]
+pub struct Random {
+    /// Whether to return a boolean with a given probability instead of an
+    /// integer.
+    chance: bool,
+    /// Error returned if the upper bound (or the denominator of the
+    /// probability) is not positive.
+    invalid_bound_error: ErrorPointRef,
+}
+impl Random {
+    /// Returns a new Random instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span, chance: bool) -> LangResult<Self> {
+        Ok(Self {
+            chance,
+            invalid_bound_error: userfunc.add_error_point(InvalidRandomBound.with_span(span)),
+        })
+    }
+}
+impl Function for Random {
+    fn name(&self) -> String {
+        if self.chance {
+            "random_chance".to_owned()
+        } else {
+            "random".to_owned()
+        }
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Function
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        if self.chance {
+            vec![FnSignature::new(vec![Type::Int, Type::Int], Type::Bool)]
+        } else {
+            vec![FnSignature::new(vec![Type::Int], Type::Int)]
+        }
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let args = (0..args.len())
+            .map(|i| args.compile(compiler, i)?.as_int())
+            .collect::<LangResult<Vec<_>>>()?;
+        // For `random_chance(num, den)`, the upper bound is the denominator.
+        let upper_bound = *args.last().unwrap();
+        let zero = compiler.int_type().const_zero();
+
+        // Check that the upper bound is positive.
+        let is_invalid = compiler.builder().build_int_compare(
+            IntPredicate::SLE,
+            upper_bound,
+            zero,
+            "isInvalidBound",
+        );
+        compiler.build_conditional(
+            is_invalid,
+            |c| Ok(self.invalid_bound_error.compile(c)),
+            |_| Ok(()),
+        )?;
+
+        let random_int = compiler.build_random(upper_bound)?;
+        if self.chance {
+            // `random(den) < num` is true with probability `num / den`.
+            Ok(Value::Bool(compiler.builder().build_int_compare(
+                IntPredicate::SLT,
+                random_int,
+                args[0],
+                "randomChance",
+            )))
+        } else {
+            Ok(Value::Int(random_int))
+        }
+    }
+}
@@ -0,0 +1,313 @@
+//! Vector functions.
+
+use inkwell::values::IntValue;
+use inkwell::IntPredicate;
+
+use super::super::ast::{
+    ArgTypes, ArgValues, ErrorPointRef, FnSignature, Function, FunctionKind, UserFunction,
+};
+use super::super::compiler::{Compiler, Value};
+use super::super::errors::*;
+use super::super::lexer::OperatorToken;
+use super::super::types::{LangInt, MAX_VECTOR_LEN};
+use super::super::{ConstValue, Span, Type};
+use LangErrorMsg::{IntegerOverflow, InternalError, InvalidVectorLength, VectorIndexOutOfRange};
+
+/// Built-in function that constructs a vector from its integer components.
+#[derive(Debug)]
+pub struct BuildVector {
+    /// Number of components.
+    len: usize,
+}
+impl BuildVector {
+    /// Constructs a new BuildVector instance that constructs a vector with the
+    /// given number of components.
+    pub fn try_new(span: Span, len: usize) -> LangResult<Self> {
+        if 1 <= len && len <= MAX_VECTOR_LEN {
+            Ok(Self { len })
+        } else {
+            Err(InvalidVectorLength.with_span(span))
+        }
+    }
+}
+impl Function for BuildVector {
+    fn name(&self) -> String {
+        "vector literal".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Atom
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![FnSignature::new(
+            vec![Type::Int; self.len],
+            Type::Vector(self.len),
+        )]
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let vec_type = compiler.int_type().vec_type(self.len as u32);
+        let mut ret = vec_type.get_undef();
+        for i in 0..self.len {
+            let component = args.compile(compiler, i)?.as_int()?;
+            let idx = compiler.int_type().const_int(i as u64, false);
+            ret = compiler
+                .builder()
+                .build_insert_element(ret, component, idx, "vectorComponent");
+        }
+        Ok(Value::Vector(ret))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let components = (0..self.len)
+            .map(|i| args.const_eval(i)?.as_int())
+            .collect::<LangResult<Vec<_>>>()?;
+        Ok(Some(ConstValue::Vector(components)))
+    }
+}
+
+/// Built-in function that returns a single component of a vector, such as
+/// `v[0]`.
+#[derive(Debug)]
+pub struct IndexVector {
+    /// Error returned if the index is out of range.
+    out_of_range_error: ErrorPointRef,
+}
+impl IndexVector {
+    /// Constructs a new IndexVector instance.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span) -> LangResult<Self> {
+        Ok(Self {
+            out_of_range_error: userfunc.add_error_point(VectorIndexOutOfRange.with_span(span)),
+        })
+    }
+}
+impl Function for IndexVector {
+    fn name(&self) -> String {
+        "vector index".to_owned()
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![]
+    }
+    fn get_signature(&self, args: &ArgTypes) -> Option<FnSignature> {
+        let mut arg_types = args.iter();
+        match (arg_types.next(), arg_types.next(), arg_types.next()) {
+            (Some(Type::Vector(_)), Some(Type::Int), None) => {
+                Some(FnSignature::new(args.clone(), Type::Int))
+            }
+            _ => None,
+        }
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let vector = args.compile(compiler, 0)?.as_vector()?;
+        let index = args.compile(compiler, 1)?.as_int()?;
+        // Treat the signed index as an unsigned integer, so that a negative
+        // index will be interpreted as a very large positive number, which will
+        // be out of range.
+        let len = index
+            .get_type()
+            .const_int(vector.get_type().get_size() as u64, false);
+        let condition = compiler.builder().build_int_compare(
+            IntPredicate::ULT,
+            index,
+            len,
+            "vectorIndexRangeCheck",
+        );
+        compiler.build_conditional(
+            condition,
+            // The index is in range.
+            |_| Ok(()),
+            // The index is out of range.
+            |c| Ok(self.out_of_range_error.compile(c)),
+        )?;
+        Ok(Value::Int(
+            compiler
+                .builder()
+                .build_extract_element(vector, index, "vectorComponent")
+                .into_int_value(),
+        ))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let components = match args.const_eval(0)? {
+            ConstValue::Vector(components) => components,
+            other => Err(uncaught_type_error("vector", other.type_name()))?,
+        };
+        let index = args.const_eval(1)?.as_int()?;
+        if 0 <= index && index < components.len() as LangInt {
+            Ok(Some(ConstValue::Int(components[index as usize])))
+        } else {
+            self.out_of_range_error.err()
+        }
+    }
+}
+
+/// Built-in function that performs a fixed two-input math operation on
+/// vectors, component-wise.
+///
+/// `+` and `-` accept two vectors; if their lengths differ, the shorter one is
+/// extended with zeros. `*` accepts a vector and an integer in either order.
+#[derive(Debug)]
+pub struct BinaryVecOp {
+    /// Token signifying what operation to perform.
+    op: OperatorToken,
+    /// Error returned if overflow occurs.
+    overflow_error: ErrorPointRef,
+}
+impl BinaryVecOp {
+    /// Constructs a new BinaryVecOp instance that performs the given operation.
+    pub fn try_new(userfunc: &mut UserFunction, span: Span, op: OperatorToken) -> LangResult<Self> {
+        Ok(Self {
+            op,
+            overflow_error: userfunc.add_error_point(IntegerOverflow.with_span(span)),
+        })
+    }
+    /// Returns the LLVM intrinsic used to perform this operation on each
+    /// component.
+    fn intrinsic(&self) -> LangResult<&'static str> {
+        match self.op {
+            OperatorToken::Plus => Ok("sadd"),
+            OperatorToken::Minus => Ok("ssub"),
+            OperatorToken::Asterisk => Ok("smul"),
+            _ => Err(InternalError("Uncaught invalid operator".into()).without_span()),
+        }
+    }
+}
+impl Function for BinaryVecOp {
+    fn name(&self) -> String {
+        format!("binary {:?} operator", self.op.to_string())
+    }
+    fn kind(&self) -> FunctionKind {
+        FunctionKind::Operator
+    }
+    fn signatures(&self) -> Vec<FnSignature> {
+        vec![]
+    }
+    fn get_signature(&self, args: &ArgTypes) -> Option<FnSignature> {
+        let mut arg_types = args.iter();
+        let ret = match (
+            self.op,
+            arg_types.next(),
+            arg_types.next(),
+            arg_types.next(),
+        ) {
+            (OperatorToken::Plus, Some(Type::Vector(len1)), Some(Type::Vector(len2)), None)
+            | (OperatorToken::Minus, Some(Type::Vector(len1)), Some(Type::Vector(len2)), None) => {
+                Type::Vector(std::cmp::max(*len1, *len2))
+            }
+            (OperatorToken::Asterisk, Some(Type::Vector(len)), Some(Type::Int), None)
+            | (OperatorToken::Asterisk, Some(Type::Int), Some(Type::Vector(len)), None) => {
+                Type::Vector(*len)
+            }
+            _ => return None,
+        };
+        Some(FnSignature::new(args.clone(), ret))
+    }
+    fn compile(&self, compiler: &mut Compiler, args: ArgValues) -> LangResult<Value> {
+        let lhs = args.compile(compiler, 0)?;
+        let rhs = args.compile(compiler, 1)?;
+        let len = std::cmp::max(vector_len(&lhs), vector_len(&rhs));
+        let intrinsic = self.intrinsic()?;
+
+        // Perform the operation on each component, keeping track of whether
+        // overflow occurred for any of them.
+        let mut ret = compiler.int_type().vec_type(len as u32).get_undef();
+        let mut any_overflow = compiler.bool_type().const_zero();
+        for i in 0..len {
+            let lhs_component = build_component(compiler, &lhs, i)?;
+            let rhs_component = build_component(compiler, &rhs, i)?;
+            let (result, is_overflow) = compiler.build_int_arithmetic_with_overflow(
+                lhs_component,
+                rhs_component,
+                intrinsic,
+            )?;
+            any_overflow = compiler
+                .builder()
+                .build_or(any_overflow, is_overflow, "anyOverflow");
+            let idx = compiler.int_type().const_int(i as u64, false);
+            ret = compiler
+                .builder()
+                .build_insert_element(ret, result, idx, "vectorComponent");
+        }
+
+        // Branch based on whether there is overflow.
+        compiler.build_conditional(
+            any_overflow,
+            // Return an error if there is overflow.
+            |c| Ok(self.overflow_error.compile(c)),
+            // Otherwise proceed.
+            |_| Ok(()),
+        )?;
+
+        Ok(Value::Vector(ret))
+    }
+    fn const_eval(&self, args: ArgValues) -> LangResult<Option<ConstValue>> {
+        let lhs = args.const_eval(0)?;
+        let rhs = args.const_eval(1)?;
+        let len = std::cmp::max(const_vector_len(&lhs), const_vector_len(&rhs));
+        let components = (0..len)
+            .map(|i| {
+                let lhs_component = const_component(&lhs, i)?;
+                let rhs_component = const_component(&rhs, i)?;
+                let result = match self.op {
+                    OperatorToken::Plus => lhs_component.checked_add(rhs_component),
+                    OperatorToken::Minus => lhs_component.checked_sub(rhs_component),
+                    OperatorToken::Asterisk => lhs_component.checked_mul(rhs_component),
+                    _ => Err(InternalError("Uncaught invalid operator".into()).without_span())?,
+                };
+                match result {
+                    Some(component) => Ok(component),
+                    None => self.overflow_error.err(),
+                }
+            })
+            .collect::<LangResult<Vec<_>>>()?;
+        Ok(Some(ConstValue::Vector(components)))
+    }
+}
+
+/// Returns the length of the given value if it is a vector, or zero if it is
+/// an integer.
+fn vector_len(value: &Value) -> usize {
+    match value {
+        Value::Vector(v) => v.get_type().get_size() as usize,
+        _ => 0,
+    }
+}
+/// Builds instructions to get the component of the given value at the given
+/// index. Vectors are extended with zeros, and integers are treated as having
+/// the same value for every component.
+fn build_component(
+    compiler: &mut Compiler,
+    value: &Value,
+    i: usize,
+) -> LangResult<IntValue<'static>> {
+    match value {
+        Value::Int(int) => Ok(*int),
+        Value::Vector(v) if i < v.get_type().get_size() as usize => {
+            let idx = compiler.int_type().const_int(i as u64, false);
+            Ok(compiler
+                .builder()
+                .build_extract_element(*v, idx, "vectorComponent")
+                .into_int_value())
+        }
+        Value::Vector(_) => Ok(compiler.int_type().const_zero()),
+        other => Err(uncaught_type_error("integer or vector", other.type_name())),
+    }
+}
+
+/// Returns the length of the given constant if it is a vector, or zero if it
+/// is an integer.
+fn const_vector_len(value: &ConstValue) -> usize {
+    match value {
+        ConstValue::Vector(components) => components.len(),
+        _ => 0,
+    }
+}
+/// Returns the component of the given constant at the given index. Vectors are
+/// extended with zeros, and integers are treated as having the same value for
+/// every component.
+fn const_component(value: &ConstValue, i: usize) -> LangResult<LangInt> {
+    match value {
+        ConstValue::Int(int) => Ok(*int),
+        ConstValue::Vector(components) => Ok(components.get(i).copied().unwrap_or(0)),
+        other => Err(uncaught_type_error("integer or vector", other.type_name())),
+    }
+}
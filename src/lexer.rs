@@ -9,41 +9,44 @@ use std::str::FromStr;
 use super::errors::*;
 use super::types::LangInt;
 use super::{Span, Type};
-use LangErrorMsg::{UnknownSymbol, Unterminated};
+use LangErrorMsg::{InvalidDigitSeparator, InvalidEscapeSequence, UnknownSymbol, Unterminated};
 
 /// A list of token patterns, arranged roughly from least to most general.
 const TOKEN_PATTERNS: &'static [&'static str] = &[
     // Line comment.
     r#"//[^\r\n]*"#,
-    // Block comment, using the non-greedy `[\s\S]*?` to match the contents of
-    // the comment.
-    r#"/\*[\s\S]*?\*/"#,
-    // Unterminated block comment -- this should raise an error.
+    // Beginning of a block comment. Block comments can be nested, which a
+    // regex cannot match, so the end is found by `find_block_comment_end()`.
     r#"/\*"#,
-    // Double-quoted string, with an optional modifier character in front. Note
-    // that like Rust strings, this can span multiple lines.
-    r#"\w?"[^"]*""#,
-    // Single-quoted string, with an optional modifier character in front. Note
-    // that like Rust strings, this can span multiple lines.
-    r#"\w?'[^']*'"#,
+    // Double-quoted string, with an optional modifier character in front and
+    // backslash escape sequences. Note that like Rust strings, this can span
+    // multiple lines.
+    r#"\w?"(?:[^"\\]|\\[\s\S])*""#,
+    // Single-quoted string, with an optional modifier character in front and
+    // backslash escape sequences. Note that like Rust strings, this can span
+    // multiple lines.
+    r#"\w?'(?:[^'\\]|\\[\s\S])*'"#,
     // Unterminated string -- this should raise an error.
     r#"\w?["']"#,
     // Number with decimal point.
     r#"-?\d?\.\d+"#,
-    // Number without decimal point.
-    r#"-?\d+"#,
+    // Number without decimal point, either decimal or hexadecimal, with
+    // optional underscores as digit separators.
+    r#"-?(0x[\dA-Fa-f_]*|\d[\d_]*)"#,
     // Identifier consisting of a letter or underscore followed by any letters,
     // digits, and/or underscores, with an optional `#` (for tags) or `@` (for
     // directives) in front.
     r#"[#@]?[A-Za-z_][A-Za-z_\d]*"#,
-    // In-place arithmetic operators `**=`, `<<=`, `>>=`, and `>>>=`.
-    r#"(\*\*|<<|>>>?)="#,
+    // In-place arithmetic operators `**=`, `%%=`, `/%=`, `<<=`, `>>=`, and `>>>=`.
+    r#"(\*\*|%%|/%|<<|>>>?)="#,
     // In-place arithmetic operators `+=`, `-=`, `*=`, `/=`, `%=`, `&=`, `|=`, and `^=`.
     r#"[+\-*/%&|^]="#,
-    // Operators `..`, `**`, `<<`, `>>`, and `>>>`.
-    r#"(\.\.|\*\*|<<|>>>?)"#,
+    // Operators `..=`, `..`, `**`, `%%`, `/%`, `<<`, `>>`, `>>>`, and `#?`.
+    r#"(\.\.=?|\*\*|%%|/%|<<|>>>?|#\?)"#,
     // Equality checks `==`, `!=`, `<=`, and `>=`.
     r#"[=!<>]="#,
+    // Arrow `=>`.
+    r#"=>"#,
     // Any other single character.
     r#"[^\s]"#,
 ];
@@ -66,7 +69,12 @@ lazy_static! {
     /// A regex that matches the beginning of a line comment.
     static ref LINE_COMMENT_PATTERN: Regex = Regex::new(r#"^//"#).unwrap();
     /// A regex that matches an assignment operator.
-    static ref ASSIGN_PATTERN: Regex = Regex::new(r#"^(.?.?)=$"#).unwrap();
+    static ref ASSIGN_PATTERN: Regex = Regex::new(r#"^(.{0,3})=$"#).unwrap();
+
+    /// A regex that matches an integer literal, capturing the sign and then
+    /// either the hexadecimal digits (after `0x`) or the decimal digits.
+    static ref INT_PATTERN: Regex =
+        Regex::new(r#"^(-?)(?:0x([\dA-Fa-f_]*)|(\d[\d_]*))$"#).unwrap();
 
     /// A regex that matches a vector type name.
     static ref VEC_TYPE_PATTERN: Regex = Regex::new(r#"vec(\d+)"#).unwrap();
@@ -75,28 +83,191 @@ lazy_static! {
 /// Splits a string into tokens and returns them as a Vec, with all comments
 /// removed.
 pub fn tokenize<'a>(s: &'a str) -> LangResult<Vec<Token<'a>>> {
-    let flat_tokens = TOKEN_PATTERN.find_iter(s).map(|m| {
+    let mut tokens = vec![];
+    let mut next_start = 0;
+    while let Some(m) = TOKEN_PATTERN.find_at(s, next_start) {
         // Get the span of this token.
-        let span = Span {
+        let mut span = Span {
             start: m.start(),
             end: m.end(),
         };
+        // Extend block comments to include their contents.
+        if BLOCK_COMMENT_PATTERN.is_match(m.as_str()) {
+            span.end = find_block_comment_end(s, span.start).ok_or_else(|| {
+                Unterminated("block comment").with_span(Span {
+                    start: m.start(),
+                    end: m.end(),
+                })
+            })?;
+        }
+        // A minus sign directly before a number is part of the number literal
+        // (so that `-9223372036854775808` can be written), unless it comes
+        // right after an operand, in which case it is subtraction.
+        let is_signed_number = m.as_str().starts_with('-') && m.as_str().len() > 1;
+        if is_signed_number && tokens.last().map_or(false, ends_operand) {
+            span.end = span.start + 1;
+        }
+        next_start = span.end;
         // Classify this token.
-        let string = m.as_str();
-        match TokenClass::try_from(string) {
-            Ok(class) => Ok(Token {
+        let string = &s[span.start..span.end];
+        // Check digit separators here rather than in TokenClass::try_from() so
+        // that the error can point to the offending underscores.
+        if let Some(bad) = find_invalid_digit_separator(string) {
+            return Err(InvalidDigitSeparator.with_span(Span {
+                start: span.start + bad.start,
+                end: span.start + bad.end,
+            }));
+        }
+        // Likewise, check escape sequences here so that the error can point to
+        // the offending backslash.
+        if let Some(bad) = find_invalid_escape_sequence(string) {
+            return Err(InvalidEscapeSequence.with_span(Span {
+                start: span.start + bad.start,
+                end: span.start + bad.end,
+            }));
+        }
+        let token = match TokenClass::try_from(string) {
+            Ok(class) => Token {
                 span,
                 string,
                 class,
-            }),
-            Err(msg) => Err(msg.with_span(span)),
-        }
-    });
-    flat_tokens
+            },
+            Err(msg) => return Err(msg.with_span(span)),
+        };
         // Remove comments.
-        .filter(|t| !t.as_ref().map_or(false, Token::is_comment))
-        // Make a Vec<_>.
-        .collect()
+        if !token.is_comment() {
+            tokens.push(token);
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns whether the given token can be the last token of an operand, in
+/// which case a `-` directly after it must be a binary operator.
+fn ends_operand(token: &Token) -> bool {
+    match token.class {
+        TokenClass::Ident(_)
+        | TokenClass::Tag(_)
+        | TokenClass::String { .. }
+        | TokenClass::Integer(_)
+        | TokenClass::Keyword(KeywordToken::True)
+        | TokenClass::Keyword(KeywordToken::False)
+        | TokenClass::Keyword(KeywordToken::This)
+        | TokenClass::Punctuation(PunctuationToken::RParen)
+        | TokenClass::Punctuation(PunctuationToken::RBracket) => true,
+        _ => false,
+    }
+}
+
+/// Returns the byte index just past the end of the block comment beginning at
+/// the given byte index, taking nested block comments into account, or None if
+/// the block comment never ends.
+fn find_block_comment_end(s: &str, start: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut depth = 0;
+    let mut i = start;
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"/*" => {
+                depth += 1;
+                i += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Returns the byte range of the first invalid run of underscores in an integer
+/// literal, if there is one. Underscores are only allowed singly and between
+/// two digits; e.g. `1_000` and `0xDEAD_BEEF` are valid, but `1__000`, `1000_`,
+/// and `0x_FF` are not.
+fn find_invalid_digit_separator(s: &str) -> Option<std::ops::Range<usize>> {
+    let captures = INT_PATTERN.captures(s)?;
+    let digits = captures.get(2).or_else(|| captures.get(3)).unwrap();
+    let mut chars = digits.as_str().char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        if ch != '_' {
+            continue;
+        }
+        let mut end = i + 1;
+        while let Some((_, '_')) = chars.peek() {
+            chars.next();
+            end += 1;
+        }
+        let is_leading = i == 0;
+        let is_trailing = end == digits.as_str().len();
+        if is_leading || is_trailing || end - i > 1 {
+            return Some(digits.start() + i..digits.start() + end);
+        }
+    }
+    None
+}
+
+/// Returns the byte range of the first invalid escape sequence in a string
+/// literal, if there is one. The valid escape sequences are `\"`, `\'`, `\\`,
+/// `\n`, and `\t`.
+fn find_invalid_escape_sequence(s: &str) -> Option<std::ops::Range<usize>> {
+    let contents = STRING_PATTERN.captures(s)?.get(3)?;
+    let mut chars = contents.as_str().char_indices();
+    while let Some((i, ch)) = chars.next() {
+        if ch != '\\' {
+            continue;
+        }
+        match chars.next() {
+            Some((_, escaped)) if "\"'\\nt".contains(escaped) => (),
+            Some((j, escaped)) => {
+                return Some(contents.start() + i..contents.start() + j + escaped.len_utf8());
+            }
+            None => return Some(contents.start() + i..contents.end()),
+        }
+    }
+    None
+}
+
+/// Returns the contents of a string literal with escape sequences replaced by
+/// the characters that they represent. Invalid escape sequences are left
+/// unchanged.
+pub fn unescape_string(contents: &str) -> String {
+    let mut ret = String::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            ret.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => ret.push('\n'),
+            Some('t') => ret.push('\t'),
+            Some(escaped) if "\"'\\".contains(escaped) => ret.push(escaped),
+            Some(other) => {
+                ret.push('\\');
+                ret.push(other);
+            }
+            None => ret.push('\\'),
+        }
+    }
+    ret
+}
+
+/// Parses an integer literal, ignoring any digit separators. Returns None if
+/// the string is not a valid integer literal or if the value is out of range.
+fn parse_int_literal(s: &str) -> Option<LangInt> {
+    let captures = INT_PATTERN.captures(s)?;
+    let sign = captures.get(1).unwrap().as_str();
+    let (radix, digits) = match captures.get(2) {
+        Some(hex_digits) => (16, hex_digits),
+        None => (10, captures.get(3).unwrap()),
+    };
+    let digits = digits.as_str().replace('_', "");
+    LangInt::from_str_radix(&format!("{}{}", sign, digits), radix).ok()
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -193,15 +364,17 @@ impl<'a> TryFrom<&'a str> for TokenClass<'a> {
             Ok(Self::Keyword(keyword))
         } else if let Ok(ty) = s.parse() {
             Ok(Self::Type(ty))
+        } else if let Ok(operator) = s.parse() {
+            // Check for operators before assignments so that `..=` is not
+            // mistaken for an in-place `..` operation.
+            Ok(Self::Operator(operator))
         } else if let Ok(assignment) = s.parse() {
             Ok(Self::Assignment(assignment))
         } else if let Ok(comparison) = s.parse() {
             Ok(Self::Comparison(comparison))
-        } else if let Ok(operator) = s.parse() {
-            Ok(Self::Operator(operator))
         } else if let Ok(punctuation) = s.parse() {
             Ok(Self::Punctuation(punctuation))
-        } else if let Ok(i) = s.parse() {
+        } else if let Some(i) = parse_int_literal(s) {
             Ok(Self::Integer(i))
         } else if let Some(captures) = STRING_PATTERN.captures(s) {
             if let Some(contents_capture) = captures.get(3) {
@@ -225,11 +398,7 @@ impl<'a> TryFrom<&'a str> for TokenClass<'a> {
         } else if LINE_COMMENT_PATTERN.is_match(s) {
             Ok(Self::Comment)
         } else if BLOCK_COMMENT_PATTERN.is_match(s) {
-            if s == "/*" {
-                Err(Unterminated("block comment"))
-            } else {
-                Ok(Self::Comment)
-            }
+            Ok(Self::Comment)
         } else {
             Err(UnknownSymbol)
         }
@@ -255,17 +424,33 @@ enum_with_str_repr! {
 
         // Branching
         Case = "case",
+        Elif = "elif",
         Else = "else",
         If = "if",
+        Match = "match",
+        Then = "then",
         Unless = "unless",
 
         // Variables
         Set = "set",
+        Const = "const",
+
+        // Debugging
+        Assert = "assert",
+        Debug = "debug",
 
         // Boolean operators
         Or = "or",
         Xor = "xor",
         And = "and",
+        Not = "not",
+
+        // Boolean literals
+        True = "true",
+        False = "false",
+
+        // State of the center cell
+        This = "this",
 
         // Boolean tests
         In = "in",
@@ -288,6 +473,8 @@ enum_with_str_repr! {
         Asterisk = "*",
         Slash = "/",
         Percent = "%",
+        DoublePercent = "%%",
+        SlashPercent = "/%",
         DoubleAsterisk = "**",
 
         // Bitshift operators
@@ -299,13 +486,16 @@ enum_with_str_repr! {
         Ampersand = "&",
         Pipe = "|",
         Caret = "^",
+        Tilde = "~",
 
         // Boolean operators and boolean tests are in KeywordToken.
 
         // Miscellaneous operators
         Dot = ".",
         DotDot = "..",
+        DotDotEq = "..=",
         Tag = "#",
+        TagClamp = "#?",
     }
 
     #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -318,6 +508,7 @@ enum_with_str_repr! {
         RBrace = "}",
         Comma = ",",
         Semicolon = ";",
+        FatArrow = "=>",
     }
 
     /// Comparison.
@@ -349,15 +540,25 @@ impl KeywordToken {
             | Self::Remain
             | Self::Return
             | Self::Case
+            | Self::Elif
             | Self::Else
             | Self::If
+            | Self::Match
             | Self::Unless
-            | Self::Set => true,
-            Self::Or
+            | Self::Set
+            | Self::Assert
+            | Self::Debug => true,
+            Self::Then
+            | Self::Or
             | Self::Xor
             | Self::And
+            | Self::Not
+            | Self::True
+            | Self::False
+            | Self::This
             | Self::In
             | Self::Is
+            | Self::Const
             | Self::Bind
             | Self::Bound
             | Self::Static
@@ -414,6 +615,7 @@ impl ComparisonToken {
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum TypeToken {
     Int,
+    Bool,
     CellState,
     Vector(Option<usize>),
 }
@@ -421,6 +623,7 @@ impl fmt::Display for TypeToken {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Int => write!(f, "int"),
+            Self::Bool => write!(f, "bool"),
             Self::CellState => write!(f, "cellstate"),
             Self::Vector(None) => write!(f, "vec"),
             Self::Vector(Some(len)) => write!(f, "vec{}", len),
@@ -443,6 +646,7 @@ impl FromStr for TypeToken {
         }
         match s {
             "int" => Ok(Self::Int),
+            "bool" => Ok(Self::Bool),
             "cellstate" => Ok(Self::CellState),
             "vec" => Ok(Self::Vector(None)),
             _ => Err(()),
@@ -455,6 +659,7 @@ impl TypeToken {
     pub fn resolve(self, ndim: u8) -> Type {
         match self {
             Self::Int => Type::Int,
+            Self::Bool => Type::Bool,
             Self::CellState => Type::CellState,
             Self::Vector(None) => Type::Vector(ndim as usize),
             Self::Vector(Some(len)) => Type::Vector(len),
@@ -481,7 +686,7 @@ impl fmt::Display for AssignmentToken {
 impl FromStr for AssignmentToken {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, ()> {
-        // Match the regex `^(.?.?)=$` and extract the first group, `(.?.?)`,
+        // Match the regex `^(.{0,3})=$` and extract the first group, `(.{0,3})`,
         // which gives the additional operator if any.
         if let Some(op_str) = ASSIGN_PATTERN
             .captures(s)
@@ -547,4 +752,26 @@ if this is {
             tokens[7].span.textpoints(source_code)
         );
     }
+
+    #[test]
+    fn test_tokenize_without_trailing_newline() {
+        // Line comment at the very end of the file
+        for &source_code in &["x // comment", "x //", "x\r\n// comment", "x // comment\r"] {
+            let tokens = tokenize(source_code).expect("Tokenization failed");
+            assert_eq!(1, tokens.len());
+            assert_eq!("x", tokens[0].string);
+            assert_eq!(Span { start: 0, end: 1 }, tokens[0].span);
+        }
+        assert!(tokenize("// comment")
+            .expect("Tokenization failed")
+            .is_empty());
+
+        // Identifier at the very end of the file
+        let tokens = tokenize("foo bar").expect("Tokenization failed");
+        assert_eq!(2, tokens.len());
+        assert_eq!("bar", tokens[1].string);
+        assert_eq!(Span { start: 4, end: 7 }, tokens[1].span);
+        let tokens = tokenize("foo /* comment */ bar").expect("Tokenization failed");
+        assert_eq!(Span { start: 18, end: 21 }, tokens[1].span);
+    }
 }
@@ -19,20 +19,36 @@ mod errors;
 mod functions;
 mod lexer;
 mod parser;
+pub mod run;
 mod span;
 mod types;
 
 pub use constvalue::ConstValue;
 pub use errors::CompleteLangResult;
-pub use span::{Span, Spanned};
+pub use span::{LineIndex, Span, Spanned};
 pub use types::Type;
 
-use errors::LangResult;
-
 /// Maximum number of dimensions.
 pub const MAX_NDIM: types::LangInt = 6;
 /// Maximum number of states.
 pub const MAX_STATES: types::LangInt = 256;
+/// Maximum number of layers of cell data, including cell states.
+pub const MAX_LAYERS: types::LangInt = 2;
+/// Maximum nesting depth of an expression, counting parentheses, function
+/// calls, and unary and exponentiation operators.
+pub const MAX_EXPR_DEPTH: usize = 64;
+/// Maximum depth of the tree of operations in an expression, which also counts
+/// chains of left-associative operators such as `a + b + c`.
+pub const MAX_EXPR_TREE_DEPTH: usize = 256;
+/// Maximum number of neighborhood configurations in an exported transition
+/// table.
+pub const MAX_TABLE_SIZE: usize = 1 << 20;
+/// Version of the language implemented by this build, as `(major, minor)`.
+/// Rules that declare a newer major version using the `@ndca_version`
+/// directive are rejected.
+pub const NDCA_VERSION: (u32, u32) = (1, 0);
+/// Radius of the neighborhood that is passed to the transition function.
+pub const NEIGHBORHOOD_RADIUS: types::LangInt = 1;
 
 fn main() -> Result<(), ()> {
     let args: Vec<String> = std::env::args().collect();
@@ -55,7 +71,13 @@ Please specify a file to run. E.g.
         .expect("Error reading file");
     let source_code = Rc::new(source_code);
 
-    let result = compile_and_run(source_code.clone());
+    let result = ast::make_rule(source_code.clone()).and_then(|rule| {
+        let line_index = LineIndex::new(&source_code);
+        for warning in rule.warnings() {
+            eprintln!("{}", warning.with_line_index(&source_code, &line_index));
+        }
+        run::run_rule(&rule, None)
+    });
     match result {
         Ok(ret) => println!("JIT-compiled transition function output: {:?}", ret),
         Err(err) => {
@@ -67,14 +89,5 @@ Please specify a file to run. E.g.
     Ok(())
 }
 
-/// Runs the given rule's transition function using the compiler and returns the
-/// result.
-fn compile_and_run(source_code: Rc<String>) -> LangResult<ConstValue> {
-    let rule = ast::make_rule(source_code.clone())?;
-    let mut compiler = compiler::Compiler::new()?;
-    let mut transition_function = rule.transition_function().compile(&mut compiler)?;
-    transition_function.call()
-}
-
 #[cfg(test)]
 mod tests;
@@ -1,4 +1,5 @@
 //! Functions for producing a parse tree.
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::rc::Rc;
@@ -9,16 +10,34 @@ pub use tree::*;
 
 use super::errors::*;
 use super::lexer::*;
-use super::{Span, Spanned};
+use super::{Span, Spanned, MAX_EXPR_DEPTH, MAX_EXPR_TREE_DEPTH};
 use LangErrorMsg::{
-    ElseWithoutIf, Expected, InternalError, InvalidDirectiveName, MissingSetKeyword, ReservedWord,
-    TopLevelNonDirective, Unimplemented, Unmatched,
+    ElseWithoutIf, Expected, ExpressionTooDeep, InternalError, InvalidDirectiveName,
+    MissingSetKeyword, ReservedWord, TopLevelNonDirective, Unimplemented, Unmatched,
 };
 
 /// Parses the given tokens and returns a ParseTree.
 pub fn parse(source_code: Rc<String>, tokens: &[Token]) -> LangResult<ParseTree> {
+    parse_collecting_errors(source_code, tokens).map_err(|mut errors| errors.remove(0))
+}
+
+/// Parses the given tokens and returns a ParseTree, or every error encountered
+/// if there were any.
+///
+/// When a statement or directive cannot be parsed, the parser skips to the
+/// next one and keeps going, so that one mistake does not hide later ones.
+pub fn parse_collecting_errors(
+    source_code: Rc<String>,
+    tokens: &[Token],
+) -> MultiLangResult<ParseTree> {
+    let errors = RefCell::new(vec![]);
+    let parsed_directives = ParseBuilder::new(tokens, &errors).directives();
+    let errors = errors.into_inner();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
     let mut directives: HashMap<Directive, Vec<Spanned<DirectiveContents>>> = HashMap::new();
-    for (directive, contents) in ParseBuilder::from(tokens).directives()?.into_iter() {
+    for (directive, contents) in parsed_directives {
         directives.entry(directive).or_default().push(contents);
     }
     Ok(ParseTree {
@@ -92,16 +111,22 @@ struct ParseBuilder<'a> {
     tokens: &'a [Token<'a>],
     /// Index of the "current" token (None = before start).
     cursor: Option<usize>,
+    /// Errors that the parser has recovered from.
+    errors: &'a RefCell<Vec<LangError>>,
+    /// Number of expressions that the cursor is currently nested inside.
+    expr_depth: usize,
 }
-impl<'a> From<&'a [Token<'a>]> for ParseBuilder<'a> {
-    fn from(tokens: &'a [Token]) -> Self {
+impl<'a> ParseBuilder<'a> {
+    /// Constructs a new ParseBuilder over the given tokens, which records
+    /// errors that it recovers from in the given list.
+    fn new(tokens: &'a [Token<'a>], errors: &'a RefCell<Vec<LangError>>) -> Self {
         Self {
             tokens,
             cursor: None,
+            errors,
+            expr_depth: 0,
         }
     }
-}
-impl<'a> ParseBuilder<'a> {
     /// Moves the cursor forward and then returns the element at the cursor.
     fn next(&mut self) -> Option<Token<'a>> {
         // Add 1 or set to zero.
@@ -151,6 +176,42 @@ impl<'a> ParseBuilder<'a> {
     fn err<T>(&self, msg: LangErrorMsg) -> LangResult<T> {
         Err(msg.with_span(self.span()))
     }
+    /// Records an error that the parser has recovered from.
+    fn report(&self, error: LangError) {
+        self.errors.borrow_mut().push(error);
+    }
+    /// Skips the rest of a statement that could not be parsed, starting with
+    /// the token at the cursor. Stops before the next token that starts a
+    /// statement or closes the enclosing block, or at the end of the file.
+    /// Blocks in between are skipped along with their contents.
+    fn skip_statement(&mut self) {
+        let mut depth = 0_usize;
+        let mut token = self.current();
+        loop {
+            match token.map(|t| t.class) {
+                Some(TokenClass::Punctuation(PunctuationToken::LBrace)) => depth += 1,
+                Some(TokenClass::Punctuation(PunctuationToken::RBrace)) => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => (),
+            }
+            match self.peek_next().map(|t| t.class) {
+                None => return,
+                Some(TokenClass::Punctuation(PunctuationToken::RBrace)) if depth == 0 => return,
+                // An `else` or `elif` cannot start a new statement, so keep
+                // skipping.
+                Some(TokenClass::Keyword(kw))
+                    if depth == 0
+                        && kw.starts_statement()
+                        && kw != KeywordToken::Else
+                        && kw != KeywordToken::Elif =>
+                {
+                    return
+                }
+                _ => token = self.next(),
+            }
+        }
+    }
     /// Consumes the next symbol and returns a Spanned { ... } of the result of
     /// the given closure if the closure returns LangResult::Ok; otherwise
     /// rewind the state of the ParseBuilder to before the closure was run and
@@ -163,7 +224,7 @@ impl<'a> ParseBuilder<'a> {
             let ret = f(b);
             let end = b.span();
             ret.map(|t| Spanned {
-                span: Span::merge(start, end),
+                span: start.join(end),
                 inner: t,
             })
         })
@@ -192,12 +253,29 @@ impl<'a> ParseBuilder<'a> {
     }
 
     /// Consumes a sequence of directives.
-    fn directives(&mut self) -> LangResult<Vec<(Directive, Spanned<DirectiveContents>)>> {
+    ///
+    /// If a directive cannot be parsed, the error is recorded and everything up
+    /// to the next directive is skipped.
+    fn directives(&mut self) -> Vec<(Directive, Spanned<DirectiveContents>)> {
         let mut directives = vec![];
         while self.peek_next().is_some() {
-            directives.push(self.expect(Self::directive)?.inner);
+            match self.expect(Self::directive) {
+                Ok(directive) => directives.push(directive.inner),
+                Err(e) => {
+                    self.report(e);
+                    self.next();
+                    while let Some(t) = self.peek_next() {
+                        match t.class {
+                            TokenClass::Directive(_) | TokenClass::Keyword(KeywordToken::Const) => {
+                                break
+                            }
+                            _ => self.next(),
+                        };
+                    }
+                }
+            }
         }
-        Ok(directives)
+        directives
     }
     /// Consumes a directive, which includes one or more arguments given to the
     /// directive. The number of arguments consumed depends on the name of the
@@ -213,10 +291,26 @@ impl<'a> ParseBuilder<'a> {
                 };
                 Ok((directive, contents))
             }
+            // Constants are declared using a keyword rather than a directive.
+            Some(TokenClass::Keyword(KeywordToken::Const)) => {
+                let contents = self.expect(Self::const_definition)?;
+                Ok((Directive::Const, contents))
+            }
             Some(_) => self.err(TopLevelNonDirective),
             None => self.err(Expected("directive")),
         }
     }
+    /// Consumes a constant definition, consisting of a name followed by `=`
+    /// and an expression.
+    fn const_definition(&mut self) -> LangResult<DirectiveContents> {
+        let name = self.expect(Self::ident)?;
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Assignment(AssignmentToken::Assign)) => (),
+            _ => self.err(Expected("'='"))?,
+        }
+        let value = self.expect(Self::expression)?;
+        Ok(DirectiveContents::Const { name, value })
+    }
     /// Consumes a function definition.
     fn function_definition(&mut self) -> LangResult<DirectiveContents> {
         Ok(DirectiveContents::Func(HelperFunc {
@@ -252,6 +346,9 @@ impl<'a> ParseBuilder<'a> {
             Some(TokenClass::Punctuation(PunctuationToken::LBrace)) => {
                 Ok(self.expect(Self::block)?.into())
             }
+            Some(TokenClass::String { prefix: None, .. }) => {
+                Ok(DirectiveContents::String(self.expect(Self::string)?))
+            }
             Some(_) => Ok(self.expect(Self::expression)?.into()),
             None => self.err(Expected("expression or code block"))?,
         }
@@ -273,69 +370,252 @@ impl<'a> ParseBuilder<'a> {
                 // There's the beginning of a statement.
                 Some(TokenClass::Keyword(kw)) if kw.starts_statement() => {
                     self.prev();
-                    statements.push(self.expect(Self::statement)?)
+                    match self.expect(Self::statement) {
+                        Ok(statement) => statements.push(statement),
+                        // Record the error and skip to the next statement, so
+                        // that later errors are reported too.
+                        Err(e) => {
+                            self.report(e);
+                            self.next();
+                            self.skip_statement();
+                        }
+                    }
                 }
                 // There's a closing brace.
                 Some(TokenClass::Punctuation(PunctuationToken::RBrace)) => break,
                 // There's something else.
-                Some(_) => self.err(Expected("statement or '}'"))?,
+                Some(_) => {
+                    self.report(Expected("statement or '}'").with_span(self.span()));
+                    self.skip_statement();
+                }
                 // We've reached the end of the file without closing the block.
                 None => Err(Unmatched('{', '}').with_span(open_span))?,
             }
         }
         Ok(statements)
     }
+    /// Consumes the arms of a `match` statement, enclosed in braces, and
+    /// returns a list of the values and blocks of each arm along with the
+    /// block of the `else` arm (if there is one).
+    fn match_arms(&mut self) -> LangResult<(Vec<MatchArm>, Option<StatementBlock>)> {
+        // Get a left brace.
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Punctuation(PunctuationToken::LBrace)) => (),
+            _ => self.err(Expected("'{'"))?,
+        }
+        // Record the span of the left brace.
+        let open_span = self.span();
+        let mut arms = vec![];
+        let mut else_arm = None;
+        loop {
+            match self.peek_next().map(|t| t.class) {
+                // There's a closing brace.
+                Some(TokenClass::Punctuation(PunctuationToken::RBrace)) => {
+                    self.next();
+                    break;
+                }
+                // We've reached the end of the file without closing the block.
+                None => Err(Unmatched('{', '}').with_span(open_span))?,
+                // Nothing can come after the `else` arm.
+                Some(_) if else_arm.is_some() => {
+                    self.next();
+                    self.err(Expected("'}'"))?
+                }
+                // There's an `else` arm.
+                Some(TokenClass::Keyword(KeywordToken::Else)) => {
+                    self.next();
+                    self.expect(Self::fat_arrow)?;
+                    else_arm = Some(self.expect(Self::block)?.inner);
+                }
+                // There's an arm with a value.
+                Some(_) => {
+                    let value = self.expect(Self::expression)?;
+                    self.expect(Self::fat_arrow)?;
+                    arms.push((value, self.expect(Self::block)?.inner));
+                }
+            }
+            // Arms may optionally be separated by commas.
+            if self.next_token_is_one_of(&[TokenClass::Punctuation(PunctuationToken::Comma)]) {
+                self.next();
+            }
+        }
+        Ok((arms, else_arm))
+    }
+    /// Consumes a `=>` token.
+    fn fat_arrow(&mut self) -> LangResult<()> {
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Punctuation(PunctuationToken::FatArrow)) => Ok(()),
+            _ => self.err(Expected("'=>'")),
+        }
+    }
     /// Consumes a statement.
     fn statement(&mut self) -> LangResult<Statement> {
         use KeywordToken::*;
         match self.next().map(|t| t.class) {
             Some(TokenClass::Keyword(kw)) if kw.starts_statement() => match kw {
                 Become => Ok(Statement::Become(self.expect(Self::expression)?)),
-                Break => self.err(Unimplemented),
+                Break => Ok(Statement::Break),
                 Case => self.err(Unimplemented),
-                Continue => self.err(Unimplemented),
-                Else => self.err(ElseWithoutIf),
-                For => self.err(Unimplemented),
-                If => Ok(Statement::If {
-                    cond_expr: self.expect(Self::expression)?,
-                    if_true: self.expect(Self::block)?.inner,
-                    if_false: if self.next_token_is_one_of(&[TokenClass::Keyword(Else)]) {
+                Continue => Ok(Statement::Continue),
+                Debug => Ok(Statement::Debug(self.expect(Self::expression)?)),
+                Assert => Ok({
+                    let cond_expr = self.expect(Self::expression)?;
+                    // Get the message, if any.
+                    let comma = &[TokenClass::Punctuation(PunctuationToken::Comma)];
+                    let msg = if self.next_token_is_one_of(comma) {
+                        self.next();
+                        Some(self.expect(Self::string)?)
+                    } else {
+                        None
+                    };
+                    Statement::Assert { cond_expr, msg }
+                }),
+                Else | Elif => self.err(ElseWithoutIf),
+                For => Ok({
+                    // Get the loop variable name.
+                    let var_name = self.expect(Self::ident)?;
+                    match self.next().map(|t| t.class) {
+                        Some(TokenClass::Keyword(In)) => (),
+                        _ => self.err(Expected("'in'"))?,
+                    }
+                    // Check for a loop over the neighborhood, which is the
+                    // identifier `neighborhood` followed directly by the loop
+                    // body.
+                    let mut tmp = *self;
+                    if tmp.next().map(|t| t.class) == Some(TokenClass::Ident("neighborhood"))
+                        && tmp.next_token_is_one_of(&[TokenClass::Punctuation(
+                            PunctuationToken::LBrace,
+                        )])
+                    {
+                        self.next();
+                        let body = self.expect(Self::block)?.inner;
+                        return Ok(Statement::ForNeighbors { var_name, body });
+                    }
+                    // Get the range to iterate over.
+                    let start_expr = self.expect(Self::expression)?;
+                    match self.next().map(|t| t.class) {
+                        Some(TokenClass::Operator(OperatorToken::DotDot)) => (),
+                        _ => self.err(Expected("range, e.g. '0..10'"))?,
+                    }
+                    let end_expr = self.expect(Self::expression)?;
+                    // Get the loop body.
+                    let body = self.expect(Self::block)?.inner;
+                    Statement::For {
+                        var_name,
+                        start_expr,
+                        end_expr,
+                        body,
+                    }
+                }),
+                If => Ok({
+                    let mut arms = vec![];
+                    let mut else_block = vec![];
+                    loop {
+                        let cond_expr = self.expect(Self::expression)?;
+                        arms.push((cond_expr, self.expect(Self::block)?.inner));
+                        if self.next_token_is_one_of(&[TokenClass::Keyword(Elif)]) {
+                            // "elif" is shorthand for "else if," so add
+                            // another arm to this same statement.
+                            self.next();
+                            continue;
+                        }
+                        if !self.next_token_is_one_of(&[TokenClass::Keyword(Else)]) {
+                            // There's no "else" clause, so just pretend that
+                            // there is one and it's empty.
+                            break;
+                        }
                         // There's an "else" clause.
                         self.next();
                         if self.next_token_is_one_of(&[TokenClass::Keyword(If)]) {
-                            // This is actually an "else if" clause. Treat this
-                            // as an "if" nested inside an "else."
-                            vec![self.expect(Self::statement)?]
+                            // This is actually an "else if" clause, so add
+                            // another arm to this same statement.
+                            self.next();
                         } else {
                             // This is just a normal "else" clause, not "else
                             // if."
-                            self.expect(Self::block)?.inner
+                            else_block = self.expect(Self::block)?.inner;
+                            break;
                         }
-                    } else {
-                        // There's no "else" clause, so just pretend that there
-                        // is one and it's empty.
-                        vec![]
-                    },
+                    }
+                    Statement::If { arms, else_block }
+                }),
+                Match => Ok({
+                    let expr = self.expect(Self::expression)?;
+                    let (arms, else_arm) = self.expect(Self::match_arms)?.inner;
+                    Statement::Match {
+                        expr,
+                        arms,
+                        else_arm,
+                    }
                 }),
-                Remain => self.err(Unimplemented),
+                Remain => Ok(Statement::Remain),
                 Return => Ok(Statement::Return(self.expect(Self::expression)?)),
                 Set => Ok({
-                    // Get the variable name.
-                    let var_name = self.expect(Self::ident)?;
+                    let comma = &[TokenClass::Punctuation(PunctuationToken::Comma)];
+                    // Get the variable names.
+                    let mut var_names = vec![self.expect(Self::ident)?];
+                    while self.next_token_is_one_of(comma) {
+                        self.next();
+                        var_names.push(self.expect(Self::ident)?);
+                    }
                     // Get the operator to use when assigning (if any). E.g.
                     // `+=` uses the `+` operator.
                     let assign_op = self.expect(Self::assign_op)?.inner;
-                    // Get the expression to assign into the variable.
-                    let value_expr = self.expect(Self::expression)?;
+                    // Get any more variable names in a chained assignment,
+                    // such as `set a = b = 0`.
+                    if var_names.len() == 1 && assign_op == AssignmentToken::Assign {
+                        loop {
+                            let mut tmp = *self;
+                            match (tmp.next(), tmp.next()) {
+                                (
+                                    Some(Token {
+                                        class: TokenClass::Ident(_),
+                                        ..
+                                    }),
+                                    Some(Token {
+                                        class: TokenClass::Assignment(AssignmentToken::Assign),
+                                        ..
+                                    }),
+                                ) => {
+                                    var_names.push(self.expect(Self::ident)?);
+                                    self.next();
+                                }
+                                _ => break,
+                            }
+                        }
+                        if var_names.len() > 1 {
+                            return Ok(Statement::SetVarChain {
+                                var_names,
+                                value_expr: self.expect(Self::expression)?,
+                            });
+                        }
+                    }
+                    // Get the expressions to assign into the variables.
+                    let mut value_exprs = vec![self.expect(Self::expression)?];
+                    while self.next_token_is_one_of(comma) {
+                        self.next();
+                        value_exprs.push(self.expect(Self::expression)?);
+                    }
                     // Construct the statement.
-                    Statement::SetVar {
-                        var_name,
-                        assign_op,
-                        value_expr,
+                    if var_names.len() == 1 && value_exprs.len() == 1 {
+                        Statement::SetVar {
+                            var_name: var_names.pop().unwrap(),
+                            assign_op,
+                            value_expr: value_exprs.pop().unwrap(),
+                        }
+                    } else {
+                        Statement::SetVars {
+                            var_names,
+                            assign_op,
+                            value_exprs,
+                        }
                     }
                 }),
                 Unless => self.err(Unimplemented),
-                While => self.err(Unimplemented),
+                While => Ok(Statement::While {
+                    cond_expr: self.expect(Self::expression)?,
+                    body: self.expect(Self::block)?.inner,
+                }),
                 _ => self.err(Expected("statement")),
             },
             _ => {
@@ -350,10 +630,32 @@ impl<'a> ParseBuilder<'a> {
         }
     }
     /// Consumes a nested expression.
+    ///
+    /// Returns an error if the expression is nested more than MAX_EXPR_DEPTH
+    /// levels deep, or if its tree of operations is more than
+    /// MAX_EXPR_TREE_DEPTH levels deep, so that pathological expressions cannot
+    /// overflow the stack when parsing or compiling them.
     fn expression(&mut self) -> LangResult<Expr> {
         // Start at the lowest precedence level.
-        self.expression_with_precedence(OpPrecedence::lowest())
-            .map(|spanned| spanned.inner)
+        let expr = self.nested(|pb| pb.expression_with_precedence(OpPrecedence::lowest()))?;
+        // Long chains of operators are parsed without recursion, but still
+        // produce deeply nested expressions. These are common (e.g. a
+        // weighted sum over the whole neighborhood), so allow more of them.
+        if self.expr_depth == 0 && expr.inner.is_deeper_than(MAX_EXPR_TREE_DEPTH) {
+            return Err(ExpressionTooDeep.with_span(expr.span));
+        }
+        Ok(expr.inner)
+    }
+    /// Executes the given closure one level of expression nesting deeper,
+    /// returning an error instead if that is too deep.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> LangResult<T>) -> LangResult<T> {
+        if self.expr_depth >= MAX_EXPR_DEPTH {
+            return self.err(ExpressionTooDeep);
+        }
+        self.expr_depth += 1;
+        let ret = f(self);
+        self.expr_depth -= 1;
+        ret
     }
     /// Consumes a nested expression of the given precedence level and higher
     /// recursively using precedence climbing.
@@ -364,10 +666,25 @@ impl<'a> ParseBuilder<'a> {
         // Get an expression at the given precedence level, which may
         // consist of expressions with higher precedence.
         match precedence {
+            OpPrecedence::LogicalOr => {
+                self.left_binary_op(&[TokenClass::Keyword(KeywordToken::Or)], precedence)
+            }
+            OpPrecedence::LogicalXor => {
+                self.left_binary_op(&[TokenClass::Keyword(KeywordToken::Xor)], precedence)
+            }
+            OpPrecedence::LogicalAnd => {
+                self.left_binary_op(&[TokenClass::Keyword(KeywordToken::And)], precedence)
+            }
+            OpPrecedence::LogicalNot => {
+                self.unary_op(&[TokenClass::Keyword(KeywordToken::Not)], precedence)
+            }
             OpPrecedence::UnaryPrefix => self.unary_op(
                 &[
                     TokenClass::Operator(OperatorToken::Tag),
+                    TokenClass::Operator(OperatorToken::TagClamp),
+                    TokenClass::Operator(OperatorToken::Plus),
                     TokenClass::Operator(OperatorToken::Minus),
+                    TokenClass::Operator(OperatorToken::Tilde),
                 ],
                 precedence,
             ),
@@ -383,10 +700,32 @@ impl<'a> ParseBuilder<'a> {
                     TokenClass::Operator(OperatorToken::Asterisk),
                     TokenClass::Operator(OperatorToken::Slash),
                     TokenClass::Operator(OperatorToken::Percent),
+                    TokenClass::Operator(OperatorToken::DoublePercent),
+                    TokenClass::Operator(OperatorToken::SlashPercent),
                 ],
                 precedence,
             ),
+            OpPrecedence::Exp => self.exponent_op(precedence),
             OpPrecedence::Comparison => self.comparison_op(precedence),
+            OpPrecedence::BitwiseOr => {
+                self.left_binary_op(&[TokenClass::Operator(OperatorToken::Pipe)], precedence)
+            }
+            OpPrecedence::BitwiseXor => {
+                self.left_binary_op(&[TokenClass::Operator(OperatorToken::Caret)], precedence)
+            }
+            OpPrecedence::BitwiseAnd => self.left_binary_op(
+                &[TokenClass::Operator(OperatorToken::Ampersand)],
+                precedence,
+            ),
+            OpPrecedence::Bitshift => self.left_binary_op(
+                &[
+                    TokenClass::Operator(OperatorToken::DoubleLessThan),
+                    TokenClass::Operator(OperatorToken::DoubleGreaterThan),
+                    TokenClass::Operator(OperatorToken::TripleGreaterThan),
+                ],
+                precedence,
+            ),
+            OpPrecedence::ArrayIndex => self.array_index(precedence),
             // TODO add remaining precedence levels
             OpPrecedence::Atom => match self.peek_next().map(|t| t.class) {
                 Some(TokenClass::Punctuation(PunctuationToken::LParen)) => {
@@ -394,12 +733,30 @@ impl<'a> ParseBuilder<'a> {
                     // lowest precedence level.
                     self.expect_spanned(|pb| pb.paren(Self::expression))
                 }
+                Some(TokenClass::Punctuation(PunctuationToken::LBracket)) => {
+                    self.expect(Self::vector)
+                }
+                Some(TokenClass::Keyword(KeywordToken::If)) => self.expect(Self::if_expr),
+                Some(TokenClass::Keyword(KeywordToken::True))
+                | Some(TokenClass::Keyword(KeywordToken::False)) => self.expect(Self::bool),
+                Some(TokenClass::Keyword(KeywordToken::This)) => self.expect(Self::this),
                 Some(TokenClass::Integer(_)) => self.expect(Self::int),
                 Some(TokenClass::String { .. }) => self.err(Unimplemented),
                 Some(TokenClass::Tag(_)) => self.err(Unimplemented),
-                Some(TokenClass::Ident(_)) => self
-                    .expect(Self::ident)
-                    .map(|spanned| spanned.map(Expr::Ident)),
+                // Type names can be called like functions to convert values.
+                Some(TokenClass::Ident(_)) | Some(TokenClass::Type(_)) => {
+                    // Look ahead to see whether this is a function call.
+                    let mut tmp = *self;
+                    tmp.next();
+                    if tmp
+                        .next_token_is_one_of(&[TokenClass::Punctuation(PunctuationToken::LParen)])
+                    {
+                        self.expect(Self::fn_call)
+                    } else {
+                        self.expect(Self::ident)
+                            .map(|spanned| spanned.map(Expr::Ident))
+                    }
+                }
                 _ => {
                     self.next();
                     self.err(Expected("expression"))
@@ -428,9 +785,10 @@ impl<'a> ParseBuilder<'a> {
         for op_token in op_tokens.iter().rev() {
             let operand = Box::new(ret);
             ret = Spanned {
-                span: Span::merge(op_token, &*operand),
+                span: op_token.span.join(operand.span),
                 inner: match op_token.class {
                     TokenClass::Operator(op) => Expr::UnaryOp { op, operand },
+                    TokenClass::Keyword(KeywordToken::Not) => Expr::LogicalNot(operand),
                     other => Err(InternalError(
                         format!("Invalid unary operator: {:?}", other).into(),
                     ))?,
@@ -439,6 +797,23 @@ impl<'a> ParseBuilder<'a> {
         }
         Ok(ret)
     }
+    /// Consumes an expression of the given precedence level or higher followed
+    /// by any number of indices in square brackets.
+    fn array_index(&mut self, precedence: OpPrecedence) -> LangResult<Spanned<Expr>> {
+        let mut ret = self.expression_with_precedence(precedence.next())?;
+        while self.next_token_is_one_of(&[TokenClass::Punctuation(PunctuationToken::LBracket)]) {
+            let index = self.expect(|pb| pb.bracket(Self::expression))?;
+            let expr = Box::new(ret);
+            ret = Spanned {
+                span: expr.span.join(index.span),
+                inner: Expr::Index {
+                    expr,
+                    index: Box::new(index.inner),
+                },
+            };
+        }
+        Ok(ret)
+    }
     /// Consumes an expression consisting of any number of the given
     /// left-associative binary operators applied to expressions of the given
     /// precedence level or higher.
@@ -464,9 +839,10 @@ impl<'a> ParseBuilder<'a> {
             let lhs = Box::new(ret);
             let rhs = Box::new(rhs);
             ret = Spanned {
-                span: Span::merge(&*lhs, &*rhs),
+                span: lhs.span.join(rhs.span),
                 inner: match op_token.class {
                     TokenClass::Operator(op) => Expr::BinaryOp { lhs, op, rhs },
+                    TokenClass::Keyword(op) => Expr::LogicalOp { lhs, op, rhs },
                     other => Err(InternalError(
                         format!("Invalid unary operator: {:?}", other).into(),
                     ))?,
@@ -475,20 +851,106 @@ impl<'a> ParseBuilder<'a> {
         }
         Ok(ret)
     }
+    /// Consumes an expression consisting of any number of right-associative
+    /// exponentiation operators applied to expressions of the given precedence
+    /// level or higher.
+    ///
+    /// The exponent may itself have unary prefix operators, so `2 ** -x` is
+    /// `2 ** (-x)` and `2 ** 3 ** 2` is `2 ** (3 ** 2)`. The base may not, so
+    /// `-2 ** 2` is `-(2 ** 2)`.
+    fn exponent_op(&mut self, precedence: OpPrecedence) -> LangResult<Spanned<Expr>> {
+        let first_token = self.peek_next();
+        let mut lhs = self.expression_with_precedence(precedence.next())?;
+        let op = match self.peek_next().map(|t| t.class) {
+            Some(TokenClass::Operator(op @ OperatorToken::DoubleAsterisk)) => op,
+            _ => return Ok(lhs),
+        };
+        self.next();
+        // Parsing the exponent at the unary prefix level recurses back into
+        // this function, which makes the operator right-associative.
+        let rhs = self.nested(|pb| pb.expression_with_precedence(OpPrecedence::UnaryPrefix))?;
+        let rhs = Box::new(rhs);
+        let span = lhs.span.join(rhs.span);
+        // A negative integer literal such as `-2` is a single token, but its
+        // sign should still apply to the whole power.
+        let mut negate = false;
+        if let Some(Token {
+            class: TokenClass::Integer(i),
+            span: literal_span,
+            ..
+        }) = first_token
+        {
+            if let (true, Some(abs)) = (literal_span == lhs.span, i.checked_neg()) {
+                if abs > 0 {
+                    lhs = Spanned::new(literal_span.start + 1, literal_span.end, Expr::Int(abs));
+                    negate = true;
+                }
+            }
+        }
+        let lhs = Box::new(lhs);
+        let power = Spanned {
+            span: lhs.span.join(rhs.span),
+            inner: Expr::BinaryOp { lhs, op, rhs },
+        };
+        Ok(if negate {
+            Spanned {
+                span,
+                inner: Expr::UnaryOp {
+                    op: OperatorToken::Minus,
+                    operand: Box::new(power),
+                },
+            }
+        } else {
+            power
+        })
+    }
     /// Consumes an expression consisting of any number of chained comparison
     /// operators. This function is similar to left_binary_op().
     fn comparison_op(&mut self, precedence: OpPrecedence) -> LangResult<Spanned<Expr>> {
         // Get the leftmost expression.
         let mut expressions = vec![self.expression_with_precedence(precedence.next())?];
+        // Handle range membership tests, such as `x in 2..4`.
+        if let Some(Token {
+            class: TokenClass::Keyword(KeywordToken::In),
+            span,
+            ..
+        }) = self.peek_next()
+        {
+            self.next();
+            return self.in_range(expressions.pop().unwrap(), span, precedence);
+        }
+        // Handle negated range membership tests, such as `x not in 2..4`,
+        // which are the logical NOT of the same test using `in`.
+        let mut tmp = *self;
+        if let (
+            Some(TokenClass::Keyword(KeywordToken::Not)),
+            Some(Token {
+                class: TokenClass::Keyword(KeywordToken::In),
+                span,
+                ..
+            }),
+        ) = (tmp.next().map(|t| t.class), tmp.next())
+        {
+            *self = tmp;
+            let in_range = self.in_range(expressions.pop().unwrap(), span, precedence)?;
+            return Ok(Spanned {
+                span: in_range.span,
+                inner: Expr::LogicalNot(Box::new(in_range)),
+            });
+        }
         let mut comparisons = vec![];
         // Alternate between getting a comparison operator and an expression.
         while let Some(Token {
             class: TokenClass::Comparison(cmp_type),
+            span,
             ..
         }) = self.peek_next()
         {
             self.next();
-            comparisons.push(cmp_type);
+            comparisons.push(Spanned {
+                span,
+                inner: cmp_type,
+            });
             expressions.push(self.expression_with_precedence(precedence.next())?);
         }
         // If there are no comparisons happening, just return the expression.
@@ -498,13 +960,113 @@ impl<'a> ParseBuilder<'a> {
         Ok(Spanned {
             // This comparison spans from the leftmost expression to the
             // rightmost expression.
-            span: Span::merge(&expressions[0], expressions.last().unwrap()),
+            span: expressions[0].span.join(expressions.last().unwrap().span),
             inner: Expr::Cmp {
                 exprs: expressions,
                 cmps: comparisons,
             },
         })
     }
+    /// Consumes the range of a range membership test, either `lo..hi`
+    /// (exclusive) or `lo..=hi` (inclusive), and returns the test as a chained
+    /// comparison `lo <= x < hi` or `lo <= x <= hi`.
+    fn in_range(
+        &mut self,
+        expr: Spanned<Expr>,
+        in_span: Span,
+        precedence: OpPrecedence,
+    ) -> LangResult<Spanned<Expr>> {
+        let lo = self.expression_with_precedence(precedence.next())?;
+        let upper_cmp = match self.next() {
+            Some(Token {
+                class: TokenClass::Operator(op),
+                span,
+                ..
+            }) if op == OperatorToken::DotDot || op == OperatorToken::DotDotEq => Spanned {
+                span,
+                inner: if op == OperatorToken::DotDot {
+                    ComparisonToken::Lt
+                } else {
+                    ComparisonToken::Lte
+                },
+            },
+            _ => self.err(Expected("range, e.g. '0..10' or '0..=9'"))?,
+        };
+        let hi = self.expression_with_precedence(precedence.next())?;
+        Ok(Spanned {
+            span: expr.span.join(hi.span),
+            inner: Expr::Cmp {
+                exprs: vec![lo, expr, hi],
+                cmps: vec![
+                    Spanned {
+                        span: in_span,
+                        inner: ComparisonToken::Lte,
+                    },
+                    upper_cmp,
+                ],
+            },
+        })
+    }
+    /// Consumes a conditional expression of the form `if cond then a else b`.
+    fn if_expr(&mut self) -> LangResult<Expr> {
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Keyword(KeywordToken::If)) => (),
+            _ => self.err(Expected("conditional expression beginning with 'if'"))?,
+        }
+        let cond_expr = Box::new(self.expect(Self::expression)?);
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Keyword(KeywordToken::Then)) => (),
+            _ => self.err(Expected("'then'"))?,
+        }
+        let if_true = Box::new(self.expect(Self::expression)?);
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Keyword(KeywordToken::Else)) => (),
+            _ => self.err(Expected("'else'"))?,
+        }
+        let if_false = Box::new(self.expect(Self::expression)?);
+        Ok(Expr::IfExpr {
+            cond_expr,
+            if_true,
+            if_false,
+        })
+    }
+    /// Consumes a function call, consisting of a function name followed by a
+    /// parenthesized list of arguments.
+    fn fn_call(&mut self) -> LangResult<Expr> {
+        let func = self.expect(Self::fn_name)?;
+        let args = self
+            .expect(|pb| {
+                pb.paren(|pb| {
+                    pb.list(
+                        &[TokenClass::Punctuation(PunctuationToken::Comma)],
+                        &[TokenClass::Punctuation(PunctuationToken::RParen)],
+                        Self::expression,
+                        "function argument",
+                    )
+                })
+            })?
+            .inner
+            .inner;
+        Ok(Expr::FnCall { func, args })
+    }
+    /// Consumes a vector literal, consisting of a bracketed list of
+    /// components.
+    fn vector(&mut self) -> LangResult<Expr> {
+        let components = self
+            .expect(|pb| {
+                pb.bracket(|pb| {
+                    pb.list(
+                        &[TokenClass::Punctuation(PunctuationToken::Comma)],
+                        &[TokenClass::Punctuation(PunctuationToken::RBracket)],
+                        Self::expression,
+                        "vector component",
+                    )
+                })
+            })?
+            .inner
+            .inner;
+        Ok(Expr::Vector(components))
+    }
     /// Consumes an integer literal.
     fn int(&mut self) -> LangResult<Expr> {
         match self.next().map(|t| t.class) {
@@ -512,11 +1074,40 @@ impl<'a> ParseBuilder<'a> {
             _ => self.err(Expected("integer")),
         }
     }
+    /// Consumes a string literal without a prefix and returns its contents,
+    /// with escape sequences replaced.
+    fn string(&mut self) -> LangResult<String> {
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::String {
+                prefix: None,
+                contents,
+                ..
+            }) => Ok(unescape_string(contents)),
+            _ => self.err(Expected("string")),
+        }
+    }
+    /// Consumes a boolean literal.
+    fn bool(&mut self) -> LangResult<Expr> {
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Keyword(KeywordToken::True)) => Ok(Expr::Bool(true)),
+            Some(TokenClass::Keyword(KeywordToken::False)) => Ok(Expr::Bool(false)),
+            _ => self.err(Expected("boolean")),
+        }
+    }
+    /// Consumes the `this` keyword.
+    fn this(&mut self) -> LangResult<Expr> {
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Keyword(KeywordToken::This)) => Ok(Expr::This),
+            _ => self.err(Expected("'this'")),
+        }
+    }
     /// Consumes a type name.
     fn type_name(&mut self) -> LangResult<TypeToken> {
         match self.next().map(|t| t.class) {
             Some(TokenClass::Type(ty)) => Ok(ty),
-            _ => self.err(Expected("type name; e.g. int, cellstate, vec, vec1..256")),
+            _ => self.err(Expected(
+                "type name; e.g. int, bool, cellstate, vec, vec1..256",
+            )),
         }
     }
     /// Consumes an identifier.
@@ -528,6 +1119,17 @@ impl<'a> ParseBuilder<'a> {
             _ => self.err(Expected("identifier, i.e. variable or function name")),
         }
     }
+    /// Consumes the name of a function, which may be either an identifier or
+    /// a type name (for type conversion functions).
+    fn fn_name(&mut self) -> LangResult<String> {
+        match self.peek_next().map(|t| t.class) {
+            Some(TokenClass::Type(ty)) => {
+                self.next();
+                Ok(ty.to_string())
+            }
+            _ => self.ident(),
+        }
+    }
     /// Consumes an assignment token and returns the operator used in the
     /// assignment, if any. (E.g. `+=` uses the `+` operator, while `=` does not
     /// use any operator.)
@@ -555,6 +1157,24 @@ impl<'a> ParseBuilder<'a> {
             None => Err(Unmatched('(', ')').with_span(open_span)),
         }
     }
+    /// Consumes a pair of square brackets with the given matcher run inside.
+    fn bracket<T>(
+        &mut self,
+        inner_matcher: impl FnOnce(&mut Self) -> LangResult<T>,
+    ) -> LangResult<Spanned<T>> {
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Punctuation(PunctuationToken::LBracket)) => (),
+            _ => self.err(Expected("bracketed expression beginning with '['"))?,
+        }
+        // Record the span of the left bracket.
+        let open_span = self.span();
+        let expr = self.expect(inner_matcher)?;
+        match self.next().map(|t| t.class) {
+            Some(TokenClass::Punctuation(PunctuationToken::RBracket)) => Ok(expr),
+            Some(_) => self.err(Expected("']'")),
+            None => Err(Unmatched('[', ']').with_span(open_span)),
+        }
+    }
     /// Consumes a list of things (using the given matcher) separated by a given
     /// separator token and ending with any of the given end tokens.
     ///
@@ -6,7 +6,7 @@ use std::rc::Rc;
 
 use super::super::errors::*;
 use super::super::lexer::{
-    AssignmentToken, ComparisonToken, OperatorToken, PunctuationToken, TypeToken,
+    AssignmentToken, ComparisonToken, KeywordToken, OperatorToken, PunctuationToken, TypeToken,
 };
 use super::super::{Span, Spanned};
 use LangErrorMsg::RepeatDirective;
@@ -51,25 +51,61 @@ impl ParseTree {
 }
 
 pub type StatementBlock = Vec<Spanned<Statement>>;
+pub type MatchArm = (Spanned<Expr>, StatementBlock);
+pub type IfArm = (Spanned<Expr>, StatementBlock);
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Directive {
+    /// Display name of the rule.
+    Name,
+    /// Longer description of the rule.
+    Description,
+    /// Named constant (declared using `const` rather than `@`).
+    Const,
+    /// Number of layers of cell data.
+    Layers,
     /// Transition function.
     Transition,
     /// State definitions.
     States,
+    /// Names of cell states.
+    Names,
     /// Number of dimensions.
     Dimensions,
+    /// Set of neighbor offsets.
+    Neighborhood,
+    /// Symmetry of the transition function.
+    Symmetry,
     /// Helper function.
     Function,
+    /// Behavior of integer arithmetic on overflow.
+    Arithmetic,
+    /// Maximum number of loop iterations per function call.
+    MaxSteps,
+    /// Cell state to use when the transition function ends without `become`
+    /// or `remain`.
+    DefaultState,
+    /// Version of the language that the rule targets.
+    NdcaVersion,
 }
 impl Directive {
     pub fn name(self) -> &'static str {
         match self {
+            Self::Name => "name",
+            Self::Description => "description",
+            Self::Const => "const",
+            Self::Layers => "layers",
             Self::Transition => "transition",
             Self::States => "states",
+            Self::Names => "names",
             Self::Dimensions => "dimensions",
+            Self::Neighborhood => "neighborhood",
+            Self::Symmetry => "symmetry",
             Self::Function => "function",
+            Self::Arithmetic => "arithmetic",
+            Self::MaxSteps => "max_steps",
+            Self::DefaultState => "default_state",
+            Self::NdcaVersion => "ndca_version",
         }
     }
 }
@@ -77,10 +113,20 @@ impl TryFrom<&str> for Directive {
     type Error = ();
     fn try_from(s: &str) -> Result<Self, ()> {
         match s.to_ascii_lowercase().as_ref() {
+            "name" => Ok(Self::Name),
+            "description" => Ok(Self::Description),
+            "layers" => Ok(Self::Layers),
             "transition" => Ok(Self::Transition),
             "states" => Ok(Self::States),
+            "names" => Ok(Self::Names),
             "dim" | "dimen" | "dimensions" | "ndim" => Ok(Self::Dimensions),
+            "neighborhood" => Ok(Self::Neighborhood),
+            "symmetry" => Ok(Self::Symmetry),
             "fn" | "function" => Ok(Self::Function),
+            "arithmetic" => Ok(Self::Arithmetic),
+            "max_steps" => Ok(Self::MaxSteps),
+            "default_state" => Ok(Self::DefaultState),
+            "ndca_version" => Ok(Self::NdcaVersion),
             _ => Err(()),
         }
     }
@@ -93,8 +139,17 @@ pub enum DirectiveContents {
     Block(Spanned<StatementBlock>),
     /// Expression.
     Expr(Spanned<Expr>),
+    /// String literal.
+    String(Spanned<String>),
     /// Function definition.
     Func(HelperFunc),
+    /// Constant definition.
+    Const {
+        /// Name of the constant.
+        name: Spanned<String>,
+        /// Value of the constant.
+        value: Spanned<Expr>,
+    },
 }
 impl From<Spanned<StatementBlock>> for DirectiveContents {
     fn from(block: Spanned<StatementBlock>) -> Self {
@@ -132,27 +187,87 @@ pub enum Statement {
         /// Value to store in the variable.
         value_expr: Spanned<Expr>,
     },
-    /// Branches conditionally.
+    /// Sets multiple variable values at once, evaluating all values before
+    /// storing any of them.
+    SetVars {
+        /// Variables to set.
+        var_names: Vec<Spanned<String>>,
+        /// Assignment operator.
+        assign_op: AssignmentToken,
+        /// Values to store in the variables.
+        value_exprs: Vec<Spanned<Expr>>,
+    },
+    /// Sets multiple variables to the same value, such as `set a = b = 0`.
+    SetVarChain {
+        /// Variables to set.
+        var_names: Vec<Spanned<String>>,
+        /// Value to store in every variable.
+        value_expr: Spanned<Expr>,
+    },
+    /// Branches conditionally, with any number of `else if` arms.
     If {
+        /// Arms, each consisting of a condition and statements to execute if
+        /// that condition is the first one that is truthy.
+        arms: Vec<IfArm>,
+        /// Statements to execute if every condition is falsey.
+        else_block: StatementBlock,
+    },
+    /// Branches based on which of several values an expression is equal to.
+    Match {
+        /// Value to match against.
+        expr: Spanned<Expr>,
+        /// Arms, each consisting of a value and statements to execute if the
+        /// expression is equal to that value.
+        arms: Vec<MatchArm>,
+        /// Statements to execute if no arm matches.
+        else_arm: Option<StatementBlock>,
+    },
+    /// Loops over a half-open range of integers.
+    For {
+        /// Loop variable.
+        var_name: Spanned<String>,
+        /// Start of the range (inclusive).
+        start_expr: Spanned<Expr>,
+        /// End of the range (exclusive).
+        end_expr: Spanned<Expr>,
+        /// Statements to execute each iteration.
+        body: StatementBlock,
+    },
+    /// Loops over the cell states of the neighbors declared by the
+    /// `@neighborhood` directive.
+    ForNeighbors {
+        /// Loop variable.
+        var_name: Spanned<String>,
+        /// Statements to execute each iteration.
+        body: StatementBlock,
+    },
+    /// Loops while a condition is truthy.
+    While {
         /// Condition.
         cond_expr: Spanned<Expr>,
-        /// Statements to execute if condition is truthy.
-        if_true: StatementBlock,
-        /// Statements to execute if condition is falsey.
-        if_false: StatementBlock,
+        /// Statements to execute each iteration.
+        body: StatementBlock,
     },
-    // ForLoop(Spanned<Expr>, Spanned<Expr>, StatementBlock),
-    // WhileLoop(Spanned<Expr>, StatementBlock),
     // DoWhileLoop(StatementBlock, Spanned<Expr>),
-    // Break,
-    // Continue,
-
-    // /// Returns the center cell state from the transition function.
-    // Remain,
+    /// Exits the innermost loop.
+    Break,
+    /// Skips to the next iteration of the innermost loop.
+    Continue,
+    /// Returns the center cell state from the transition function.
+    Remain,
     /// Returns a value from a transition function.
     Become(Spanned<Expr>),
     /// Returns a value from a helper function.
     Return(Spanned<Expr>),
+    /// Evaluates a value for debugging, without using the result.
+    Debug(Spanned<Expr>),
+    /// Returns an error if a condition is false.
+    Assert {
+        /// Condition that must be true.
+        cond_expr: Spanned<Expr>,
+        /// Message to include in the error.
+        msg: Option<Spanned<String>>,
+    },
 }
 
 /// Expression node in the parse tree.
@@ -160,6 +275,10 @@ pub enum Statement {
 pub enum Expr {
     /// Integer literal.
     Int(i64),
+    /// Boolean literal.
+    Bool(bool),
+    /// State of the center cell.
+    This,
     /// Identifier.
     Ident(String),
     /// Parethetical or bracketed group.
@@ -171,6 +290,15 @@ pub enum Expr {
     },
     /// Comma-separated list.
     List(Vec<Box<Spanned<Expr>>>),
+    /// Vector literal.
+    Vector(Vec<Spanned<Expr>>),
+    /// Vector component access.
+    Index {
+        /// Vector to index.
+        expr: Box<Spanned<Expr>>,
+        /// Index of the component.
+        index: Box<Spanned<Expr>>,
+    },
     /// Operation on one value.
     UnaryOp {
         /// Operator.
@@ -187,11 +315,71 @@ pub enum Expr {
         /// Right-hand-side operand.
         rhs: Box<Spanned<Expr>>,
     },
+    /// Logical NOT of a value.
+    LogicalNot(Box<Spanned<Expr>>),
+    /// Logical operation on two values.
+    LogicalOp {
+        /// Left-hand-side operand.
+        lhs: Box<Spanned<Expr>>,
+        /// Operator keyword (`and`, `or`, or `xor`).
+        op: KeywordToken,
+        /// Right-hand-side operand.
+        rhs: Box<Spanned<Expr>>,
+    },
+    /// Conditional expression, such as `if cond then a else b`.
+    IfExpr {
+        /// Condition to test.
+        cond_expr: Box<Spanned<Expr>>,
+        /// Value if the condition is truthy.
+        if_true: Box<Spanned<Expr>>,
+        /// Value if the condition is falsey.
+        if_false: Box<Spanned<Expr>>,
+    },
+    /// Function call.
+    FnCall {
+        /// Name of the function.
+        func: Spanned<String>,
+        /// Arguments passed to the function.
+        args: Vec<Spanned<Expr>>,
+    },
     /// Comparison between two values.
     Cmp {
         /// Expressions to compare (at least two).
         exprs: Vec<Spanned<Expr>>,
         /// Comparison operations (one less than the number of expressions).
-        cmps: Vec<ComparisonToken>,
+        cmps: Vec<Spanned<ComparisonToken>>,
     },
 }
+impl Expr {
+    /// Returns whether this expression has subexpressions nested more than
+    /// `max_depth` levels deep. This never looks more than `max_depth + 1`
+    /// levels deep, so it is safe to call on arbitrarily deep expressions.
+    pub fn is_deeper_than(&self, max_depth: usize) -> bool {
+        let children: Vec<&Spanned<Expr>> = match self {
+            Self::Int(_) | Self::Bool(_) | Self::This | Self::Ident(_) => vec![],
+            Self::Group { inner, .. } => vec![&**inner],
+            Self::List(items) => items.iter().map(|item| &**item).collect(),
+            Self::Vector(items) | Self::FnCall { args: items, .. } => items.iter().collect(),
+            Self::Cmp { exprs, .. } => exprs.iter().collect(),
+            Self::Index { expr, index } => vec![&**expr, &**index],
+            Self::UnaryOp { operand, .. } | Self::LogicalNot(operand) => vec![&**operand],
+            Self::BinaryOp { lhs, rhs, .. } | Self::LogicalOp { lhs, rhs, .. } => {
+                vec![&**lhs, &**rhs]
+            }
+            Self::IfExpr {
+                cond_expr,
+                if_true,
+                if_false,
+            } => vec![&**cond_expr, &**if_true, &**if_false],
+        };
+        if children.is_empty() {
+            false
+        } else if max_depth == 0 {
+            true
+        } else {
+            children
+                .iter()
+                .any(|child| child.inner.is_deeper_than(max_depth - 1))
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Functions for running rules from host code.
+//!
+//! The free functions here compile the rule again on every call. To run a rule
+//! on many cells, compile it once into a CompiledRule instead.
+
+use std::rc::Rc;
+
+use super::ast::{self, Rule};
+use super::compiler::{CompiledFunction, Compiler};
+use super::errors::LangResult;
+use super::types::LangCellState;
+use super::ConstValue;
+
+/// Compiles the transition function of the given rule and runs it once with
+/// the given cell states of the neighborhood (or all `#0` if None), returning
+/// the new cell state of the center cell.
+pub fn run_rule(rule: &Rule, neighborhood: Option<&[LangCellState]>) -> LangResult<ConstValue> {
+    let mut compiler = Compiler::new()?;
+    let mut transition_function = rule.compile_transition_function(&mut compiler)?;
+    if let Some(cells) = neighborhood {
+        transition_function.set_neighborhood(cells);
+    }
+    transition_function.call()
+}
+
+/// Compiles the transition function of the given rule once and runs it with
+/// each of the given neighborhoods, returning the new cell state of each
+/// center cell. This is much faster than calling run_rule() in a loop.
+pub fn run_rule_batch(
+    rule: &Rule,
+    neighborhoods: &[&[LangCellState]],
+) -> LangResult<Vec<LangCellState>> {
+    CompiledRule::new(rule)?.run_batch(neighborhoods)
+}
+
+/// Parses the rule with the given source code and runs its transition function
+/// once with a neighborhood of all `#0`, returning the new cell state of the
+/// center cell. Warnings are ignored.
+pub fn compile_and_run(source_code: Rc<String>) -> LangResult<ConstValue> {
+    run_rule(&ast::make_rule(source_code)?, None)
+}
+
+/// Rule whose transition function has been JIT compiled once, so that it can
+/// be run on any number of cells without compiling it again.
+///
+/// The LLVM context is owned by the current thread and the compiled function
+/// keeps its execution engine alive, so this does not borrow the compiler
+/// that built it. Like CompiledFunction, it can be cloned to run the same
+/// rule several times simultaneously.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    /// Compiled transition function.
+    transition_function: CompiledFunction,
+}
+impl CompiledRule {
+    /// JIT compiles the transition function of the given rule.
+    pub fn new(rule: &Rule) -> LangResult<Self> {
+        let mut compiler = Compiler::new()?;
+        Ok(Self {
+            transition_function: rule.compile_transition_function(&mut compiler)?,
+        })
+    }
+    /// Parses the rule with the given source code and JIT compiles its
+    /// transition function. Warnings are ignored.
+    pub fn from_source(source_code: Rc<String>) -> LangResult<Self> {
+        Self::new(&ast::make_rule(source_code)?)
+    }
+
+    /// Returns the number of cells in the neighborhood passed to run().
+    pub fn neighborhood_len(&self) -> usize {
+        self.transition_function.neighborhood_len()
+    }
+
+    /// Runs the transition function with the given cell states of the
+    /// neighborhood and returns the new cell state of the center cell.
+    ///
+    /// Panics if given a neighborhood of the wrong size.
+    pub fn run(&mut self, neighborhood: &[LangCellState]) -> LangResult<LangCellState> {
+        self.transition_function.set_neighborhood(neighborhood);
+        self.transition_function.call()?.as_cell_state()
+    }
+    /// Runs the transition function with each of the given neighborhoods and
+    /// returns the new cell state of each center cell, stopping at the first
+    /// error.
+    ///
+    /// Panics if given a neighborhood of the wrong size.
+    pub fn run_batch(
+        &mut self,
+        neighborhoods: &[&[LangCellState]],
+    ) -> LangResult<Vec<LangCellState>> {
+        self.transition_function.call_batch(neighborhoods)
+    }
+}
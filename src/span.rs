@@ -11,12 +11,14 @@ lazy_static! {
     static ref NEWLINE_PATTERN: Regex = Regex::new("\r\n?|\n").unwrap();
 }
 
-/// The 1-indexed line and column number of a given character index in a string.
+/// The 1-indexed line and column number of a given byte index in a string.
+///
+/// Columns count Unicode scalar values (`char`s), not bytes.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct TextPoint(pub usize, pub usize);
 impl TextPoint {
-    /// Finds the line and column number of the given character index in the
-    /// given string.
+    /// Finds the line and column number of the given byte index in the given
+    /// string.
     pub fn from_idx(s: &str, idx: usize) -> Self {
         let (line_number, line_start_idx) = NEWLINE_PATTERN
             // Find all linebreaks.
@@ -34,8 +36,12 @@ impl TextPoint {
             // If the index occurs before any linebreaks, then this is the first
             // line and the index of the start of this line is 0.
             .unwrap_or((1, 0));
-        // The first column is numbered 1.
-        let column_number = idx - line_start_idx + 1;
+        // Count characters rather than bytes. The first column is numbered 1.
+        let column_number = s[line_start_idx..]
+            .char_indices()
+            .take_while(|&(i, _)| line_start_idx + i < idx)
+            .count()
+            + 1;
         Self(line_number, column_number)
     }
     /// Returns the 1-indexed line number of this text point.
@@ -48,6 +54,84 @@ impl TextPoint {
     }
 }
 
+/// The byte indices of the beginning of each line in a string, used to quickly
+/// find the line and column numbers of many indices in the same string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The byte index of the beginning of each line, in order.
+    line_starts: Vec<usize>,
+    /// The byte index of every multibyte character, in order, along with the
+    /// total number of extra bytes (beyond one per character) taken by that
+    /// character and all the ones before it.
+    multibyte_chars: Vec<(usize, usize)>,
+}
+impl LineIndex {
+    /// Finds the beginning of every line in the given string.
+    pub fn new(s: &str) -> Self {
+        let mut extra_bytes = 0;
+        let multibyte_chars = s
+            .char_indices()
+            .filter(|(_, c)| c.len_utf8() > 1)
+            .map(|(i, c)| {
+                extra_bytes += c.len_utf8() - 1;
+                (i, extra_bytes)
+            })
+            .collect();
+        Self {
+            line_starts: std::iter::once(0)
+                .chain(
+                    NEWLINE_PATTERN
+                        .find_iter(s)
+                        .map(|regex_match| regex_match.end()),
+                )
+                .collect(),
+            multibyte_chars,
+        }
+    }
+    /// Returns the line and column number of the given byte index.
+    pub fn textpoint(&self, idx: usize) -> TextPoint {
+        // Find the last line beginning that occurs before the given index.
+        let line_idx = match self.line_starts.binary_search(&idx) {
+            Ok(line_idx) => line_idx,
+            Err(line_idx) => line_idx - 1,
+        };
+        let line_start_idx = self.line_starts[line_idx];
+        // Count characters rather than bytes. Lines and columns are numbered
+        // starting at 1. Saturate so that an index in the middle of a
+        // multibyte character cannot underflow.
+        let char_count = (idx - line_start_idx)
+            .saturating_sub(self.extra_bytes_before(idx) - self.extra_bytes_before(line_start_idx));
+        TextPoint(line_idx + 1, char_count + 1)
+    }
+    /// Returns the number of extra bytes taken by multibyte characters that
+    /// start before the given byte index.
+    fn extra_bytes_before(&self, idx: usize) -> usize {
+        let char_count = self
+            .multibyte_chars
+            .binary_search_by_key(&idx, |&(i, _)| i)
+            .unwrap_or_else(|i| i);
+        char_count
+            .checked_sub(1)
+            .map_or(0, |i| self.multibyte_chars[i].1)
+    }
+    /// Returns the contents of the given 1-indexed line of the given string,
+    /// which must be the same string that this index was constructed from,
+    /// excluding the linebreak at the end.
+    pub fn line<'a>(&self, s: &'a str, line_number: usize) -> &'a str {
+        let start = self.line_starts[line_number - 1];
+        let end = self
+            .line_starts
+            .get(line_number)
+            .copied()
+            .unwrap_or(s.len());
+        s[start..end].trim_end_matches(|c| c == '\r' || c == '\n')
+    }
+    /// Returns the number of lines in the string.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+}
+
 /// A contiguous span of text from one byte index to another in a &str.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Span {
@@ -72,13 +156,24 @@ impl Span {
             TextPoint::from_idx(string, self.end),
         )
     }
+    /// Returns a pair of TextPoints representing the start and end of this
+    /// span, using a precomputed LineIndex of the string that it applies to.
+    pub fn line_col(self, line_index: &LineIndex) -> (TextPoint, TextPoint) {
+        (
+            line_index.textpoint(self.start),
+            line_index.textpoint(self.end),
+        )
+    }
     /// Returns the smallest contiguous span encompassing the two given spans.
     pub fn merge<T: Into<Span>, U: Into<Span>>(span1: T, span2: U) -> Self {
-        let span1: Span = span1.into();
-        let span2: Span = span2.into();
+        span1.into().join(span2.into())
+    }
+    /// Returns the smallest contiguous span encompassing this span and the
+    /// given one, from the earliest start to the latest end.
+    pub fn join(self, other: Span) -> Self {
         Self {
-            start: std::cmp::min(span1.start, span2.start),
-            end: std::cmp::max(span1.end, span2.end),
+            start: std::cmp::min(self.start, other.start),
+            end: std::cmp::max(self.end, other.end),
         }
     }
 }
@@ -157,5 +252,83 @@ mod tests {
             .map(|idx| TextPoint::from_idx(&s, idx))
             .collect();
         assert_eq!(expected_textpoints, actual_textpoints);
+
+        let line_index = LineIndex::new(&s);
+        let indexed_textpoints: Vec<_> =
+            (0..s.len()).map(|idx| line_index.textpoint(idx)).collect();
+        assert_eq!(expected_textpoints, indexed_textpoints);
+        assert_eq!(4, line_index.line_count());
+        assert_eq!("def", line_index.line(&s, 2));
+        assert_eq!("jkl", line_index.line(&s, 4));
+    }
+
+    #[test]
+    fn test_line_index() {
+        // Build a large string with every kind of linebreak.
+        let s: String = (0..5_000)
+            .map(|i| format!("line {}{}", i, ["\n", "\r\n", "\r"][i % 3]))
+            .collect();
+        let line_index = LineIndex::new(&s);
+        assert_eq!(5_001, line_index.line_count());
+        assert_eq!("line 4999", line_index.line(&s, 5_000));
+        assert_eq!("", line_index.line(&s, 5_001));
+
+        // Check that the precomputed index gives the same results as scanning
+        // the string.
+        let spans: Vec<_> = (0..s.len())
+            .step_by(97)
+            .map(|start| Span {
+                start,
+                end: std::cmp::min(start + 50, s.len()),
+            })
+            .collect();
+        for &span in &spans {
+            assert_eq!(span.textpoints(&s), span.line_col(&line_index));
+        }
+    }
+
+    #[test]
+    fn test_span_join() {
+        let a = Span { start: 3, end: 5 };
+        let b = Span { start: 8, end: 13 };
+        assert_eq!(Span { start: 3, end: 13 }, a.join(b));
+        assert_eq!(Span { start: 3, end: 13 }, b.join(a));
+        // Overlapping and nested spans
+        assert_eq!(b, b.join(Span { start: 9, end: 10 }));
+        assert_eq!(Span { start: 3, end: 9 }, a.join(Span { start: 4, end: 9 }));
+        // Empty spans
+        assert_eq!(a, a.join(Span::empty(4)));
+        assert_eq!(Span { start: 0, end: 5 }, a.join(Span::empty(0)));
+    }
+
+    #[test]
+    fn test_multibyte_textpoint() {
+        let s = "é\nnaïve → 🦀\nx";
+        let line_index = LineIndex::new(s);
+        for &(idx, expected) in &[
+            (0, TextPoint(1, 1)),
+            (2, TextPoint(1, 2)),
+            (3, TextPoint(2, 1)),
+            (7, TextPoint(2, 4)),
+            (10, TextPoint(2, 7)),
+            (13, TextPoint(2, 8)),
+            (14, TextPoint(2, 9)),
+            (18, TextPoint(2, 10)),
+            (19, TextPoint(3, 1)),
+        ] {
+            assert_eq!(expected, TextPoint::from_idx(s, idx));
+            assert_eq!(expected, line_index.textpoint(idx));
+        }
+        // Indices in the middle of a multibyte character must not panic.
+        for idx in 0..=s.len() {
+            let expected_line = if idx < 3 {
+                1
+            } else if idx < 19 {
+                2
+            } else {
+                3
+            };
+            assert_eq!(expected_line, line_index.textpoint(idx).line());
+        }
     }
 }
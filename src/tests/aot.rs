@@ -0,0 +1,119 @@
+use std::fs;
+use std::process;
+use std::rc::Rc;
+
+use super::{ast, Compiler, ConstValue};
+
+#[test]
+fn test_compile_to_object() {
+    let source_code = "
+        @transition {
+            if neighbor(0, 1) == #1 {
+                become #1
+            }
+            remain
+        }";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Rule should compile");
+    let mut compiler = Compiler::new().expect("Failed to create compiler");
+    rule.compile_transition_function(&mut compiler)
+        .expect("Failed to compile transition function");
+
+    let path = std::env::temp_dir().join(format!("ndca_test_{}.o", process::id()));
+    compiler
+        .compile_to_object(&path, None)
+        .expect("Failed to write object file");
+    let bytes = fs::read(&path).expect("Failed to read object file");
+    fs::remove_file(&path).expect("Failed to remove object file");
+
+    // The file should start with the magic number for ELF, Mach-O (64-bit), or
+    // COFF (x86-64 or ARM64).
+    assert!(
+        bytes.starts_with(b"\x7fELF")
+            || bytes.starts_with(&[0xCF, 0xFA, 0xED, 0xFE])
+            || bytes.starts_with(&[0x64, 0x86])
+            || bytes.starts_with(&[0x64, 0xAA]),
+        "Not a valid object file",
+    );
+    // The symbol table should contain the transition function.
+    let symbol = rule.transition_function_symbol();
+    assert_eq!("ndca_transition", symbol);
+    assert!(
+        bytes.windows(symbol.len()).any(|w| w == symbol.as_bytes()),
+        "Object file does not contain symbol {:?}",
+        symbol,
+    );
+
+    // Unknown target triples are rejected.
+    assert!(compiler
+        .compile_to_object(&path, Some("not-a-real-target"))
+        .is_err());
+}
+
+#[test]
+fn test_compile_to_object_with_symmetry() {
+    let source_code = "
+        @symmetry rotate4
+        @transition {
+            if neighbor(0, 1) == #1 {
+                become #1
+            }
+            remain
+        }";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Rule should compile");
+    let mut compiler = Compiler::new().expect("Failed to create compiler");
+    let mut transition_function = rule
+        .compile_transition_function(&mut compiler)
+        .expect("Failed to compile transition function");
+
+    // The exported function applies the symmetry itself, so callers of the
+    // object file do not need to permute the neighborhood.
+    let symbol = rule.transition_function_symbol();
+    let ir = compiler.llvm_fn_ir();
+    assert!(
+        ir.contains(&format!("define i32 @{}(", symbol)),
+        "Function definition not found in LLVM IR:\n{}",
+        ir,
+    );
+    assert!(
+        ir.contains(&format!("call i32 @{}.asymmetric(", symbol)),
+        "Symmetry wrapper not found in LLVM IR:\n{}",
+        ir,
+    );
+    transition_function.set_neighborhood(&[0, 0, 0, 1, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        ConstValue::CellState(1),
+        transition_function
+            .call()
+            .expect("Transition function failed"),
+    );
+
+    let path = std::env::temp_dir().join(format!("ndca_test_symmetry_{}.o", process::id()));
+    compiler
+        .compile_to_object(&path, None)
+        .expect("Failed to write object file");
+    let bytes = fs::read(&path).expect("Failed to read object file");
+    fs::remove_file(&path).expect("Failed to remove object file");
+    assert!(
+        bytes.windows(symbol.len()).any(|w| w == symbol.as_bytes()),
+        "Object file does not contain symbol {:?}",
+        symbol,
+    );
+}
+
+#[test]
+fn test_llvm_ir() {
+    let source_code = include_str!("../../examples/life.ndca");
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Rule should compile");
+    let mut compiler = Compiler::new().expect("Failed to create compiler");
+    rule.compile_transition_function(&mut compiler)
+        .expect("Failed to compile transition function");
+
+    let definition = format!("define i32 @{}(", rule.transition_function_symbol());
+    for ir in &[compiler.llvm_fn_ir(), compiler.llvm_ir()] {
+        assert!(
+            ir.contains(&definition),
+            "Function definition not found in LLVM IR:\n{}",
+            ir,
+        );
+    }
+}
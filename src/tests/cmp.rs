@@ -1,4 +1,4 @@
-use super::{assert_output, ConstValue};
+use super::{assert_func_output, assert_output, ConstValue, LangInt};
 
 #[test]
 fn test_branch_nonzero() {
@@ -6,11 +6,11 @@ fn test_branch_nonzero() {
         Ok(ConstValue::CellState(10)),
         "
         @transition {
-            if 0 {
+            if bool(0) {
             } else {
-                if 1 {
-                    if 2 {
-                        if -1 {
+                if bool(1) {
+                    if bool(2) {
+                        if bool(-1) {
                             become #10
                         }
                     }
@@ -51,3 +51,143 @@ fn test_cmp() {
         }",
     );
 }
+
+#[test]
+fn test_unordered_cmp() {
+    // Cell states can be compared for equality ...
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            if #1 == #1 != #2 {
+                become #1
+            }
+            become #0
+        }
+        @states 3",
+    );
+    // ... but have no ordering.
+    assert_output(
+        Err("Error at line 3; column 19
+if #1 < #2 {
+      ^   [E0032] Type error: cannot compare cell state values using '<' because they have no ordering; convert them to integers first using the '#id' tag"),
+        "
+        @transition {
+            if #1 < #2 {
+                become #1
+            }
+            become #0
+        }
+        @states 3",
+    );
+    assert_output(
+        Err("Error at line 3; column 25
+if #1 == #1 >= #0 {
+            ^^   [E0032] Type error: cannot compare cell state values using '>=' because they have no ordering; convert them to integers first using the '#id' tag"),
+        "
+        @transition {
+            if #1 == #1 >= #0 {
+                become #1
+            }
+            become #0
+        }",
+    );
+    // Neither do booleans.
+    assert_output(
+        Err("Error at line 3; column 21
+if true > false {
+        ^   [E0032] Type error: cannot compare boolean values using '>' because they have no ordering"),
+        "
+        @transition {
+            if true > false {
+                become #1
+            }
+            become #0
+        }",
+    );
+}
+
+#[test]
+fn test_in_range() {
+    for &(range, x, expected) in &[
+        ("2..4", 1, 0),
+        ("2..4", 2, 1),
+        ("2..4", 3, 1),
+        ("2..4", 4, 0),
+        ("2..=4", 1, 0),
+        ("2..=4", 2, 1),
+        ("2..=4", 4, 1),
+        ("2..=4", 5, 0),
+        ("-x..x + 1", 3, 1),
+        ("-x..x + 1", -3, 0),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            &format!(
+                "
+                @function int test(int x) {{
+                    if x in {} and x != 0 {{
+                        return 1
+                    }}
+                    return 0
+                }}",
+                range,
+            ),
+            Some("test"),
+        );
+    }
+
+    // Missing range
+    assert_func_output(
+        &[ConstValue::Int(0)],
+        Err("Error at line 1; column 39
+@function int test(int x) { if x in 2 { return 1 } return 0 }
+                                      ^   [E0005] Expected range, e.g. '0..10' or '0..=9'"),
+        "@function int test(int x) { if x in 2 { return 1 } return 0 }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_not_in_range() {
+    for &(range, lo, hi) in &[("2..4", 2, 3), ("2..=4", 2, 4), ("-3..=-3", -3, -3)] {
+        // `not in` is exactly the complement of `in`.
+        let source_code = format!(
+            "
+            @function int test(int x) {{
+                if (x not in {0}) == (x in {0}) {{
+                    return -1
+                }}
+                return int(x not in {0})
+            }}",
+            range,
+        );
+        for x in lo - 2..=hi + 2 {
+            assert_func_output(
+                &[ConstValue::Int(x)],
+                Ok(ConstValue::Int((x < lo || hi < x) as LangInt)),
+                &source_code,
+                Some("test"),
+            );
+        }
+    }
+
+    // `not in` binds more tightly than logical operators.
+    assert_func_output(
+        &[ConstValue::Int(5)],
+        Ok(ConstValue::Bool(true)),
+        "@function bool test(int x) { return not x not in 0..3 or x not in 6..9 }",
+        Some("test"),
+    );
+
+    // Missing range
+    assert_func_output(
+        &[ConstValue::Int(0)],
+        Err("Error at line 1; column 43
+@function int test(int x) { if x not in 2 { return 1 } return 0 }
+                                          ^   [E0005] Expected range, e.g. '0..10' or '0..=9'"),
+        "@function int test(int x) { if x not in 2 { return 1 } return 0 }",
+        Some("test"),
+    );
+}
@@ -0,0 +1,496 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use super::super::errors::LangErrorMsg;
+use super::super::lexer::ComparisonToken;
+use super::super::{Span, Type};
+use super::{assert_output, ast};
+
+#[test]
+fn test_multiple_type_errors() {
+    let source_code = "
+        @transition {
+            set x = 1
+            set x = #1
+            if 3 {
+                remain
+            }
+        }";
+    assert_errors(
+        &[
+            "Error at line 4; column 21
+set x = #1
+        ^^   [E0020] Type error: expected integer but got cell state",
+            "Error at line 5; column 16
+if 3 {
+   ^   [E0020] Type error: expected boolean but got integer",
+        ],
+        source_code,
+    );
+
+    // Only the first error is returned when not collecting errors.
+    let source_code = Rc::new(source_code.to_owned());
+    let err = ast::make_rule(source_code.clone()).unwrap_err();
+    assert_eq!(
+        "Error at line 4; column 21
+set x = #1
+        ^^   [E0020] Type error: expected integer but got cell state",
+        err.with_source(&source_code).to_string(),
+    );
+}
+
+#[test]
+fn test_static_type_errors() {
+    // Every type error is caught while building the AST, before anything is
+    // compiled.
+    assert_errors(
+        &[
+            "Error at line 3; column 13
+return x > 1
+^^^^^^^^^^^^   [E0020] Type error: expected integer but got boolean",
+            "Error at line 7; column 21
+set y = true
+        ^^^^   [E0020] Type error: expected integer but got boolean",
+            "Error at line 8; column 19
+while y {
+      ^   [E0020] Type error: expected boolean but got integer",
+            "Error at line 11; column 16
+if helper(y) {
+   ^^^^^^^^^   [E0020] Type error: expected boolean but got integer",
+        ],
+        "
+        @function int helper(int x) {
+            return x > 1
+        }
+        @transition {
+            set y = 1
+            set y = true
+            while y {
+                set y = 0
+            }
+            if helper(y) {
+                remain
+            }
+            become #1
+        }",
+    );
+}
+
+#[test]
+fn test_error_without_source() {
+    let source_code = "@transition { become #5 }";
+    let error = ast::make_rule(Rc::new(source_code.to_owned())).unwrap_err();
+    // Without source code, only the code and message are shown.
+    assert_eq!("[E1007] Cell state out of range", error.to_string());
+    assert_eq!(
+        "Error at line 1; column 22
+@transition { become #5 }
+                     ^^   [E1007] Cell state out of range",
+        error.clone().with_source(source_code).to_string(),
+    );
+    assert_eq!(
+        "[E0014] Multiple \"transition\" directives; only one is allowed",
+        LangErrorMsg::RepeatDirective("transition")
+            .without_span()
+            .to_string(),
+    );
+
+    // Errors can be propagated as `std::error::Error`.
+    fn build_rule(source_code: &str) -> Result<ast::Rule, Box<dyn std::error::Error>> {
+        Ok(ast::make_rule(Rc::new(source_code.to_owned()))?)
+    }
+    let error = build_rule(source_code).unwrap_err();
+    assert_eq!("[E1007] Cell state out of range", error.to_string());
+}
+
+#[test]
+fn test_multiple_syntax_errors() {
+    assert_errors(
+        &[
+            "Error at line 3; column 19
+set x 1
+      ^   [E0005] Expected assignment symbol, e.g. '=' or '+='",
+            "Error at line 5; column 17
+set = 3
+    ^   [E0005] Expected identifier, i.e. variable or function name",
+        ],
+        "
+        @transition {
+            set x 1
+            set y = 2
+            set = 3
+        }",
+    );
+}
+
+#[test]
+fn test_caret_at_start() {
+    // Span starting at the first character of the first line
+    assert_output(
+        Err("Error at line 1; column 1
+become #1
+^^^^^^   [E0012] Only directives may appear at the top level of a file"),
+        "become #1",
+    );
+
+    // Zero-length span inside the initial whitespace of a line
+    assert_eq!(
+        "Error at line 1; column 1
+@transition {}
+^   [E9001] This feature is unimplemented",
+        LangErrorMsg::Unimplemented
+            .with_span(Span::empty(0))
+            .with_source("    @transition {}")
+            .to_string(),
+    );
+}
+
+#[test]
+fn test_multiline_span() {
+    assert_output(
+        Err("Error at line 3; column 13
+match #1 {
+^^^^^^^^^^
+#0 => {} }
+^^^^^^^^^^   [E0009] This 'match' has no 'else' arm and does not cover #1"),
+        "
+        @transition {
+            match #1 {
+                #0 => {} }
+        }",
+    );
+
+    // Span ending at the beginning of a line
+    assert_eq!(
+        "Error at line 1; column 1
+@transition {
+^^^^^^^^^^^^^   [E9001] This feature is unimplemented",
+        LangErrorMsg::Unimplemented
+            .with_span(Span { start: 0, end: 14 })
+            .with_source("@transition {\n}")
+            .to_string(),
+    );
+}
+
+#[test]
+fn test_tab_alignment() {
+    assert_output(
+        Err("Error at line 3; column 10
+set x =\t#1
+       \t^^   [E0020] Type error: expected integer but got cell state"),
+        "@transition {\n\tset x = 1\n\tset x =\t#1\n}",
+    );
+}
+
+#[test]
+fn test_unicode_alignment() {
+    // Columns count characters, not bytes.
+    assert_output(
+        Err("Error at line 1; column 37
+@transition { /* café → */ become #(x) }
+                                    ^   [E0024] This variable must be initialized before it is used"),
+        "@transition { /* café → */ become #(x) }",
+    );
+    assert_output(
+        Err("Error at line 1; column 35
+@transition { /* naïve */ set x = ü }
+                                  ^   [E0001] Unknown symbol"),
+        "@transition { /* naïve */ set x = ü }",
+    );
+    assert_output(
+        Err("Error at line 3; column 18
+/* → */\tset x = #1
+       \t        ^^   [E0020] Type error: expected integer but got cell state"),
+        "@transition {\n\t/* → */\tset x = 1\n\t/* → */\tset x = #1\n}",
+    );
+}
+
+#[test]
+fn test_colored() {
+    let source_code = "@transition {\n    set x = 1\n    set x = #1\n}";
+    let err = ast::make_rule(Rc::new(source_code.to_owned()))
+        .unwrap_err()
+        .with_source(source_code);
+    let plain = err.to_string();
+    assert!(!plain.contains('\x1b'));
+    assert_eq!(
+        "Error at line 3; column 13
+set x = #1
+        \x1b[31m^^\x1b[0m   \x1b[1m[E0020] Type error: expected integer but got cell state\x1b[0m",
+        err.colored().to_string(),
+    );
+}
+
+#[test]
+fn test_json() {
+    let source_code = "@transition {\n    set x = 1\n    set x = #1\n}";
+    let err = ast::make_rule(Rc::new(source_code.to_owned()))
+        .unwrap_err()
+        .with_source(source_code);
+    assert_eq!(
+        r#"{"code": "E0020", "line": 3, "span": [13, 15], "message": "Type error: expected integer but got cell state"}"#,
+        err.to_json(),
+    );
+
+    let err = LangErrorMsg::Expected("\"quoted\"")
+        .without_span()
+        .with_source("");
+    assert_eq!(
+        r#"{"code": "E0005", "line": null, "span": null, "message": "Expected \"quoted\""}"#,
+        err.to_json(),
+    );
+}
+
+#[test]
+fn test_unreachable_code() {
+    let source_code = "
+        @transition {
+            if neighbor(0, 0) == #1 {
+                become #1
+            } else {
+                remain
+            }
+            become #2 // unreachable
+            become #3
+        }";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Rule should compile");
+    let warnings: Vec<String> = rule
+        .warnings()
+        .into_iter()
+        .map(|w| w.with_source(source_code).to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "Warning at line 8; column 13
+become #2 // unreachable
+^^^^^^^^^   [W0001] This code is unreachable"
+        ],
+        warnings,
+    );
+
+    // No warnings when every statement is reachable
+    let source_code = "
+        @transition {
+            if neighbor(0, 0) == #1 {
+                become #1
+            }
+            become #0
+        }";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Rule should compile");
+    assert!(rule.warnings().is_empty());
+}
+
+#[test]
+fn test_validate() {
+    // Clean rule
+    let source_code = "@transition {\n    become #1\n}";
+    let warnings = ast::Rule::validate(Rc::new(source_code.to_owned()));
+    assert!(warnings.expect("Rule should be valid").is_empty());
+
+    // Rule with an error
+    let source_code = "@transition {\n    set x = 1\n    set x = #1\n}";
+    let err = ast::Rule::validate(Rc::new(source_code.to_owned())).unwrap_err();
+    assert_eq!(
+        "Error at line 3; column 13
+set x = #1
+        ^^   [E0020] Type error: expected integer but got cell state",
+        err.to_string(),
+    );
+
+    // Rule with only warnings
+    let source_code = "@transition {\n    remain\n    become #1\n}";
+    let warnings: Vec<String> = ast::Rule::validate(Rc::new(source_code.to_owned()))
+        .expect("Rule should be valid")
+        .into_iter()
+        .map(|w| w.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "Warning at line 3; column 5
+become #1
+^^^^^^^^^   [W0001] This code is unreachable"
+        ],
+        warnings,
+    );
+}
+
+#[test]
+fn test_expression_too_deep() {
+    let too_deep = |expr: String| {
+        let source_code = format!("@transition {{\n    become #({})\n}}", expr);
+        match ast::make_rule(Rc::new(source_code)) {
+            Ok(_) => panic!("Expression should be too deep"),
+            Err(e) => assert_eq!(LangErrorMsg::ExpressionTooDeep, e.msg),
+        }
+    };
+    // Nested parentheses
+    too_deep(format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)));
+    // Nested function calls
+    too_deep(format!(
+        "{}1{}",
+        "max(0, ".repeat(10_000),
+        ")".repeat(10_000)
+    ));
+    // Right-associative exponentiation
+    too_deep(format!("1{}", " ** 1".repeat(10_000)));
+    // Long chains of operators
+    too_deep(format!("1{}", " + 1".repeat(1000)));
+    too_deep(format!("{}1", "- ".repeat(1000)));
+
+    // Nesting up to the limit is fine.
+    let source_code = format!(
+        "@transition {{\n    become #({}1{})\n}}",
+        "(".repeat(40),
+        ")".repeat(40),
+    );
+    assert!(ast::make_rule(Rc::new(source_code)).is_ok());
+    let source_code = format!(
+        "@transition {{\n    become #(1{} - 39)\n}}",
+        " + 1".repeat(39)
+    );
+    assert!(ast::make_rule(Rc::new(source_code)).is_ok());
+    // Long flat chains of operators are fine too, such as a weighted sum over
+    // a 4D neighborhood.
+    let source_code = format!(
+        "@ndim 4 @transition {{\n    become #(0{} - 81)\n}}",
+        " + 1".repeat(81)
+    );
+    assert!(ast::make_rule(Rc::new(source_code)).is_ok());
+    let source_code = format!(
+        "@transition {{\n    become #(1{} - 200)\n}}",
+        " + 1".repeat(200)
+    );
+    assert!(ast::make_rule(Rc::new(source_code)).is_ok());
+}
+
+#[test]
+fn test_error_codes() {
+    assert_eq!("E0001", LangErrorMsg::UnknownSymbol.code());
+    assert_eq!("E1002", LangErrorMsg::DivideByZero.code());
+
+    let all_msgs = vec![
+        LangErrorMsg::Unimplemented,
+        LangErrorMsg::UnknownError,
+        LangErrorMsg::InternalError("".into()),
+        LangErrorMsg::UnknownSymbol,
+        LangErrorMsg::Unterminated(""),
+        LangErrorMsg::InvalidDigitSeparator,
+        LangErrorMsg::Unmatched('{', '}'),
+        LangErrorMsg::Expected(""),
+        LangErrorMsg::ExpectedGot {
+            expected: "",
+            got: "",
+        },
+        LangErrorMsg::ReservedWord("".into()),
+        LangErrorMsg::ElseWithoutIf,
+        LangErrorMsg::MatchWithoutElse(vec![2]),
+        LangErrorMsg::DuplicateMatchArm,
+        LangErrorMsg::MissingSetKeyword,
+        LangErrorMsg::TopLevelNonDirective,
+        LangErrorMsg::InvalidDirectiveName,
+        LangErrorMsg::RepeatDirective(""),
+        LangErrorMsg::FunctionNameConflict,
+        LangErrorMsg::UnknownFunction,
+        LangErrorMsg::InvalidDimensionCount,
+        LangErrorMsg::InvalidStateCount,
+        LangErrorMsg::InvalidVectorLength,
+        LangErrorMsg::TypeError {
+            expected: Type::Int,
+            got: Type::Int,
+        },
+        LangErrorMsg::CmpError {
+            lhs: Type::Int,
+            cmp: ComparisonToken::Eql,
+            rhs: Type::Int,
+        },
+        LangErrorMsg::InvalidArguments {
+            name: "".to_owned(),
+            omit_first: false,
+            expected: vec![],
+            got: vec![].into(),
+        },
+        LangErrorMsg::CannotAssignTypeToVariable(Type::Int),
+        LangErrorMsg::UseOfUninitializedVariable,
+        LangErrorMsg::BecomeInHelperFunction,
+        LangErrorMsg::RemainInHelperFunction,
+        LangErrorMsg::BreakOutsideLoop,
+        LangErrorMsg::ReturnInTransitionFunction,
+        LangErrorMsg::CannotEvalAsConst,
+        LangErrorMsg::MissingReturn,
+        LangErrorMsg::UnorderedType {
+            ty: Type::CellState,
+            cmp: ComparisonToken::Lt,
+        },
+        LangErrorMsg::InvalidNeighborhoodOffset(2),
+        LangErrorMsg::UnsupportedSymmetry,
+        LangErrorMsg::AssignmentCountMismatch { vars: 2, values: 1 },
+        LangErrorMsg::InvalidEscapeSequence,
+        LangErrorMsg::DuplicateConstant,
+        LangErrorMsg::ConstantUsedBeforeDefinition,
+        LangErrorMsg::AssignToConstant,
+        LangErrorMsg::InvalidLayerCount,
+        LangErrorMsg::ExpressionTooDeep,
+        LangErrorMsg::InvalidStepLimit,
+        LangErrorMsg::TableTooLarge,
+        LangErrorMsg::UnsupportedTableExport("a rule with more than one layer"),
+        LangErrorMsg::UnsupportedVersion(2, 0),
+        LangErrorMsg::UnreachableCode,
+        LangErrorMsg::IntegerOverflow,
+        LangErrorMsg::DivideByZero,
+        LangErrorMsg::NegativeExponent,
+        LangErrorMsg::ShiftOutOfRange,
+        LangErrorMsg::NeighborOutOfRange,
+        LangErrorMsg::VectorIndexOutOfRange,
+        LangErrorMsg::CellStateOutOfRange,
+        LangErrorMsg::StepLimitExceeded,
+        LangErrorMsg::InvalidClampRange,
+        LangErrorMsg::InvalidModulus,
+        LangErrorMsg::InvalidRandomBound,
+        LangErrorMsg::AssertionFailed(None),
+        LangErrorMsg::LayerOutOfRange,
+    ];
+    let codes: HashSet<&str> = all_msgs.iter().map(LangErrorMsg::code).collect();
+    assert_eq!(all_msgs.len(), codes.len());
+}
+
+#[test]
+fn test_joined_spans() {
+    // Binary operators span from the left operand to the right operand.
+    assert_output(
+        Err("Error at line 5; column 21
+set c = a + b
+        ^^^^^   [E1001] Integer overflow"),
+        "
+        @transition {
+            set a = 9223372036854775807
+            set b = 1
+            set c = a + b
+            remain
+        }",
+    );
+    // Function calls span from the function name to the closing parenthesis.
+    assert_output(
+        Err("Error at line 4; column 21
+set c = f(1, true)
+        ^^^^^^^^^^   [E0022] Invalid arguments [Int, Bool] for f; expected [Int, Int]"),
+        "
+        @function int f(int x, int y) { return x }
+        @transition {
+            set c = f(1, true)
+            remain
+        }",
+    );
+}
+
+fn assert_errors(expected: &[&str], source_code: &str) {
+    let source_code = Rc::new(source_code.to_owned());
+    let errors = match ast::make_rule_collecting_errors(source_code.clone()) {
+        Ok(_) => panic!("Expected errors, but rule was built successfully"),
+        Err(errors) => errors,
+    };
+    let errors: Vec<String> = errors
+        .into_iter()
+        .map(|e| e.with_source(&source_code).to_string())
+        .collect();
+    assert_eq!(expected, &errors[..]);
+}
@@ -0,0 +1,87 @@
+use super::{assert_func_output, assert_neighborhood_output, assert_output, ConstValue};
+
+#[test]
+fn test_helper_function_calls() {
+    // Recursive helper function
+    let source_code = "
+        @function int factorial(int n) {
+            if n <= 1 {
+                return 1
+            }
+            return n * factorial(n - 1)
+        }
+        @function int test(int n) {
+            return factorial(n)
+        }";
+    for &(n, expected) in &[(0, 1), (1, 1), (5, 120), (20, 2432902008176640000)] {
+        assert_func_output(
+            &[ConstValue::Int(n)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Runtime error inside a helper function
+    assert_func_output(
+        &[ConstValue::Int(21)],
+        Err("Error at line 6; column 20
+return n * factorial(n - 1)
+       ^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
+        source_code,
+        Some("test"),
+    );
+
+    // Helper function returning a cell state
+    assert_output(
+        Ok(ConstValue::CellState(3)),
+        "
+        @function cellstate pick(int a, int b) {
+            if a > b {
+                return #(a - b)
+            }
+            return #0
+        }
+        @transition {
+            become pick(7, 4)
+        }
+        @states 4",
+    );
+
+    // Helper functions can call each other and access the neighborhood
+    assert_neighborhood_output(
+        &[0, 1, 0, 1, 1, 0, 0, 0, 1],
+        Ok(ConstValue::CellState(1)),
+        "
+        @function int is_live(int dx, int dy) {
+            if neighbor(dx, dy) == #1 {
+                return 1
+            }
+            return 0
+        }
+        @function int count_live_row(int dy) {
+            return is_live(-1, dy) + is_live(0, dy) + is_live(1, dy)
+        }
+        @transition {
+            set count = count_live_row(-1) + count_live_row(0) + count_live_row(1)
+            if count == 4 {
+                become #1
+            }
+            become #0
+        }",
+    );
+
+    // Wrong argument types
+    assert_output(
+        Err("Error at line 6; column 21
+become #double(#1)
+        ^^^^^^^^^^   [E0022] Invalid arguments [CellState] for double; expected [Int]"),
+        "
+        @function int double(int n) {
+            return n * 2
+        }
+        @transition {
+            become #double(#1)
+        }",
+    );
+}
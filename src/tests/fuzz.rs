@@ -0,0 +1,228 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use super::ast;
+
+/// Fragments of source code from which random inputs are assembled.
+const FRAGMENTS: &[&str] = &[
+    "@transition",
+    "@function",
+    "@states",
+    "@names",
+    "@ndim",
+    "@neighborhood",
+    "@symmetry",
+    "@layers",
+    "@max_steps",
+    "@default_state",
+    "@ndca_version",
+    "const",
+    "@",
+    "#",
+    "#1",
+    "#?",
+    "#foo",
+    "{",
+    "}",
+    "(",
+    ")",
+    "[",
+    "]",
+    ",",
+    ".",
+    "..",
+    "..=",
+    "=",
+    "==",
+    "!=",
+    "<",
+    "<=",
+    "=>",
+    "+",
+    "-",
+    "*",
+    "/",
+    "%",
+    "**",
+    "<<",
+    ">>>",
+    "+=",
+    "&",
+    "|",
+    "^",
+    "~",
+    "not",
+    "and",
+    "or",
+    "xor",
+    "in",
+    "if",
+    "else",
+    "then",
+    "for",
+    "while",
+    "break",
+    "continue",
+    "match",
+    "case",
+    "set",
+    "become",
+    "remain",
+    "return",
+    "assert",
+    "int",
+    "bool",
+    "cellstate",
+    "vec3",
+    "x",
+    "neighbor",
+    "count_neighbors_of",
+    "Moore",
+    "vonNeumann",
+    "0",
+    "1",
+    "-1",
+    "0x",
+    "0xFF",
+    "1_000",
+    "_",
+    "9223372036854775807",
+    "9223372036854775808",
+    "-9223372036854775808",
+    "0xFFFFFFFFFFFFFFFF",
+    "\"",
+    "\"abc\"",
+    "'\\q'",
+    "/*",
+    "*/",
+    "//",
+    " ",
+    "\n",
+    "\r",
+    "\t",
+    "é",
+    "→",
+    "🦀",
+    "\u{0}",
+];
+
+/// Simple deterministic pseudo-random number generator (a 64-bit LCG), so
+/// that failures are reproducible without any extra dependencies.
+struct Lcg(u64);
+impl Lcg {
+    fn next_int(&mut self) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize
+    }
+}
+
+/// Builds a rule from the given source code, and formats any errors along
+/// with the source code, panicking with the offending input if anything
+/// panics along the way.
+fn assert_no_panic(source_code: String) {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let source_code = Rc::new(source_code.clone());
+        if let Err(e) = ast::make_rule(source_code.clone()) {
+            assert!(!e.with_source(&source_code).to_string().is_empty());
+        }
+        if let Err(errors) = ast::make_rule_collecting_errors(source_code.clone()) {
+            for e in errors {
+                assert!(!e.with_source(&source_code).to_string().is_empty());
+            }
+        }
+    }));
+    if result.is_err() {
+        panic!(
+            "Panicked while building rule from source code {:?}",
+            source_code
+        );
+    }
+}
+
+#[test]
+fn test_fuzz_no_panic() {
+    let mut rng = Lcg(0x4e44_4341);
+
+    // Random sequences of tokens and other fragments
+    for _ in 0..2_000 {
+        let len = rng.next_int() % 40;
+        let source_code: String = (0..len)
+            .map(|_| FRAGMENTS[rng.next_int() % FRAGMENTS.len()])
+            .collect::<Vec<_>>()
+            .join(if rng.next_int() % 2 == 0 { " " } else { "" });
+        assert_no_panic(source_code);
+    }
+
+    // Random bytes
+    for _ in 0..1_000 {
+        let len = rng.next_int() % 64;
+        let bytes: Vec<u8> = (0..len).map(|_| rng.next_int() as u8).collect();
+        assert_no_panic(String::from_utf8_lossy(&bytes).into_owned());
+    }
+
+    // Extreme integers in directives that take integers
+    let extreme_values = [
+        "-9223372036854775808",
+        "9223372036854775807",
+        "-9223372036854775807 - 1",
+        "0x7FFFFFFFFFFFFFFF",
+        "-1",
+        "0",
+        "1 << 63",
+    ];
+    let directive_templates = [
+        "@neighborhood [[{}, 0]]",
+        "@neighborhood [[0, {}], [1, 1]]",
+        "@neighborhood [{}]",
+        "@ndim 1 @neighborhood [[{}]]",
+        "@states {}",
+        "@states [{}, 2]",
+        "@layers {}",
+        "@ndim {}",
+        "@max_steps {}",
+        "@default_state {}",
+        "@default_state #({})",
+    ];
+    for template in &directive_templates {
+        for value in &extreme_values {
+            let directive = template.replace("{}", value);
+            assert_no_panic(format!("@transition {{ remain }} {}", directive));
+        }
+    }
+
+    // Valid rules with random bytes deleted, duplicated, or replaced
+    let valid_rules = [
+        "@transition { if count_neighbors_of(#1) in 2..=3 { become #1 } remain } @states 2",
+        "@states 2 @names [dead, live] @transition { become live } @default_state #dead",
+        "const N = 3 @function int f(int x) { return x ** N } @transition { become #(f(1)) }",
+        "@ndim 1 @neighborhood vonNeumann /* nested /* comment */ */ @transition { remain }",
+    ];
+    // Make sure that the mutations start from rules that actually build.
+    for &source_code in &valid_rules {
+        if let Err(e) = ast::make_rule(Rc::new(source_code.to_owned())) {
+            panic!("{}", e.with_source(source_code));
+        }
+    }
+    for _ in 0..1_000 {
+        let mut bytes = valid_rules[rng.next_int() % valid_rules.len()]
+            .as_bytes()
+            .to_vec();
+        for _ in 0..1 + rng.next_int() % 3 {
+            let i = rng.next_int() % bytes.len();
+            match rng.next_int() % 3 {
+                0 => {
+                    bytes.remove(i);
+                }
+                1 => {
+                    let byte = bytes[i];
+                    bytes.insert(i, byte);
+                }
+                _ => bytes[i] = rng.next_int() as u8,
+            }
+        }
+        assert_no_panic(String::from_utf8_lossy(&bytes).into_owned());
+    }
+}
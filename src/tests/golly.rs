@@ -0,0 +1,66 @@
+use std::rc::Rc;
+
+use super::{ast, Compiler, LangErrorMsg};
+
+#[test]
+fn test_export_golly_table() {
+    let export = |source_code: &str| {
+        let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to build rule");
+        rule.export_golly_table(&mut Compiler::new().expect("Failed to create compiler"))
+    };
+
+    // Every cell copies its northern neighbor.
+    let table = export(
+        "
+        @neighborhood vonNeumann
+        @transition {
+            become neighbor(0, -1)
+        }",
+    )
+    .expect("Failed to export table");
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(
+        &[
+            "@TABLE",
+            "n_states:2",
+            "neighborhood:vonNeumann",
+            "symmetries:none"
+        ][..],
+        &lines[..4],
+    );
+    // Only configurations where the center cell changes are listed.
+    assert_eq!(16, lines.len() - 4);
+    assert!(lines.contains(&"0,1,0,0,0,1"));
+    assert!(lines.contains(&"0,1,1,0,1,1"));
+    assert!(lines.contains(&"1,0,1,1,1,0"));
+    assert!(!lines.contains(&"1,1,0,0,0,1"));
+    assert!(!lines.iter().any(|line| line.starts_with("0,0,")));
+
+    // The Moore neighborhood lists neighbors clockwise from north.
+    let table = export(
+        "
+        @transition {
+            become neighbor(1, -1)
+        }",
+    )
+    .expect("Failed to export table");
+    assert!(table.contains("neighborhood:Moore\n"));
+    assert!(table.contains("\n0,0,1,0,0,0,0,0,0,1\n"));
+    assert!(!table.contains("\n0,1,0,0,0,0,0,0,0,1\n"));
+
+    // Rules that Golly cannot represent
+    let error = export("@neighborhood [[1, 0]]").unwrap_err();
+    assert_eq!(
+        LangErrorMsg::UnsupportedTableExport("this neighborhood; use 'moore' or 'vonNeumann'"),
+        error.msg,
+    );
+    let error = export("@layers 2").unwrap_err();
+    assert_eq!(
+        LangErrorMsg::UnsupportedTableExport("a rule with more than one layer"),
+        error.msg,
+    );
+    let error = export("@states 256").unwrap_err();
+    assert_eq!(LangErrorMsg::TableTooLarge, error.msg);
+    let error = export("@states 5").unwrap_err();
+    assert_eq!(LangErrorMsg::TableTooLarge, error.msg);
+}
@@ -0,0 +1,438 @@
+use std::rc::Rc;
+
+use super::{assert_func_output, assert_output, lexer, parser, ConstValue};
+
+#[test]
+fn test_logic() {
+    let cases = [(false, false), (false, true), (true, false), (true, true)];
+    let source_code = "@function bool test(bool x, bool y) { return x and y }";
+    for &(x, y) in &cases {
+        assert_func_output(
+            &[ConstValue::Bool(x), ConstValue::Bool(y)],
+            Ok(ConstValue::Bool(x && y)),
+            source_code,
+            Some("test"),
+        );
+    }
+    let source_code = "@function bool test(bool x, bool y) { return x or y }";
+    for &(x, y) in &cases {
+        assert_func_output(
+            &[ConstValue::Bool(x), ConstValue::Bool(y)],
+            Ok(ConstValue::Bool(x || y)),
+            source_code,
+            Some("test"),
+        );
+    }
+    let source_code = "@function bool test(bool x, bool y) { return x xor y }";
+    for &(x, y) in &cases {
+        assert_func_output(
+            &[ConstValue::Bool(x), ConstValue::Bool(y)],
+            Ok(ConstValue::Bool(x != y)),
+            source_code,
+            Some("test"),
+        );
+    }
+    let source_code = "@function bool test(bool x) { return not x }";
+    for &x in &[false, true] {
+        assert_func_output(
+            &[ConstValue::Bool(x)],
+            Ok(ConstValue::Bool(!x)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Logical operators do not accept integers
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return x and y }
+                                          ^^^^^^^   [E0022] Invalid arguments [Int, Int] for binary \"and\" operator; expected [Bool, Bool]"),
+        "@function int test(int x, int y) { return x and y }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_bool_conditions() {
+    // Integers cannot be used directly as conditions
+    assert_output(
+        Err("Error at line 4; column 16
+if x {
+   ^   [E0020] Type error: expected boolean but got integer"),
+        "
+        @transition {
+            set x = 3
+            if x {
+                become #1
+            }
+        }",
+    );
+    // ... but comparisons can
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            set x = 3
+            if x > 0 {
+                become #1
+            }
+            remain
+        }",
+    );
+
+    // Conversions between booleans and integers
+    let source_code = "@function int test(int x) { return int(x > 2) + int(bool(x)) }";
+    for &(x, expected) in &[(0, 0), (1, 1), (-5, 1), (3, 2)] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+    // Round-tripping through both conversions
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            if int(bool(5)) == 1 and bool(int(true)) {
+                become #1
+            }
+            remain
+        }",
+    );
+    let source_code = "@function bool test(bool b) { return bool(int(b)) }";
+    for &b in &[false, true] {
+        assert_func_output(
+            &[ConstValue::Bool(b)],
+            Ok(ConstValue::Bool(b)),
+            source_code,
+            Some("test"),
+        );
+    }
+    // Conversions are type-checked and never accept their own output type
+    assert_func_output(
+        &[ConstValue::Int(1)],
+        Err("Error at line 1; column 36
+@function int test(int x) { return int(x) }
+                                   ^^^^^^   [E0022] Invalid arguments [Int] for int; expected [Bool]"),
+        "@function int test(int x) { return int(x) }",
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Bool(true)],
+        Err("Error at line 1; column 38
+@function bool test(bool b) { return bool(b) }
+                                     ^^^^^^^   [E0022] Invalid arguments [Bool] for bool; expected [Int]"),
+        "@function bool test(bool b) { return bool(b) }",
+        Some("test"),
+    );
+    // Booleans can be stored in variables and compared for equality
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            set a = 1 < 2
+            set b = true
+            if a == b and a != false {
+                become #1
+            }
+            remain
+        }",
+    );
+}
+
+#[test]
+fn test_logic_precedence() {
+    // `not` binds more loosely than comparison, `and` binds more tightly than
+    // `xor`, and `xor` binds more tightly than `or`.
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            set x = 3
+            set y = 4
+            if x > 0 and y < 3 {
+                become #0
+            }
+            if not x == 3 {
+                become #0
+            }
+            if false and false or true {
+                if true or true xor true {
+                    if not true xor true {
+                        become #1
+                    }
+                }
+            }
+            become #0
+        }",
+    );
+}
+
+#[test]
+fn test_logic_short_circuit() {
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            set zero = 0
+            if zero > 0 and 1 / zero > 0 {
+                become #0
+            }
+            if true or 1 / zero > 0 {
+                become #1
+            }
+            become #0
+        }",
+    );
+    assert_output(
+        Err("Error at line 4; column 25
+if true xor 1 / zero > 0 {
+            ^^^^^^^^   [E1002] Divide by zero"),
+        "
+        @transition {
+            set zero = 0
+            if true xor 1 / zero > 0 {
+                become #1
+            }
+            remain
+        }",
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            become #1
+        }
+        @states if true or 1 / 0 > 0 then 2 else 3",
+    );
+}
+
+#[test]
+fn test_conditional_expr() {
+    // Integer values
+    let source_code = "@function int test(int x, int y) { return (if x != 0 then y else 2) * 10 }";
+    for &(x, y, expected) in &[(1, 5, 50), (-3, 5, 50), (0, 5, 20)] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Cell state values
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            set x = false
+            set c = if x then #1 else #2
+            become if c == #2 then c else #0
+        }
+        @states 3",
+    );
+
+    // Only the selected value is evaluated
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            set zero = 0
+            become if zero != 0 then #(1 / zero) else #(if true then 1 else 1 / zero)
+        }",
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            become #1
+        }
+        @states if false then 1 / 0 else 2",
+    );
+
+    // Type mismatch
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 64
+@function int test(int x, int y) { return if x > y then y else #2 }
+                                                               ^^   [E0020] Type error: expected integer but got cell state"),
+        "@function int test(int x, int y) { return if x > y then y else #2 }",
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 46
+@function int test(int x, int y) { return if #1 then y else x }
+                                             ^^   [E0020] Type error: expected boolean but got cell state"),
+        "@function int test(int x, int y) { return if #1 then y else x }",
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 46
+@function int test(int x, int y) { return if x then y else x }
+                                             ^   [E0020] Type error: expected boolean but got integer"),
+        "@function int test(int x, int y) { return if x then y else x }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_if_else_chain() {
+    // Long chains of `else if`
+    let arms: Vec<String> = (0..50)
+        .map(|i| format!("if x == {} {{ return {} }}", i, i * 10))
+        .collect();
+    let source_code = format!(
+        "@function int test(int x) {{ {} else {{ return -1 }} }}",
+        arms.join(" else "),
+    );
+    for &(x, expected) in &[(0, 0), (1, 10), (25, 250), (49, 490), (50, -1), (-3, -1)] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            &source_code,
+            Some("test"),
+        );
+    }
+
+    // Only the first true arm is executed, and the chain may omit `else`
+    let source_code = "
+    @function int test(int x) {
+        set y = 0
+        if x > 10 { set y += 1 } else if x > 5 { set y += 2 } else if x > 0 { set y += 3 }
+        return y
+    }";
+    for &(x, expected) in &[(20, 1), (7, 2), (1, 3), (0, 0)] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Type error in a later arm
+    assert_func_output(
+        &[ConstValue::Int(1)],
+        Err("Error at line 1; column 60
+@function int test(int x) { if x == 1 { return 1 } else if x { return 2 } return 3 }
+                                                           ^   [E0020] Type error: expected boolean but got integer"),
+        "@function int test(int x) { if x == 1 { return 1 } else if x { return 2 } return 3 }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_elif() {
+    // `elif` is shorthand for `else if`. Pad it to the same length so that
+    // the spans in both parse trees match.
+    let else_if_source = "
+        @transition {
+            if x > 10 { become #1 }
+            else if x > 5 { become #2 }
+            else if x > 0 { remain }
+            else { become #0 }
+        }";
+    let elif_source = else_if_source.replace("else if", "elif   ");
+    let transition_statements = |source_code: &str| {
+        let source_code = Rc::new(source_code.to_owned());
+        let tokens = lexer::tokenize(&source_code).expect("Failed to tokenize");
+        let mut parse_tree = parser::parse(source_code.clone(), &tokens).expect("Failed to parse");
+        match parse_tree.take_single_directive(parser::Directive::Transition) {
+            Ok(Some((_, parser::DirectiveContents::Block(statements)))) => statements.inner,
+            _ => panic!("Missing transition function"),
+        }
+    };
+    let statements = transition_statements(&elif_source);
+    assert_eq!(transition_statements(else_if_source), statements);
+    // The whole chain is one `if` statement.
+    assert_eq!(1, statements.len());
+    match &statements[0].inner {
+        parser::Statement::If { arms, else_block } => {
+            assert_eq!(3, arms.len());
+            assert_eq!(1, else_block.len());
+        }
+        other => panic!("Expected if statement; got {:?}", other),
+    }
+
+    // `elif` and `else if` can be mixed, and the chain may omit `else`
+    let source_code = "
+    @function int test(int x) {
+        set y = 0
+        if x > 10 { set y += 1 } elif x > 5 { set y += 2 } else if x > 0 { set y += 3 }
+        elif x < -5 { set y += 4 }
+        return y
+    }";
+    for &(x, expected) in &[(20, 1), (7, 2), (1, 3), (0, 0), (-7, 4)] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // `elif` must follow an `if` block
+    assert_func_output(
+        &[ConstValue::Int(1)],
+        Err("Error at line 1; column 29
+@function int test(int x) { elif x > 0 { return 1 } return 0 }
+                            ^^^^   [E0008] This 'else' has no matching 'if'"),
+        "@function int test(int x) { elif x > 0 { return 1 } return 0 }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_assert() {
+    let source_code = "@function int test(int x) { assert x > 0 return x }";
+    assert_func_output(
+        &[ConstValue::Int(5)],
+        Ok(ConstValue::Int(5)),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(0)],
+        Err("Error at line 1; column 29
+@function int test(int x) { assert x > 0 return x }
+                            ^^^^^^^^^^^^   [E1012] Assertion failed"),
+        source_code,
+        Some("test"),
+    );
+
+    // Assertion with a message
+    let source_code = r#"@function int test(int x) { assert x != 3, "x must not be 3" return x }"#;
+    assert_func_output(
+        &[ConstValue::Int(2)],
+        Ok(ConstValue::Int(2)),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(3)],
+        Err(r#"Error at line 1; column 29
+@function int test(int x) { assert x != 3, "x must not be 3" return x }
+                            ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^   [E1012] Assertion failed: x must not be 3"#),
+        source_code,
+        Some("test"),
+    );
+
+    // Compile-time type checking
+    assert_func_output(
+        &[ConstValue::Int(3)],
+        Err("Error at line 1; column 36
+@function int test(int x) { assert x return x }
+                                   ^   [E0020] Type error: expected boolean but got integer"),
+        "@function int test(int x) { assert x return x }",
+        Some("test"),
+    );
+}
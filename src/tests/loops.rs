@@ -0,0 +1,384 @@
+use std::rc::Rc;
+
+use super::{assert_func_output, assert_output, ast, Compiler, ConstValue};
+
+#[test]
+fn test_while() {
+    // Sum 1..=5
+    assert_output(
+        Ok(ConstValue::CellState(15)),
+        "
+        @transition {
+            set i = 1
+            set sum = 0
+            while i <= 5 {
+                set sum += i
+                set i += 1
+            }
+            become #sum
+        }
+        @states 16",
+    );
+
+    // Condition is false from the start
+    assert_func_output(
+        &[ConstValue::Int(10)],
+        Ok(ConstValue::Int(10)),
+        "
+        @function int test(int x) {
+            while x < 5 {
+                set x += 1
+            }
+            return x
+        }",
+        Some("test"),
+    );
+
+    // Return from inside the loop
+    assert_func_output(
+        &[ConstValue::Int(100)],
+        Ok(ConstValue::Int(7)),
+        "
+        @function int test(int x) {
+            set n = 0
+            while true {
+                if n * n > 40 {
+                    return n
+                }
+                set n += 1
+            }
+        }",
+        Some("test"),
+    );
+
+    // Nested loops
+    assert_func_output(
+        &[ConstValue::Int(4)],
+        Ok(ConstValue::Int(10)),
+        "
+        @function int test(int x) {
+            set count = 0
+            set i = 0
+            while i < x {
+                set j = 0
+                while j <= i {
+                    set count += 1
+                    set j += 1
+                }
+                set i += 1
+            }
+            return count
+        }",
+        Some("test"),
+    );
+
+    // Wrong condition type
+    assert_output(
+        Err("Error at line 3; column 19
+while #1 {
+      ^^   [E0020] Type error: expected boolean but got cell state"),
+        "
+        @transition {
+            while #1 {
+                become #1
+            }
+        }",
+    );
+}
+
+#[test]
+fn test_for() {
+    // Computed range
+    let source_code = "
+        @function int test(int lo, int hi) {
+            set sum = 0
+            for i in lo * 2 .. hi + 1 {
+                set sum += i
+            }
+            return sum
+        }";
+    for &(lo, hi, expected) in &[(1, 5, 2 + 3 + 4 + 5), (-2, 2, -4 - 3 - 2 - 1 + 0 + 1 + 2)] {
+        assert_func_output(
+            &[ConstValue::Int(lo), ConstValue::Int(hi)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Empty ranges
+    for &(lo, hi) in &[(5, 9), (5, 4)] {
+        assert_func_output(
+            &[ConstValue::Int(lo), ConstValue::Int(hi)],
+            Ok(ConstValue::Int(0)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Assigning to the loop variable does not affect iteration
+    assert_output(
+        Ok(ConstValue::CellState(5)),
+        "
+        @transition {
+            set count = 0
+            for i in 0..5 {
+                set count += 1
+                set i = 100
+            }
+            become #count
+        }
+        @states 6",
+    );
+
+    // Loop variable is not visible after the loop
+    assert_output(
+        Err("Error at line 5; column 22
+become #(i)
+         ^   [E0024] This variable must be initialized before it is used"),
+        "
+        @transition {
+            for i in 0..3 {
+            }
+            become #(i)
+        }
+        @states 3",
+    );
+    assert_func_output(
+        &[],
+        Err("Error at line 7; column 30
+set total += j
+             ^   [E0024] This variable must be initialized before it is used"),
+        "
+        @function int test() {
+            set total = 0
+            for i in 0..3 {
+                for j in 0..2 {
+                }
+                set total += j
+            }
+            return total
+        }",
+        Some("test"),
+    );
+
+    // Loop variables shadow variables with the same name, even of a different
+    // type, without changing them.
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            set i = 10
+            set c = #1
+            set sum = 0
+            for i in 0..3 {
+                for c in 0..i {
+                    set sum += c
+                }
+            }
+            if i == 10 and sum == 1 {
+                become c
+            }
+            become #0
+        }",
+    );
+
+    // Wrong types
+    assert_output(
+        Err("Error at line 3; column 25
+for i in 0..#2 {
+            ^^   [E0020] Type error: expected integer but got cell state"),
+        "
+        @transition {
+            for i in 0..#2 {
+            }
+        }",
+    );
+}
+
+#[test]
+fn test_break_continue() {
+    // Break out of a nested loop
+    assert_func_output(
+        &[ConstValue::Int(3)],
+        Ok(ConstValue::Int(12)),
+        "
+        @function int test(int x) {
+            set count = 0
+            for i in 0..4 {
+                set j = 0
+                while true {
+                    if j == x {
+                        break
+                    }
+                    set count += 1
+                    set j += 1
+                }
+            }
+            return count
+        }",
+        Some("test"),
+    );
+
+    // Break out of the outer loop
+    assert_func_output(
+        &[ConstValue::Int(3)],
+        Ok(ConstValue::Int(3)),
+        "
+        @function int test(int x) {
+            set i = 0
+            while true {
+                if i == x {
+                    break
+                }
+                set i += 1
+            }
+            return i
+        }",
+        Some("test"),
+    );
+
+    // Continue past a condition
+    assert_func_output(
+        &[ConstValue::Int(10)],
+        Ok(ConstValue::Int(1 + 3 + 5 + 7 + 9)),
+        "
+        @function int test(int x) {
+            set sum = 0
+            for i in 0..x {
+                if i % 2 == 0 {
+                    continue
+                }
+                set sum += i
+            }
+            return sum
+        }",
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(10)],
+        Ok(ConstValue::Int(1 + 3 + 5 + 7 + 9)),
+        "
+        @function int test(int x) {
+            set sum = 0
+            set i = 0
+            while i < x {
+                set i += 1
+                if i % 2 == 0 {
+                    continue
+                }
+                set sum += i
+            }
+            return sum
+        }",
+        Some("test"),
+    );
+
+    // Outside of a loop
+    assert_output(
+        Err("Error at line 4; column 17
+break
+^^^^^   [E0027] Cannot use 'break' or 'continue' outside of a loop"),
+        "
+        @transition {
+            if true {
+                break
+            }
+        }",
+    );
+}
+
+#[test]
+fn test_step_limit() {
+    // Infinite while loop
+    assert_output(
+        Err("Error at line 3; column 19
+while true {
+      ^^^^   [E1008] Step limit exceeded; this loop may never end"),
+        "
+        @transition {
+            while true {
+            }
+            remain
+        }",
+    );
+
+    // Very long for loop
+    assert_func_output(
+        &[ConstValue::Int(1 << 40)],
+        Err("Error at line 3; column 22
+for i in 0..x {
+         ^^^^   [E1008] Step limit exceeded; this loop may never end"),
+        "
+        @function int test(int x) {
+            for i in 0..x {
+            }
+            return 0
+        }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_max_steps_directive() {
+    let source_code = "
+        @function int test(int n) {
+            set i = 0
+            while i < n {
+                set i += 1
+            }
+            return i
+        }
+        @max_steps 100";
+    assert_func_output(
+        &[ConstValue::Int(100)],
+        Ok(ConstValue::Int(100)),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(101)],
+        Err("Error at line 4; column 19
+while i < n {
+      ^^^^^   [E1008] Step limit exceeded; this loop may never end"),
+        source_code,
+        Some("test"),
+    );
+
+    // Infinite loop
+    assert_output(
+        Err("Error at line 3; column 19
+while true {
+      ^^^^   [E1008] Step limit exceeded; this loop may never end"),
+        "
+        @transition {
+            while true {
+            }
+            remain
+        }
+        @max_steps 100",
+    );
+
+    // A step limit set on the compiler takes precedence.
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    assert_eq!(Some(100), rule.max_steps());
+    let mut compiler = Compiler::new()
+        .expect("Failed to create compiler")
+        .with_step_limit(1000);
+    let mut compiled_function = rule
+        .compile_helper_function(&mut compiler, "test")
+        .expect("Failed to compile function");
+    compiled_function.set_args(&[ConstValue::Int(500)]);
+    assert_eq!(
+        ConstValue::Int(500),
+        compiled_function.call().expect("Function failed"),
+    );
+
+    // The step limit must be positive.
+    assert_output(
+        Err("Error at line 1; column 35
+@transition { remain } @max_steps 0
+                                  ^   [E0042] Step limit must be positive"),
+        "@transition { remain } @max_steps 0",
+    );
+}
@@ -0,0 +1,84 @@
+use super::{assert_neighborhood_output, assert_output, ConstValue};
+
+#[test]
+fn test_match() {
+    let source_code = "
+        @transition {
+            match neighbor(1, 0) {
+                #0 => { become #3 }
+                #1 => {
+                    set x = 4
+                    become #(x)
+                },
+                #(1 + 1) => {}
+                else => { remain }
+            }
+            become #5
+        }
+        @states 6";
+    for &(neighbor, expected) in &[(0, 3), (1, 4), (2, 5), (3, 2)] {
+        let mut neighborhood = [0; 9];
+        neighborhood[4] = 2;
+        neighborhood[5] = neighbor;
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            source_code,
+        );
+    }
+
+    // Duplicate arms
+    assert_output(
+        Err("Error at line 5; column 17
+#(2 - 1) => { become #2 }
+^^^^^^^^   [E0010] This value is already matched by an earlier arm"),
+        "
+        @transition {
+            match #1 {
+                #1 => { become #1 }
+                #(2 - 1) => { become #2 }
+                else => {}
+            }
+        }
+        @states 3",
+    );
+
+    // Without an `else` arm, every cell state must be matched.
+    let source_code = "
+        @transition {
+            match this {
+                #0 => { become #1 }
+                #2 => { become #0 }
+                #1 => { become #2 }
+            }
+        }
+        @states 3";
+    for &(center, expected) in &[(0, 1), (1, 2), (2, 0)] {
+        let mut neighborhood = [0; 9];
+        neighborhood[4] = center;
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            source_code,
+        );
+    }
+    assert_output(
+        Err("Error at line 1; column 15
+@transition { match #1 { #0 => {} #2 => {} } } @states 4
+              ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^   [E0009] This 'match' has no 'else' arm and does not cover #1, #3"),
+        "@transition { match #1 { #0 => {} #2 => {} } } @states 4",
+    );
+    // With an `else` arm, the other arms need not cover every cell state.
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "@transition { match #1 { #0 => {} else => { become #2 } } remain } @states 4",
+    );
+
+    // Arm values must be cell states
+    assert_output(
+        Err("Error at line 1; column 26
+@transition { match #1 { 1 => {} else => {} } }
+                         ^   [E0020] Type error: expected cell state but got integer"),
+        "@transition { match #1 { 1 => {} else => {} } }",
+    );
+}
@@ -10,7 +10,7 @@ proptest! {
         let expected = x.checked_add(y).map(ConstValue::Int).ok_or(
             "Error at line 1; column 43
 @function int test(int x, int y) { return x + y }
-                                          ^^^^^   Integer overflow",
+                                          ^^^^^   [E1001] Integer overflow",
         );
         assert_func_output(
             &[ConstValue::Int(x), ConstValue::Int(y)],
@@ -88,7 +88,7 @@ fn test_overflow() {
     assert_output(
         Err("Error at line 3; column 22
 become #(9223372036854775805 + 3)
-         ^^^^^^^^^^^^^^^^^^^^^^^   Integer overflow"),
+         ^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
         "
         @transition {
             become #(9223372036854775805 + 3)
@@ -99,7 +99,7 @@ become #(9223372036854775805 + 3)
     assert_output(
         Err("Error at line 3; column 22
 become #(-9223372036854775805 - 4)
-         ^^^^^^^^^^^^^^^^^^^^^^^^   Integer overflow"),
+         ^^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
         "
         @transition {
             become #(-9223372036854775805 - 4)
@@ -110,7 +110,7 @@ become #(-9223372036854775805 - 4)
     assert_output(
         Err("Error at line 3; column 22
 become #(9223372036854775805 - -3)
-         ^^^^^^^^^^^^^^^^^^^^^^^^   Integer overflow"),
+         ^^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
         "
         @transition {
             become #(9223372036854775805 - -3)
@@ -121,7 +121,7 @@ become #(9223372036854775805 - -3)
     assert_output(
         Err("Error at line 3; column 22
 become #(8589934592 * 8589934592)
-         ^^^^^^^^^^^^^^^^^^^^^^^   Integer overflow"),
+         ^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
         "
         @transition {
             become #(8589934592 * 8589934592)
@@ -132,7 +132,7 @@ become #(8589934592 * 8589934592)
     assert_output(
         Err("Error at line 3; column 22
 become #(-9223372036854775808 / -1)
-         ^^^^^^^^^^^^^^^^^^^^^^^^^   Integer overflow"),
+         ^^^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
         "
         @transition {
             become #(-9223372036854775808 / -1)
@@ -143,7 +143,7 @@ become #(-9223372036854775808 / -1)
     assert_output(
         Err("Error at line 3; column 22
 become #(--9223372036854775808)
-         ^^^^^^^^^^^^^^^^^^^^^   Integer overflow"),
+         ^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
         "
         @transition {
             become #(--9223372036854775808)
@@ -154,7 +154,7 @@ become #(--9223372036854775808)
     assert_output(
         Err("Error at line 3; column 22
 become #(12 / 0)
-         ^^^^^^   Divide by zero"),
+         ^^^^^^   [E1002] Divide by zero"),
         "
         @transition {
             become #(12 / 0)
@@ -165,10 +165,1006 @@ become #(12 / 0)
     assert_output(
         Err("Error at line 3; column 22
 become #(12 % 0)
-         ^^^^^^   Divide by zero"),
+         ^^^^^^   [E1002] Divide by zero"),
         "
         @transition {
             become #(12 % 0)
         }",
     );
 }
+
+#[test]
+fn test_negative_literals() {
+    // The most negative integer can be written as a literal
+    assert_func_output(
+        &[],
+        Ok(ConstValue::Int(LangInt::min_value())),
+        "@function int test() { return -9223372036854775808 }",
+        Some("test"),
+    );
+
+    // A minus sign after an operand is subtraction
+    for &(expr, expected) in &[
+        ("x-1", 4),
+        ("x -1", 4),
+        ("3-x", -2),
+        ("(x)-1", 4),
+        ("[x, 2][1]-1", 1),
+        ("x - -1", 6),
+        ("-x-1", -6),
+        ("x*-1", -5),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(5)],
+            Ok(ConstValue::Int(expected)),
+            &format!("@function int test(int x) {{ return {} }}", expr),
+            Some("test"),
+        );
+    }
+
+    // Negating a computed value can still overflow
+    assert_func_output(
+        &[],
+        Err("Error at line 1; column 33
+@function int test() { return -(9223372036854775807 + 1) }
+                                ^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
+        "@function int test() { return -(9223372036854775807 + 1) }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_unary_plus_and_parens() {
+    // Unary plus
+    assert_output(
+        Ok(ConstValue::CellState(5)),
+        "
+        @transition {
+            become #(+5)
+        }
+        @states 6",
+    );
+    assert_func_output(
+        &[ConstValue::Int(-3)],
+        Ok(ConstValue::Int(-3)),
+        "@function int test(int x) { return +x }",
+        Some("test"),
+    );
+    assert_output(
+        Err("Error at line 3; column 22
+become #(+#1)
+         ^^^   [E0022] Invalid arguments [CellState] for unary \"+\" operator; expected [Int]"),
+        "
+        @transition {
+            become #(+#1)
+        }",
+    );
+
+    // Double negation
+    assert_output(
+        Ok(ConstValue::CellState(5)),
+        "
+        @transition {
+            become #(-(-5))
+        }
+        @states 6",
+    );
+    let source_code = "@function int test(int x) { return --x }";
+    assert_func_output(
+        &[ConstValue::Int(7)],
+        Ok(ConstValue::Int(7)),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(LangInt::min_value())],
+        Err("Error at line 1; column 37
+@function int test(int x) { return --x }
+                                    ^^   [E1001] Integer overflow"),
+        source_code,
+        Some("test"),
+    );
+
+    // Redundant parentheses do not change the span of the inner expression.
+    assert_output(
+        Err("Error at line 3; column 24
+become #(((12 / 0)))
+           ^^^^^^   [E1002] Divide by zero"),
+        "
+        @transition {
+            become #(((12 / 0)))
+        }",
+    );
+}
+
+#[test]
+fn test_wrapping_arithmetic() {
+    let max = LangInt::max_value();
+    let min = LangInt::min_value();
+
+    // Checked mode (default)
+    for &source_code in &[
+        "@function int test(int x, int y) { return x + y }",
+        "@function int test(int x, int y) { return x + y } @arithmetic checked",
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(max), ConstValue::Int(1)],
+            Err(&format!(
+                "Error at line 1; column 43
+{}
+                                          ^^^^^   [E1001] Integer overflow",
+                source_code,
+            )),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Wrapping mode
+    for &(op, x, y, expected) in &[
+        ("+", max, 1, min),
+        ("-", min, 1, max),
+        ("*", max, 2, -2),
+        ("/", min, -1, min),
+        ("%", min, -1, 0),
+        ("**", 3, 41, 3_i64.wrapping_pow(41)),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            &format!(
+                "@function int test(int x, int y) {{ return x {} y }} @arithmetic wrapping",
+                op,
+            ),
+            Some("test"),
+        );
+    }
+    assert_func_output(
+        &[ConstValue::Int(min)],
+        Ok(ConstValue::Int(min)),
+        "@function int test(int x) { return -x } @arithmetic wrapping",
+        Some("test"),
+    );
+
+    // Division by zero is still an error.
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(0)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return x / y } @arithmetic wrapping
+                                          ^^^^^   [E1002] Divide by zero"),
+        "@function int test(int x, int y) { return x / y } @arithmetic wrapping",
+        Some("test"),
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #2
+        }
+        @arithmetic wrapping
+        @states 9223372036854775807 * 2 + 5",
+    );
+
+    // Invalid mode
+    assert_output(
+        Err("Error at line 1; column 36
+@transition { remain } @arithmetic saturating
+                                   ^^^^^^^^^^   [E0005] Expected 'checked' or 'wrapping'"),
+        "@transition { remain } @arithmetic saturating",
+    );
+}
+
+#[test]
+fn test_constant_folding() {
+    // Nested arithmetic
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #(3 * 99 % 2 + (2 ** 3 - -(7 - 14)))
+        }
+        @states 3",
+    );
+
+    // Errors in constant expressions are reported at compile time, even if
+    // the expression would never be evaluated.
+    assert_output(
+        Err("Error at line 4; column 26
+become #(10 % 0)
+         ^^^^^^   [E1002] Divide by zero"),
+        "
+        @transition {
+            if false {
+                become #(10 % 0)
+            }
+        }",
+    );
+
+    // Short-circuiting is respected.
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            become #(if true or 1 / 0 > 0 then 1 else 0)
+        }",
+    );
+}
+
+#[test]
+fn test_exponentiation() {
+    let source_code = "@function int test(int x, int y) { return x ** y }";
+    for &(x, y, expected) in &[(2, 10, 1024), (0, 0, 1), (-3, 3, -27), (1, 1 << 40, 1)] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Overflow
+    assert_func_output(
+        &[],
+        Err("Error at line 1; column 31
+@function int test() { return 2 ** 63 }
+                              ^^^^^^^   [E1001] Integer overflow"),
+        "@function int test() { return 2 ** 63 }",
+        Some("test"),
+    );
+
+    // Negative exponent
+    assert_func_output(
+        &[],
+        Err("Error at line 1; column 31
+@function int test() { return 2 ** -1 }
+                              ^^^^^^^   [E1003] Negative exponent"),
+        "@function int test() { return 2 ** -1 }",
+        Some("test"),
+    );
+
+    // Associativity and precedence
+    for &(expr, expected) in &[
+        ("2 ** 3 ** 2", 512),
+        ("(2 ** 3) ** 2", 64),
+        ("-2 ** 2", -4),
+        ("(-2) ** 2", 4),
+        ("-x ** 2", -4),
+        ("2 ** x ** 2 * 3", 48),
+        ("2 ** -(-x)", 4),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(2)],
+            Ok(ConstValue::Int(expected)),
+            &format!("@function int test(int x) {{ return {} }}", expr),
+            Some("test"),
+        );
+    }
+    assert_func_output(
+        &[ConstValue::Int(1)],
+        Err("Error at line 1; column 36
+@function int test(int x) { return 2 ** -x }
+                                   ^^^^^^^   [E1003] Negative exponent"),
+        "@function int test(int x) { return 2 ** -x }",
+        Some("test"),
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(8)),
+        "
+        @transition {
+            become #8
+        }
+        @states 3 ** 2",
+    );
+    assert_output(
+        Err("Error at line 5; column 17
+@states 3 ** 41
+        ^^^^^^^   [E1001] Integer overflow"),
+        "
+        @transition {
+            become #0
+        }
+        @states 3 ** 41",
+    );
+    assert_output(
+        Ok(ConstValue::CellState(5)),
+        "
+        @transition {
+            become #5
+        }
+        @states 6 + 7 ** 0",
+    );
+    assert_output(
+        Ok(ConstValue::CellState(3)),
+        "
+        @transition {
+            become #3
+        }
+        @states 2 ** 62 / 2 ** 60",
+    );
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #2
+        }
+        @states 4 + (-1) ** 1000000000001",
+    );
+    assert_output(
+        Err("Error at line 5; column 17
+@states 2 ** -1
+        ^^^^^^^   [E1003] Negative exponent"),
+        "
+        @transition {
+            become #0
+        }
+        @states 2 ** -1",
+    );
+}
+
+#[test]
+fn test_bitwise() {
+    let source_code = "@function int test(int x, int y) { return x & y }";
+    assert_func_output(
+        &[ConstValue::Int(6), ConstValue::Int(3)],
+        Ok(ConstValue::Int(2)),
+        source_code,
+        Some("test"),
+    );
+    let source_code = "@function int test(int x, int y) { return x | y }";
+    assert_func_output(
+        &[ConstValue::Int(6), ConstValue::Int(1)],
+        Ok(ConstValue::Int(7)),
+        source_code,
+        Some("test"),
+    );
+    let source_code = "@function int test(int x, int y) { return x ^ y }";
+    assert_func_output(
+        &[ConstValue::Int(6), ConstValue::Int(3)],
+        Ok(ConstValue::Int(5)),
+        source_code,
+        Some("test"),
+    );
+
+    // Precedence: AND binds tighter than XOR, which binds tighter than OR,
+    // which binds tighter than comparison.
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            become #(1 | 6 ^ 3 & 5 == 7)
+        }",
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(10)),
+        "
+        @transition {
+            become #10
+        }
+        @states (6 & 3) | (12 ^ 7)",
+    );
+}
+
+#[test]
+fn test_bitshift() {
+    for &(op, x, y, expected) in &[
+        ("<<", 1, 4, 16),
+        ("<<", -3, 2, -12),
+        (">>", -16, 2, -4),
+        (">>", 16, 63, 0),
+        (">>>", -16, 60, 15),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            &format!("@function int test(int x, int y) {{ return x {} y }}", op),
+            Some("test"),
+        );
+    }
+
+    // Shift amount out of range
+    for &y in &[64, -1] {
+        assert_func_output(
+            &[ConstValue::Int(1), ConstValue::Int(y)],
+            Err(
+                "Error at line 1; column 43
+@function int test(int x, int y) { return x << y }
+                                          ^^^^^^   [E1004] Bitshift amount must range from 0 to 63",
+            ),
+            "@function int test(int x, int y) { return x << y }",
+            Some("test"),
+        );
+    }
+
+    // In-place assignment
+    for &(op, x, y, expected) in &[
+        ("<<", 1, 4, 16),
+        ("<<", -3, 2, -12),
+        (">>", -16, 2, -4),
+        (">>", 16, 63, 0),
+        (">>>", -16, 60, 15),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            &format!(
+                "@function int test(int x, int y) {{ set x {}= y return x }}",
+                op,
+            ),
+            Some("test"),
+        );
+    }
+    for &y in &[64, -1] {
+        assert_func_output(
+            &[ConstValue::Int(1), ConstValue::Int(y)],
+            Err("Error at line 1; column 36
+@function int test(int x, int y) { set x >>= y return x }
+                                   ^^^^^^^^^^^   [E1004] Bitshift amount must range from 0 to 63"),
+            "@function int test(int x, int y) { set x >>= y return x }",
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Err("Error at line 5; column 17
+@states 1 >> 64
+        ^^^^^^^   [E1004] Bitshift amount must range from 0 to 63"),
+        "
+        @transition {
+            become #0
+        }
+        @states 1 >> 64",
+    );
+}
+
+#[test]
+fn test_bitwise_not() {
+    let source_code = "@function int test(int x) { return ~x }";
+    for &(x, expected) in &[(0, -1), (5, -6), (-6, 5)] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(5)),
+        "
+        @transition {
+            become #5
+        }
+        @states ~~6",
+    );
+}
+
+#[test]
+fn test_modulo() {
+    let source_code = "@function int test(int x, int y) { return x %% y }";
+    for &(x, y, expected) in &[(7, 3, 1), (-7, 3, 2), (7, -3, 1), (-7, -3, 2), (-6, 3, 0)] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Divide by zero
+    assert_func_output(
+        &[ConstValue::Int(-7), ConstValue::Int(0)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return x %% y }
+                                          ^^^^^^   [E1002] Divide by zero"),
+        source_code,
+        Some("test"),
+    );
+
+    // Overflow
+    assert_func_output(
+        &[ConstValue::Int(LangInt::MIN), ConstValue::Int(-1)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return x %% y }
+                                          ^^^^^^   [E1001] Integer overflow"),
+        source_code,
+        Some("test"),
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            become #1
+        }
+        @states -7 %% 3",
+    );
+
+    // In-place modulo keeps a negative accumulator in range
+    assert_func_output(
+        &[ConstValue::Int(-4)],
+        Ok(ConstValue::Int(1)),
+        "
+        @function int test(int step) {
+            set acc = 0
+            set i = 0
+            while i < 5 {
+                set acc += step
+                set acc %%= 7
+                set i += 1
+            }
+            return acc
+        }",
+        Some("test"),
+    );
+    let source_code = "
+        @function int test(int x, int y) {
+            set x %%= y
+            return x
+        }";
+    for &(x, y, expected) in &[(-7, 3, 2), (-1, 5, 4), (-6, -3, 0), (7, 3, 1)] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+    assert_func_output(
+        &[ConstValue::Int(-7), ConstValue::Int(0)],
+        Err("Error at line 3; column 13
+set x %%= y
+^^^^^^^^^^^   [E1002] Divide by zero"),
+        source_code,
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_floor_div() {
+    let source_code = "@function int test(int x, int y) { return x /% y }";
+    for &(x, y, expected) in &[
+        (7, 3, 2),
+        (-7, 3, -3),
+        (7, -3, -3),
+        (-7, -3, 2),
+        (-6, 3, -2),
+        (-1, 5, -1),
+        (LangInt::MIN, 1, LangInt::MIN),
+        (LangInt::MIN, 2, LangInt::MIN / 2),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Divide by zero
+    assert_func_output(
+        &[ConstValue::Int(-7), ConstValue::Int(0)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return x /% y }
+                                          ^^^^^^   [E1002] Divide by zero"),
+        source_code,
+        Some("test"),
+    );
+
+    // Overflow
+    assert_func_output(
+        &[ConstValue::Int(LangInt::MIN), ConstValue::Int(-1)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return x /% y }
+                                          ^^^^^^   [E1001] Integer overflow"),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(LangInt::MIN), ConstValue::Int(-1)],
+        Ok(ConstValue::Int(LangInt::MIN)),
+        "@function int test(int x, int y) { return x /% y } @arithmetic wrapping",
+        Some("test"),
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            become #1
+        }
+        @states (-7 /% 3) + 5",
+    );
+    assert_output(
+        Err("Error at line 6; column 17
+@states MIN /% -1
+        ^^^^^^^^^   [E1001] Integer overflow"),
+        "
+        const MIN = -9223372036854775807 - 1
+        @transition {
+            become #1
+        }
+        @states MIN /% -1",
+    );
+
+    // In-place floored division
+    let source_code = "
+        @function int test(int x, int y) {
+            set x /%= y
+            return x
+        }";
+    for &(x, y, expected) in &[(-7, 2, -4), (7, 2, 3), (0, -3, 0)] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+}
+
+#[test]
+fn test_clamp() {
+    let source_code = "@function int test(int x) { return clamp(x, -2, 5) }";
+    for &(x, expected) in &[(-10, -2), (-2, -2), (0, 0), (5, 5), (6, 5)] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Invalid range
+    assert_func_output(
+        &[ConstValue::Int(3)],
+        Err("Error at line 1; column 36
+@function int test(int x) { return clamp(x, x + 1, x) }
+                                   ^^^^^^^^^^^^^^^^^^   [E1009] Lower bound of clamp is greater than upper bound"),
+        "@function int test(int x) { return clamp(x, x + 1, x) }",
+        Some("test"),
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #2
+        }
+        @states clamp(100, 1, 3)",
+    );
+    assert_output(
+        Err("Error at line 5; column 17
+@states clamp(2, 3, 1)
+        ^^^^^^^^^^^^^^   [E1009] Lower bound of clamp is greater than upper bound"),
+        "
+        @transition {
+            become #0
+        }
+        @states clamp(2, 3, 1)",
+    );
+}
+
+#[test]
+fn test_saturating() {
+    let max = LangInt::max_value();
+    let min = LangInt::min_value();
+    for &(func, x, y, expected) in &[
+        ("saturating_add", max, 1, max),
+        ("saturating_add", min, -1, min),
+        ("saturating_add", max - 1, 1, max),
+        ("saturating_add", 3, -5, -2),
+        ("saturating_sub", min, 1, min),
+        ("saturating_sub", max, -1, max),
+        ("saturating_sub", 0, min, max),
+        ("saturating_sub", 3, 5, -2),
+        ("saturating_mul", max, 2, max),
+        ("saturating_mul", min, 2, min),
+        ("saturating_mul", max, -2, min),
+        ("saturating_mul", min, -1, max),
+        ("saturating_mul", -4, 5, -20),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            &format!(
+                "@function int test(int x, int y) {{ return {}(x, y) }}",
+                func,
+            ),
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #2
+        }
+        @states saturating_add(9223372036854775807, 1) - 9223372036854775804",
+    );
+}
+
+#[test]
+fn test_gcd_lcm() {
+    let max = LangInt::max_value();
+    let min = LangInt::min_value();
+    for &(func, x, y, expected) in &[
+        ("gcd", 0, 5, 5),
+        ("gcd", 5, 0, 5),
+        ("gcd", 0, 0, 0),
+        ("gcd", 12, 18, 6),
+        ("gcd", -12, 18, 6),
+        ("gcd", 17, 5, 1),
+        ("gcd", min, 6, 2),
+        ("lcm", 4, 6, 12),
+        ("lcm", -4, 6, 12),
+        ("lcm", 0, 5, 0),
+        ("lcm", 0, 0, 0),
+        ("lcm", max, 1, max),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(expected)),
+            &format!(
+                "@function int test(int x, int y) {{ return {}(x, y) }}",
+                func,
+            ),
+            Some("test"),
+        );
+    }
+
+    // Overflow
+    for &(func, x, y) in &[
+        ("lcm", max, 2),
+        ("lcm", min, 1),
+        ("gcd", min, 0),
+        ("gcd", min, min),
+    ] {
+        let source_code = format!(
+            "@function int test(int x, int y) {{ return {}(x, y) }}",
+            func
+        );
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Err(&format!(
+                "Error at line 1; column 43
+{}
+                                          ^^^^^^^^^   [E1001] Integer overflow",
+                source_code,
+            )),
+            &source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(5)),
+        "
+        @transition {
+            become #5
+        }
+        @states lcm(4, 6) - gcd(12, 18) - 1",
+    );
+    assert_output(
+        Err("Error at line 5; column 17
+@states gcd(-9223372036854775807 - 1, 0)
+        ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^   [E1001] Integer overflow"),
+        "
+        @transition {
+            become #0
+        }
+        @states gcd(-9223372036854775807 - 1, 0)",
+    );
+}
+
+#[test]
+fn test_powmod() {
+    let max = LangInt::max_value();
+    let source_code = "@function int test(int b, int e, int m) { return powmod(b, e, m) }";
+    for &(base, exponent, modulus, expected) in &[
+        (2, 10, 1000, 24),
+        (-2, 3, 5, 2),
+        (7, 0, 13, 1),
+        (7, 0, 1, 0),
+        (0, 0, 5, 1),
+        (3, 200, max, 7480851290986031919),
+        (max, max, 1_000_000_007, 856225998),
+        (max - 1, 12345, max, max - 1),
+    ] {
+        assert_func_output(
+            &[
+                ConstValue::Int(base),
+                ConstValue::Int(exponent),
+                ConstValue::Int(modulus),
+            ],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Negative exponent
+    assert_func_output(
+        &[ConstValue::Int(2), ConstValue::Int(-1), ConstValue::Int(7)],
+        Err("Error at line 1; column 50
+@function int test(int b, int e, int m) { return powmod(b, e, m) }
+                                                 ^^^^^^^^^^^^^^^   [E1003] Negative exponent"),
+        source_code,
+        Some("test"),
+    );
+    // Modulus is not positive
+    for &modulus in &[0, -7] {
+        assert_func_output(
+            &[ConstValue::Int(2), ConstValue::Int(3), ConstValue::Int(modulus)],
+            Err("Error at line 1; column 50
+@function int test(int b, int e, int m) { return powmod(b, e, m) }
+                                                 ^^^^^^^^^^^^^^^   [E1010] Modulus must be positive"),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(24)),
+        "
+        @transition {
+            become #24
+        }
+        @states powmod(2, 10, 1000) + 1",
+    );
+}
+
+#[test]
+fn test_sign() {
+    let source_code = "@function int test(int x) { return sign(x) }";
+    for &(x, expected) in &[
+        (LangInt::min_value(), -1),
+        (-5, -1),
+        (0, 0),
+        (7, 1),
+        (LangInt::max_value(), 1),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(x)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #(sign(-12) + sign(0) + 3 * sign(99))
+        }
+        @states 3",
+    );
+}
+
+#[test]
+fn test_min_max() {
+    let source_code = "@function int test(int x, int y) { return min(x, y) * 100 + max(x, y) }";
+    for &(x, y) in &[(3, 8), (8, 3), (-5, 2), (4, 4)] {
+        assert_func_output(
+            &[ConstValue::Int(x), ConstValue::Int(y)],
+            Ok(ConstValue::Int(x.min(y) * 100 + x.max(y))),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // More than two arguments
+    let source_code =
+        "@function int test(int x, int y) { return max(x, 10, y, -3) - min(y, x, 0) }";
+    assert_func_output(
+        &[ConstValue::Int(-20), ConstValue::Int(15)],
+        Ok(ConstValue::Int(15 - -20)),
+        source_code,
+        Some("test"),
+    );
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #2
+        }
+        @states max(1, 5, 3)",
+    );
+
+    // Wrong argument types
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return min(x, #2) }
+                                          ^^^^^^^^^^   [E0022] Invalid arguments [Int, CellState] for min; expected [Int, Int]"),
+        "@function int test(int x, int y) { return min(x, #2) }",
+        Some("test"),
+    );
+
+    // Too few arguments
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return max(x) }
+                                          ^^^^^^   [E0022] Invalid arguments [Int] for max; expected [Int, Int]"),
+        "@function int test(int x, int y) { return max(x) }",
+        Some("test"),
+    );
+
+    // Unknown function
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(2)],
+        Err("Error at line 1; column 43
+@function int test(int x, int y) { return foo(x, y) }
+                                          ^^^   [E0016] There is no function with this name"),
+        "@function int test(int x, int y) { return foo(x, y) }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_digit_separators() {
+    // Valid separated literals
+    for &(literal, value) in &[
+        ("1_000_000", 1_000_000),
+        ("-1_0", -10),
+        ("0xDEAD_BEEF", 0xDEAD_BEEF),
+        ("0xff", 0xff),
+        ("-0x1_0", -0x10),
+    ] {
+        assert_func_output(
+            &[],
+            Ok(ConstValue::Int(value)),
+            &format!("@function int test() {{ return {} }}", literal),
+            Some("test"),
+        );
+    }
+
+    // Doubled underscore
+    assert_func_output(
+        &[],
+        Err("Error at line 1; column 32
+@function int test() { return 1__000 }
+                               ^^   [E0003] Digit separators must be single underscores between digits"),
+        "@function int test() { return 1__000 }",
+        Some("test"),
+    );
+
+    // Trailing underscore
+    assert_func_output(
+        &[],
+        Err("Error at line 1; column 35
+@function int test() { return 1000_ }
+                                  ^   [E0003] Digit separators must be single underscores between digits"),
+        "@function int test() { return 1000_ }",
+        Some("test"),
+    );
+
+    // Leading underscore after hexadecimal prefix
+    assert_func_output(
+        &[],
+        Err("Error at line 1; column 33
+@function int test() { return 0x_FF }
+                                ^   [E0003] Digit separators must be single underscores between digits"),
+        "@function int test() { return 0x_FF }",
+        Some("test"),
+    );
+}
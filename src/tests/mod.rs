@@ -3,15 +3,27 @@
 use std::borrow::Cow;
 use std::rc::Rc;
 
+mod aot;
 mod cmp;
+mod errors;
+mod functions;
+mod fuzz;
+mod golly;
+mod logic;
+mod loops;
+mod matches;
 mod math;
+mod neighborhood;
+mod random;
+mod run;
 mod vars;
 mod vecs;
 
-use super::ast;
 use super::compiler::Compiler;
-use super::types::LangInt;
+use super::errors::LangErrorMsg;
+use super::types::{LangCellState, LangInt};
 use super::ConstValue;
+use super::{ast, lexer, parser};
 
 #[test]
 fn test_become() {
@@ -26,7 +38,7 @@ fn test_become() {
     assert_output(
         Err("Error at line 3; column 20
 become #10
-       ^^^   Cell state out of range"),
+       ^^^   [E1007] Cell state out of range"),
         "
         @transition {
             become #10
@@ -36,12 +48,728 @@ become #10
     assert_output(
         Err("Error at line 3; column 20
 become #10
-       ^^^   Cell state out of range"),
+       ^^^   [E1007] Cell state out of range"),
         "
         @transition {
             become #10
         }",
     );
+
+    // Integers are implicitly converted to cell states.
+    assert_output(
+        Ok(ConstValue::CellState(7)),
+        "
+        @transition {
+            become 7
+        }
+        @states 8",
+    );
+    assert_output(
+        Err("Error at line 3; column 20
+become 8
+       ^   [E1007] Cell state out of range"),
+        "
+        @transition {
+            become 8
+        }
+        @states 8",
+    );
+    let source_code = "
+        @states 4
+        @function cellstate test(int i) {
+            return i - 1
+        }";
+    for &(i, expected) in &[(1, 0), (4, 3)] {
+        assert_func_output(
+            &[ConstValue::Int(i)],
+            Ok(ConstValue::CellState(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+    for &i in &[0, 5] {
+        assert_func_output(
+            &[ConstValue::Int(i)],
+            Err("Error at line 4; column 20
+return i - 1
+       ^^^^^   [E1007] Cell state out of range"),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Cell states that are known at compile time are range-checked while
+    // building the rule, and only dynamic ones are checked at runtime.
+    for &source_code in &[
+        "@transition { become #150 } @states 100",
+        "@transition { become 150 } @states 100",
+        "@transition { set s = #(50 * 3) remain } @states 100",
+        "@function cellstate f() { return 100 } @transition { remain } @states 100",
+    ] {
+        let err = ast::make_rule(Rc::new(source_code.to_owned())).unwrap_err();
+        assert_eq!(LangErrorMsg::CellStateOutOfRange, err.msg);
+    }
+    for &source_code in &[
+        "@transition { become #99 } @states 100",
+        "@function cellstate f(int i) { return i } @transition { remain } @states 100",
+    ] {
+        ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to build rule");
+    }
+}
+
+#[test]
+fn test_state_wrap() {
+    let source_code = "
+        @states 5
+        @function cellstate test(int i) {
+            return state_wrap(i)
+        }";
+    for &(i, expected) in &[
+        (0, 0),
+        (4, 4),
+        (7, 2),
+        (-1, 4),
+        (-5, 0),
+        (-6, 4),
+        (LangInt::max_value(), 2),
+        (LangInt::min_value(), 2),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(i)],
+            Ok(ConstValue::CellState(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(9)),
+        "
+        @transition {
+            become state_wrap(-1)
+        }
+        @states 10",
+    );
+}
+
+#[test]
+fn test_state_clamp() {
+    let source_code = "
+        @states 5
+        @function cellstate test(int i) {
+            return #?i
+        }";
+    for &(i, expected) in &[
+        (0, 0),
+        (3, 3),
+        (4, 4),
+        (5, 4),
+        (150, 4),
+        (-1, 0),
+        (LangInt::max_value(), 4),
+        (LangInt::min_value(), 0),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(i)],
+            Ok(ConstValue::CellState(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(9)),
+        "
+        @transition {
+            become #?150
+        }
+        @states 10",
+    );
+    // The strict operator still reports an error
+    assert_output(
+        Err("Error at line 3; column 20
+become #150
+       ^^^^   [E1007] Cell state out of range"),
+        "
+        @transition {
+            become #150
+        }
+        @states 10",
+    );
+}
+
+#[test]
+fn test_state_id() {
+    // Round trip
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            if state_id(#7) == 7 {
+                become #1
+            }
+            become #0
+        }
+        @states 8",
+    );
+    assert_func_output(
+        &[ConstValue::Int(6)],
+        Ok(ConstValue::Int(6 * 6 + 1)),
+        "
+        @states 8
+        @function int test(int i) {
+            set s = #(i)
+            return state_id(s) * i + state_id(#1)
+        }",
+        Some("test"),
+    );
+    assert_output(
+        Err("Error at line 3; column 22
+become #(state_id(3))
+         ^^^^^^^^^^^   [E0022] Invalid arguments [Int] for state_id; expected [CellState]"),
+        "
+        @transition {
+            become #(state_id(3))
+        }",
+    );
+}
+
+#[test]
+fn test_states_directive() {
+    assert_output(
+        Ok(ConstValue::CellState(4)),
+        "
+        @transition {
+            become #4
+        }
+        @states 5",
+    );
+    assert_output(
+        Err("Error at line 3; column 20
+become #5
+       ^^   [E1007] Cell state out of range"),
+        "
+        @transition {
+            become #5
+        }
+        @states 5",
+    );
+
+    // Runtime range check
+    assert_output(
+        Ok(ConstValue::CellState(4)),
+        "
+        @transition {
+            set x = 4
+            become #x
+        }
+        @states 5",
+    );
+    assert_output(
+        Err("Error at line 4; column 20
+become #x
+       ^^   [E1007] Cell state out of range"),
+        "
+        @transition {
+            set x = 5
+            become #x
+        }
+        @states 5",
+    );
+
+    // Invalid state counts
+    for &count in &["0", "257"] {
+        assert_output(
+            Err(&format!(
+                "Error at line 5; column 17
+@states {}
+        {}   [E0018] Number of states must range from 1 to 256",
+                count,
+                "^".repeat(count.len()),
+            )),
+            &format!(
+                "
+        @transition {{
+            become #0
+        }}
+        @states {}",
+                count,
+            ),
+        );
+    }
+    assert_output(
+        Err("Error at line 5; column 17
+@states #0
+        ^^   [E0020] Type error: expected integer but got cell state"),
+        "
+        @transition {
+            become #0
+        }
+        @states #0",
+    );
+}
+
+#[test]
+fn test_name_directive() {
+    let rule = ast::make_rule(Rc::new(
+        "@name \"Conway's Game of Life\" @transition { remain }".to_owned(),
+    ))
+    .expect("Failed to build rule");
+    assert_eq!(Some("Conway's Game of Life"), rule.name());
+
+    let rule =
+        ast::make_rule(Rc::new("@transition { remain }".to_owned())).expect("Failed to build rule");
+    assert_eq!(None, rule.name());
+
+    // Duplicate name
+    assert_output(
+        Err("Error at line 1; column 43
+@transition { remain } @name \"Life\" @name \"HighLife\"
+                                          ^^^^^^^^^^   [E0014] Multiple \"name\" directives; only one is allowed"),
+        "@transition { remain } @name \"Life\" @name \"HighLife\"",
+    );
+
+    // Name that is not a string
+    assert_output(
+        Err("Error at line 1; column 30
+@transition { remain } @name life
+                             ^^^^   [E0005] Expected string"),
+        "@transition { remain } @name life",
+    );
+}
+
+#[test]
+fn test_description_directive() {
+    // Single-line description
+    let rule = ast::make_rule(Rc::new(
+        "@description \"Cells are born with 3 neighbors\" @transition { remain }".to_owned(),
+    ))
+    .expect("Failed to build rule");
+    assert_eq!(Some("Cells are born with 3 neighbors"), rule.description());
+    assert_eq!(None, rule.name());
+
+    // Multi-line description
+    let rule = ast::make_rule(Rc::new(
+        "@name \"Life\"
+@description \"Cells are born with 3 neighbors
+and survive with 2 or 3.\\nEveryone else dies.\"
+@transition { remain }"
+            .to_owned(),
+    ))
+    .expect("Failed to build rule");
+    assert_eq!(Some("Life"), rule.name());
+    assert_eq!(
+        Some("Cells are born with 3 neighbors\nand survive with 2 or 3.\nEveryone else dies."),
+        rule.description(),
+    );
+
+    let rule =
+        ast::make_rule(Rc::new("@transition { remain }".to_owned())).expect("Failed to build rule");
+    assert_eq!(None, rule.description());
+
+    // Errors after a multi-line description point to the right line
+    assert_output(
+        Err("Error at line 5; column 13
+set x = #1
+        ^^   [E0020] Type error: expected integer but got cell state"),
+        "@description \"Line one
+    line two\"
+@transition {
+    set x = 1
+    set x = #1
+}",
+    );
+
+    // Description that is not a string
+    assert_output(
+        Err("Error at line 1; column 37
+@transition { remain } @description 3
+                                    ^   [E0005] Expected string"),
+        "@transition { remain } @description 3",
+    );
+}
+
+#[test]
+fn test_ndca_version_directive() {
+    let version = |source_code: &str| {
+        ast::make_rule(Rc::new(source_code.to_owned()))
+            .expect("Failed to build rule")
+            .version()
+    };
+    assert_eq!(
+        Some((1, 0)),
+        version("@ndca_version \"1.0\" @transition { remain }")
+    );
+    // Newer minor versions are accepted.
+    assert_eq!(
+        Some((1, 12)),
+        version("@transition { remain } @ndca_version \"1.12\"")
+    );
+    assert_eq!(None, version("@transition { remain }"));
+
+    // Unsupported major version
+    assert_output(
+        Err("Error at line 2; column 23
+@ndca_version \"2.0\"
+              ^^^^^   [E0045] NDCA version 2.0 is not supported (maximum is 1.x)"),
+        "
+        @ndca_version \"2.0\"
+        @transition { remain }",
+    );
+
+    // Malformed versions
+    for &bad_version in &["\"1\"", "\"1.0.0\"", "\"1.x\"", "\"+1.0\"", "\"\"", "1"] {
+        let source_code = format!("@transition {{ remain }} @ndca_version {}", bad_version);
+        let expected = format!(
+            "Error at line 1; column 38
+{}
+{}{}   [E0005] Expected version string, e.g. \"1.0\"",
+            source_code,
+            " ".repeat(37),
+            "^".repeat(bad_version.len()),
+        );
+        assert_output(Err(expected.as_str()), &source_code);
+    }
+}
+
+#[test]
+fn test_string_escapes() {
+    let rule = ast::make_rule(Rc::new(
+        r#"@name "Say \"hi\"\\\n\t'bye'" @transition { remain }"#.to_owned(),
+    ))
+    .expect("Failed to build rule");
+    assert_eq!(Some("Say \"hi\"\\\n\t'bye'"), rule.name());
+    let rule = ast::make_rule(Rc::new(
+        r#"@name 'it\'s' @transition { remain }"#.to_owned(),
+    ))
+    .expect("Failed to build rule");
+    assert_eq!(Some("it's"), rule.name());
+
+    // Invalid escape sequence
+    assert_output(
+        Err(r#"Error at line 1; column 32
+@transition { remain } @name "a\qb"
+                               ^^   [E0036] Invalid escape sequence"#),
+        r#"@transition { remain } @name "a\qb""#,
+    );
+
+    // Unterminated string
+    assert_output(
+        Err(r#"Error at line 1; column 30
+@transition { remain } @name "Life
+                             ^   [E0002] This string never ends"#),
+        r#"@transition { remain } @name "Life"#,
+    );
+    assert_output(
+        Err(r#"Error at line 1; column 30
+@transition { remain } @name "Life\"
+                             ^   [E0002] This string never ends"#),
+        r#"@transition { remain } @name "Life\""#,
+    );
+}
+
+#[test]
+fn test_names_directive() {
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            if neighbor(0, 0) == dead {
+                become dying
+            }
+            become alive
+        }
+        @states 3
+        @names [dead, alive, dying]",
+    );
+
+    // Variables take precedence over cell state names
+    assert_output(
+        Ok(ConstValue::CellState(0)),
+        "
+        @transition {
+            set alive = #0
+            become alive
+        }
+        @names [dead, alive]",
+    );
+
+    // Unknown name
+    assert_output(
+        Err("Error at line 3; column 20
+become alvie
+       ^^^^^   [E0024] This variable must be initialized before it is used"),
+        "
+        @transition {
+            become alvie
+        }
+        @names [dead, alive]",
+    );
+
+    // Duplicate name
+    assert_output(
+        Err("Error at line 1; column 45
+@transition { remain } @names [dead, alive, dead]
+                                            ^^^^   [E0030] There is already a cell state with this name"),
+        "@transition { remain } @names [dead, alive, dead]",
+    );
+
+    // More names than states
+    assert_output(
+        Err("Error at line 1; column 45
+@transition { remain } @names [dead, alive, dying]
+                                            ^^^^^   [E1007] Cell state out of range"),
+        "@transition { remain } @names [dead, alive, dying]",
+    );
+}
+
+#[test]
+fn test_default_state_directive() {
+    // Falling off the end of the transition function gives the default state.
+    let source_code = "
+        @states 3
+        @default_state #1
+        @transition {
+            if neighbor(1, 0) == #1 {
+                become #2
+            }
+        }";
+    assert_neighborhood_output(
+        &[0, 0, 0, 0, 0, 1, 0, 0, 0],
+        Ok(ConstValue::CellState(2)),
+        source_code,
+    );
+    for &center in &[0, 2] {
+        assert_neighborhood_output(
+            &[0, 0, 0, 0, center, 2, 0, 0, 0],
+            Ok(ConstValue::CellState(1)),
+            source_code,
+        );
+    }
+    // ... including when there is no transition function at all.
+    assert_output(Ok(ConstValue::CellState(1)), "@default_state #1");
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to build rule");
+    assert_eq!(Some(1), rule.default_state());
+
+    // The default state may be an integer or a named cell state.
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @states 3
+        @names [dead, dying, alive]
+        @default_state #alive
+        @transition {
+            if false {
+                remain
+            }
+        }",
+    );
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "@transition { } @states 3 @default_state 1 + 1",
+    );
+
+    // Invalid default states
+    assert_output(
+        Err("Error at line 1; column 49
+@transition { remain } @states 3 @default_state #5
+                                                ^^   [E1007] Cell state out of range"),
+        "@transition { remain } @states 3 @default_state #5",
+    );
+    assert_output(
+        Err("Error at line 1; column 49
+@transition { remain } @states 3 @default_state 5
+                                                ^   [E1007] Cell state out of range"),
+        "@transition { remain } @states 3 @default_state 5",
+    );
+    assert_output(
+        Err("Error at line 1; column 39
+@transition { remain } @default_state true
+                                      ^^^^   [E0020] Type error: expected cell state but got boolean"),
+        "@transition { remain } @default_state true",
+    );
+}
+
+#[test]
+fn test_ndim_directive() {
+    // Vectors without an explicit length have one component per dimension,
+    // which defaults to 2.
+    assert_func_output(
+        &[],
+        Ok(ConstValue::Vector(vec![1, 2])),
+        "@function vec test() { return [1, 2] }",
+        Some("test"),
+    );
+    assert_func_output(
+        &[],
+        Ok(ConstValue::Vector(vec![1, 2, 3])),
+        "
+        @ndim 3
+        @function vec test() { return [1, 2, 3] }",
+        Some("test"),
+    );
+    assert_func_output(
+        &[],
+        Err("Error at line 3; column 32
+@function vec test() { return [1, 2] }
+                       ^^^^^^^^^^^^^   [E0020] Type error: expected vector of length 3 but got vector of length 2"),
+        "
+        @ndim 3
+        @function vec test() { return [1, 2] }",
+        Some("test"),
+    );
+
+    // Invalid dimension counts
+    for &count in &["0", "7"] {
+        assert_output(
+            Err(&format!(
+                "Error at line 2; column 15
+@ndim {}
+      ^   [E0017] Number of dimensions must range from 1 to 6",
+                count,
+            )),
+            &format!(
+                "
+        @ndim {}
+        @transition {{
+            become #0
+        }}",
+                count,
+            ),
+        );
+    }
+}
+
+#[test]
+fn test_block_comments() {
+    // Nested block comment
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            /* become #1 /* nested */ become #3 */
+            become #2
+        }
+        @states 4",
+    );
+
+    // Block comment spanning multiple lines
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            /*
+            // line comment inside
+            become #1
+            */
+            become #2
+        }
+        @states 4",
+    );
+
+    // Unterminated block comment
+    assert_output(
+        Err("Error at line 3; column 13
+/* become #1 /* nested */
+^^   [E0002] This block comment never ends"),
+        "
+        @transition {
+            /* become #1 /* nested */
+            become #2
+        }",
+    );
+}
+
+#[test]
+fn test_no_trailing_newline() {
+    // Ending with a line comment
+    assert_output(
+        Ok(ConstValue::CellState(1)),
+        "@transition { become #1 } // comment",
+    );
+    assert_output(
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            become #2
+        }
+        @states 3 // three states",
+    );
+    // Ending with an identifier
+    assert_output(
+        Ok(ConstValue::CellState(0)),
+        "@transition { remain } @neighborhood vonNeumann",
+    );
+    // Errors at the end of the file point at the last token.
+    assert_output(
+        Err("Error at line 1; column 32
+@transition { remain } @states foo
+                               ^^^   [E0024] This variable must be initialized before it is used"),
+        "@transition { remain } @states foo",
+    );
+}
+
+#[test]
+fn test_value_cmp() {
+    assert_eq!(ConstValue::Int(3), ConstValue::Int(3));
+    assert_ne!(ConstValue::Int(3), ConstValue::Int(4));
+    assert!(ConstValue::Int(-3) < ConstValue::Int(3));
+    assert!(ConstValue::Int(3) >= ConstValue::Int(3));
+
+    // Values of different types are never equal, and are unordered.
+    assert_ne!(ConstValue::Int(1), ConstValue::CellState(1));
+    assert_ne!(ConstValue::Int(1), ConstValue::Bool(true));
+    assert_ne!(ConstValue::Int(1), ConstValue::Vector(vec![1]));
+    assert_eq!(
+        None,
+        ConstValue::Int(1).partial_cmp(&ConstValue::CellState(1))
+    );
+
+    // Only integers are ordered.
+    assert_eq!(
+        None,
+        ConstValue::CellState(1).partial_cmp(&ConstValue::CellState(2))
+    );
+    assert_eq!(
+        Some(std::cmp::Ordering::Equal),
+        ConstValue::Vector(vec![1, 2]).partial_cmp(&ConstValue::Vector(vec![1, 2])),
+    );
+
+    assert!(ConstValue::Bool(true).as_bool().unwrap());
+    assert!(ConstValue::Int(1).as_bool().is_err());
+}
+
+#[test]
+fn test_value_type_name() {
+    let compiler = Compiler::new().expect("Failed to create compiler");
+    for (value, expected) in vec![
+        (ConstValue::Int(3), "integer"),
+        (ConstValue::Bool(true), "boolean"),
+        (ConstValue::CellState(1), "cell state"),
+        (ConstValue::Vector(vec![1, 2]), "vector"),
+    ] {
+        assert_eq!(expected, value.type_name());
+        assert_eq!(expected, compiler.value_from_const(value).type_name());
+    }
+
+    // Internal type errors mention both types
+    let error = ConstValue::Int(3).as_cell_state().unwrap_err();
+    assert_eq!(
+        LangErrorMsg::InternalError(
+            "Uncaught type error: expected cell state but got integer".into()
+        ),
+        error.msg,
+    );
+    let error = compiler
+        .value_from_const(ConstValue::Vector(vec![1, 2]))
+        .as_bool()
+        .unwrap_err();
+    assert_eq!(
+        LangErrorMsg::InternalError("Uncaught type error: expected boolean but got vector".into()),
+        error.msg,
+    );
 }
 
 /// Compiles and runs the transition function of the given source code.
@@ -49,12 +777,34 @@ fn assert_output<'a>(expected: Result<ConstValue, &'a str>, source_code: &str) {
     assert_func_output(&[], expected, source_code, None)
 }
 
+/// Compiles and runs the transition function of the given source code with
+/// the given neighborhood.
+fn assert_neighborhood_output<'a>(
+    neighborhood: &[LangCellState],
+    expected: Result<ConstValue, &'a str>,
+    source_code: &str,
+) {
+    assert_func_output_with_neighborhood(&[], Some(neighborhood), expected, source_code, None)
+}
+
 /// Compiles and runs the specified function of the given source code.
 fn assert_func_output<'a>(
     args: &[ConstValue],
     expected: Result<ConstValue, &'a str>,
     source_code: &str,
     fn_name: Option<&str>,
+) {
+    assert_func_output_with_neighborhood(args, None, expected, source_code, fn_name)
+}
+
+/// Compiles and runs the specified function of the given source code,
+/// optionally with the given neighborhood.
+fn assert_func_output_with_neighborhood<'a>(
+    args: &[ConstValue],
+    neighborhood: Option<&[LangCellState]>,
+    expected: Result<ConstValue, &'a str>,
+    source_code: &str,
+    fn_name: Option<&str>,
 ) {
     let expected_result = expected.map_err(|e| e.into());
 
@@ -63,15 +813,17 @@ fn assert_func_output<'a>(
         Ok(rule) => {
             // Compile the rule.
             let mut compiler = Compiler::new().expect("Failed to create compiler");
-            let user_fn = if let Some(name) = fn_name {
-                &rule.helper_functions()[name]
+            let compiled_function = if let Some(name) = fn_name {
+                rule.compile_helper_function(&mut compiler, name)
             } else {
-                rule.transition_function()
+                rule.compile_transition_function(&mut compiler)
             };
-            user_fn
-                .compile(&mut compiler)
+            compiled_function
                 .and_then(|mut compiled_function| {
                     compiled_function.set_args(args);
+                    if let Some(cells) = neighborhood {
+                        compiled_function.set_neighborhood(cells);
+                    }
                     compiled_function.call()
                 })
                 .map_err(|e| e.with_source(source_code).to_string().into())
@@ -0,0 +1,747 @@
+use std::rc::Rc;
+
+use super::{assert_neighborhood_output, ast, Compiler, ConstValue, LangCellState};
+
+#[test]
+fn test_neighbor_access() {
+    // 3x3 neighborhood with the X axis varying fastest
+    let neighborhood = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+    for &(dx, dy, expected) in &[
+        (-1, -1, 0),
+        (0, -1, 1),
+        (1, -1, 2),
+        (-1, 0, 3),
+        (0, 0, 4),
+        (1, 0, 5),
+        (-1, 1, 6),
+        (1, 1, 8),
+    ] {
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            &format!(
+                "
+                @transition {{
+                    become neighbor({}, {})
+                }}",
+                dx, dy,
+            ),
+        );
+    }
+
+    // Offsets can also be given as vectors
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(7)),
+        "
+        @transition {
+            set offset = [1, 1]
+            become neighbor(offset - [1, 0])
+        }",
+    );
+
+    // Neighbor states can be compared and used in expressions
+    assert_neighborhood_output(
+        &[0, 0, 1, 0, 1, 0, 1, 0, 0],
+        Ok(ConstValue::CellState(1)),
+        "
+        @transition {
+            if neighbor(1, -1) == neighbor(0, 0) and neighbor(1, 1) == #0 {
+                become neighbor(-1, 1)
+            }
+            become #0
+        }",
+    );
+
+    // 1D neighborhood
+    assert_neighborhood_output(
+        &[2, 0, 1],
+        Ok(ConstValue::CellState(2)),
+        "
+        @ndim 1
+        @transition {
+            become neighbor(-1)
+        }
+        @states 3",
+    );
+
+    // Out of range
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 4; column 20
+become neighbor(dx, 0)
+       ^^^^^^^^^^^^^^^   [E1005] Neighbor offset must range from -1 to 1"),
+        "
+        @transition {
+            set dx = 2
+            become neighbor(dx, 0)
+        }",
+    );
+
+    // Wrong number of arguments
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 3; column 20
+become neighbor(1)
+       ^^^^^^^^^^^   [E0022] Invalid arguments [Int] for neighbor; expected [Int, Int] or [Vector(2)]"),
+        "
+        @transition {
+            become neighbor(1)
+        }",
+    );
+}
+
+#[test]
+fn test_remain() {
+    let neighborhood = [0, 1, 0, 1, 3, 1, 0, 1, 0];
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(3)),
+        "
+        @transition {
+            remain
+        }
+        @states 4",
+    );
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(0)),
+        "
+        @transition {
+            if neighbor(0, 0) == #2 {
+                remain
+            }
+            become #0
+        }
+        @states 4",
+    );
+
+    // Missing `become` or `remain` on some paths
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 6; column 9
+}
+^   [E0031] Not every path through the transition function ends in 'become' or 'remain'"),
+        "
+        @transition {
+            if neighbor(1, 0) == #0 {
+                become #1
+            }
+        }
+        @states 4",
+    );
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(3)),
+        "
+        @transition {
+            if neighbor(1, 0) == #0 {
+                become #1
+            } else {
+                remain
+            }
+        }
+        @states 4",
+    );
+
+    // 3D neighborhood
+    let mut neighborhood = [0; 27];
+    neighborhood[13] = 2;
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(2)),
+        "
+        @ndim 3
+        @transition {
+            remain
+        }
+        @states 3",
+    );
+
+    // `remain` in a helper function
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 3; column 13
+remain
+^^^^^^   [E0026] Cannot use 'remain' outside of transition functions"),
+        "
+        @function int test() {
+            remain
+        }
+        @transition {
+            become #0
+        }",
+    );
+}
+
+#[test]
+fn test_this() {
+    // `become this` is equivalent to `remain`.
+    let neighborhood = [0, 1, 0, 1, 3, 1, 0, 1, 0];
+    for &transition in &["become this", "remain"] {
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(3)),
+            &format!(
+                "
+                @transition {{
+                    {}
+                }}
+                @states 4",
+                transition,
+            ),
+        );
+    }
+
+    // `this` is an ordinary expression.
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(2)),
+        "
+        @transition {
+            if this == neighbor(0, 0) and this != neighbor(1, 0) {
+                become #(state_id(this) - 1)
+            }
+            become #0
+        }
+        @states 4",
+    );
+
+    // `this` can be used in a helper function.
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(1)),
+        "
+        @function bool is_alive() {
+            return this == #1
+        }
+        @transition {
+            if is_alive() {
+                become #0
+            }
+            become neighbor(1, 0)
+        }
+        @states 4",
+    );
+
+    // 3D neighborhood
+    let mut neighborhood = [0; 27];
+    neighborhood[13] = 2;
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(2)),
+        "
+        @ndim 3
+        @transition {
+            become this
+        }
+        @states 3",
+    );
+}
+
+#[test]
+fn test_count_neighbors_of() {
+    // The center cell is not counted.
+    let neighborhood = [1, 1, 0, 0, 1, 1, 2, 0, 1];
+    for &(state, expected) in &[(0, 3), (1, 4), (2, 1), (3, 0)] {
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            &format!(
+                "
+                @transition {{
+                    become #(count_neighbors_of(#{}))
+                }}
+                @states 9",
+                state,
+            ),
+        );
+    }
+
+    // Conway's Game of Life
+    let life = "
+        @transition {
+            set n = count_neighbors_of(#1)
+            if n == 3 or n == 2 and neighbor(0, 0) == #1 {
+                become #1
+            }
+            become #0
+        }";
+    for &(neighborhood, expected) in &[
+        // Survival
+        ([1, 0, 0, 0, 1, 0, 0, 0, 1], 1),
+        ([1, 1, 0, 0, 1, 0, 0, 0, 1], 1),
+        // Birth
+        ([0, 1, 0, 1, 0, 1, 0, 0, 0], 1),
+        // Underpopulation
+        ([0, 0, 0, 0, 1, 0, 0, 0, 1], 0),
+        // Overpopulation
+        ([1, 1, 1, 0, 1, 1, 0, 0, 0], 0),
+        // No birth
+        ([1, 0, 0, 0, 0, 0, 0, 0, 1], 0),
+    ] {
+        assert_neighborhood_output(&neighborhood, Ok(ConstValue::CellState(expected)), life);
+    }
+
+    // 1D neighborhood
+    assert_neighborhood_output(
+        &[1, 1, 1],
+        Ok(ConstValue::CellState(2)),
+        "
+        @ndim 1
+        @transition {
+            become #(count_neighbors_of(#1))
+        }
+        @states 3",
+    );
+}
+
+#[test]
+fn test_neighbor_reductions() {
+    // The center cell is not included.
+    let neighborhood = [1, 1, 0, 0, 1, 1, 2, 0, 1];
+    for &(neighborhood_directive, min, max, sum) in &[
+        ("moore", 0, 2, 6),
+        ("vonNeumann", 0, 1, 2),
+        ("[[1, 1], [-1, 1]]", 1, 2, 3),
+        ("[[0, 0]]", 1, 1, 1),
+    ] {
+        for &(function, expected) in &[
+            ("neighbor_min", min),
+            ("neighbor_max", max),
+            ("neighbor_sum", sum),
+        ] {
+            assert_neighborhood_output(
+                &neighborhood,
+                Ok(ConstValue::CellState(expected)),
+                &format!(
+                    "
+                    @neighborhood {}
+                    @transition {{
+                        become #({}())
+                    }}
+                    @states 9",
+                    neighborhood_directive, function,
+                ),
+            );
+        }
+    }
+
+    // Sandpile-style rule using the sum of the neighbors
+    let source_code = "
+        @neighborhood vonNeumann
+        @transition {
+            become #(neighbor_sum() + state_id(this) - 4 * int(this == #4))
+        }
+        @states 16";
+    assert_neighborhood_output(
+        &[0, 2, 0, 3, 4, 1, 0, 0, 0],
+        Ok(ConstValue::CellState(6)),
+        source_code,
+    );
+
+    // Reductions take no arguments.
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 3; column 22
+become #(neighbor_max(#1))
+         ^^^^^^^^^^^^^^^^   [E0022] Invalid arguments [CellState] for neighbor_max; expected []"),
+        "
+        @transition {
+            become #(neighbor_max(#1))
+        }",
+    );
+}
+
+#[test]
+fn test_neighborhood_directive() {
+    let neighborhood = [1, 1, 0, 0, 1, 1, 2, 0, 1];
+    let count_ones = |neighborhood_directive: &str| {
+        format!(
+            "
+            @neighborhood {}
+            @transition {{
+                become #(count_neighbors_of(#1))
+            }}
+            @states 9",
+            neighborhood_directive,
+        )
+    };
+
+    // Moore neighborhood (the default)
+    for &directive in &["moore", "Moore"] {
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(4)),
+            &count_ones(directive),
+        );
+    }
+
+    // von Neumann neighborhood
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(2)),
+        &count_ones("vonNeumann"),
+    );
+    let rule = ast::make_rule(Rc::new(count_ones("vonNeumann"))).expect("Failed to build rule");
+    assert_eq!(
+        rule.neighborhood(),
+        &[vec![0, -1], vec![-1, 0], vec![1, 0], vec![0, 1]][..],
+    );
+
+    // Custom neighborhood, which may include the center cell
+    assert_neighborhood_output(
+        &neighborhood,
+        Ok(ConstValue::CellState(3)),
+        &count_ones("[[-1, -1], [0, 0], [1, 1], [-1, 1]]"),
+    );
+
+    // 1D custom neighborhood
+    assert_neighborhood_output(
+        &[1, 0, 1],
+        Ok(ConstValue::CellState(1)),
+        "
+        @ndim 1
+        @neighborhood [[1]]
+        @transition {
+            become #(count_neighbors_of(#1))
+        }",
+    );
+
+    // Offset with the wrong number of dimensions
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 1; column 47
+@transition { remain } @neighborhood [[1, 0], [0, 1, 0]]
+                                              ^^^^^^^^^   [E0033] Neighborhood offset must be a vector of length 2 to match the number of dimensions"),
+        "@transition { remain } @neighborhood [[1, 0], [0, 1, 0]]",
+    );
+
+    // Offset out of range
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 1; column 47
+@transition { remain } @neighborhood [[0, 1], [-2, 0]]
+                                              ^^^^^^^   [E1005] Neighbor offset must range from -1 to 1"),
+        "@transition { remain } @neighborhood [[0, 1], [-2, 0]]",
+    );
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 1; column 39
+@transition { remain } @neighborhood [[-9223372036854775808, 0]]
+                                      ^^^^^^^^^^^^^^^^^^^^^^^^^   [E1005] Neighbor offset must range from -1 to 1"),
+        "@transition { remain } @neighborhood [[-9223372036854775808, 0]]",
+    );
+
+    // Unknown neighborhood
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 1; column 38
+@transition { remain } @neighborhood hexagonal
+                                     ^^^^^^^^^   [E0005] Expected 'moore', 'vonNeumann', or list of offsets, e.g. '[[-1, 0], [1, 0]]'"),
+        "@transition { remain } @neighborhood hexagonal",
+    );
+}
+
+#[test]
+fn test_symmetry_directive() {
+    /// Rotates a 3x3 neighborhood by 90 degrees.
+    fn rotate(cells: [LangCellState; 9]) -> [LangCellState; 9] {
+        let mut ret = [0; 9];
+        for y in 0..3 {
+            for x in 0..3 {
+                ret[y * 3 + x] = cells[(2 - x) * 3 + y];
+            }
+        }
+        ret
+    }
+
+    // The output is the same under any rotation of the input.
+    let source_code = "
+        @symmetry rotate4
+        @transition {
+            if neighbor(0, -1) == #1 and neighbor(1, -1) == #0 {
+                become #2
+            }
+            remain
+        }
+        @states 3";
+    for &(mut neighborhood, expected) in &[
+        ([0, 1, 0, 0, 0, 0, 0, 0, 0], 2),
+        ([0, 1, 1, 0, 0, 0, 0, 0, 0], 0),
+        ([1, 1, 0, 0, 1, 0, 0, 0, 0], 2),
+        ([0, 0, 0, 0, 1, 0, 0, 0, 0], 1),
+    ] {
+        for _ in 0..4 {
+            assert_neighborhood_output(
+                &neighborhood,
+                Ok(ConstValue::CellState(expected)),
+                source_code,
+            );
+            neighborhood = rotate(neighborhood);
+        }
+    }
+
+    // Without symmetry, the rule only matches in one orientation.
+    assert_neighborhood_output(
+        &[0, 0, 0, 0, 0, 1, 0, 0, 0],
+        Ok(ConstValue::CellState(0)),
+        "
+        @symmetry none
+        @transition {
+            if neighbor(0, -1) == #1 and neighbor(1, -1) == #0 {
+                become #2
+            }
+            remain
+        }
+        @states 3",
+    );
+
+    // Rotations by 45 degrees also move orthogonal neighbors to diagonal ones.
+    let diagonal_neighborhood = [1, 0, 0, 0, 0, 0, 0, 0, 0];
+    for &(symmetry, expected) in &[("rotate4", 0), ("rotate8", 1)] {
+        assert_neighborhood_output(
+            &diagonal_neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            &format!(
+                "
+                @symmetry {}
+                @transition {{
+                    if neighbor(0, -1) == #1 {{
+                        become #1
+                    }}
+                    remain
+                }}",
+                symmetry,
+            ),
+        );
+    }
+
+    // Reflection only swaps left and right.
+    let source_code = "
+        @symmetry reflect
+        @transition {
+            if neighbor(-1, 0) == #1 {
+                become #1
+            }
+            remain
+        }";
+    for &(neighborhood, expected) in &[
+        ([0, 0, 0, 1, 0, 0, 0, 0, 0], 1),
+        ([0, 0, 0, 0, 0, 1, 0, 0, 0], 1),
+        ([0, 1, 0, 0, 0, 0, 0, 0, 0], 0),
+    ] {
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            source_code,
+        );
+    }
+
+    // Errors are returned from whichever orientation causes them.
+    assert_neighborhood_output(
+        &[0, 1, 0, 0, 0, 0, 0, 0, 0],
+        Err("Error at line 1; column 78
+@symmetry rotate4 @transition { set d = state_id(neighbor(1, 0)) - 1 set d = 1 / d remain }
+                                                                             ^^^^^   [E1002] Divide by zero"),
+        "@symmetry rotate4 @transition { set d = state_id(neighbor(1, 0)) - 1 set d = 1 / d remain }",
+    );
+
+    // Symmetry is only supported in 2D.
+    assert_neighborhood_output(
+        &[0; 27],
+        Err("Error at line 1; column 42
+@ndim 3 @transition { remain } @symmetry rotate4
+                                         ^^^^^^^   [E0034] Symmetry is only supported in 2D"),
+        "@ndim 3 @transition { remain } @symmetry rotate4",
+    );
+
+    // Unknown symmetry
+    assert_neighborhood_output(
+        &[0; 9],
+        Err("Error at line 1; column 34
+@transition { remain } @symmetry rotate6
+                                 ^^^^^^^   [E0005] Expected 'none', 'rotate4', 'rotate8', or 'reflect'"),
+        "@transition { remain } @symmetry rotate6",
+    );
+}
+
+#[test]
+fn test_for_neighbors() {
+    let neighborhood = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+    for &(neighborhood_name, expected) in &[("moore", 40), ("vonNeumann", 20)] {
+        assert_neighborhood_output(
+            &neighborhood,
+            Ok(ConstValue::CellState(expected)),
+            &format!(
+                "
+                @neighborhood {}
+                @transition {{
+                    set total = 0
+                    for n in neighborhood {{
+                        set total += state_id(n)
+                    }}
+                    become #(total)
+                }}
+                @states 50",
+                neighborhood_name,
+            ),
+        );
+    }
+
+    // Neighbors are visited in the order they were declared, and `break` and
+    // `continue` work as usual.
+    let source_code = "
+        @neighborhood [[1, 0], [-1, 0], [0, 1], [0, -1]]
+        @transition {
+            set ret = #0
+            for n in neighborhood {
+                if n == #0 {
+                    continue
+                }
+                set ret = n
+                break
+            }
+            become ret
+        }
+        @states 4";
+    assert_neighborhood_output(
+        &[0, 1, 0, 3, 0, 0, 0, 2, 0],
+        Ok(ConstValue::CellState(3)),
+        source_code,
+    );
+    assert_neighborhood_output(
+        &[0, 1, 0, 0, 0, 0, 0, 0, 0],
+        Ok(ConstValue::CellState(1)),
+        source_code,
+    );
+
+    // Nested loops over a large neighborhood
+    assert_neighborhood_output(
+        &[0; 729],
+        Ok(ConstValue::CellState(1)),
+        "
+        @ndim 6
+        @transition {
+            set pairs = 0
+            for a in neighborhood {
+                for b in neighborhood {
+                    set pairs += 1
+                }
+            }
+            become #(if pairs == 728 * 728 then 1 else 0)
+        }",
+    );
+
+    // The loop variable is a cell state.
+    assert_neighborhood_output(
+        &neighborhood,
+        Err("Error at line 4; column 25
+set n = 1
+        ^   [E0020] Type error: expected cell state but got integer"),
+        "
+        @transition {
+            for n in neighborhood {
+                set n = 1
+            }
+            remain
+        }",
+    );
+}
+
+/// Compiles and runs the transition function of the given source code with
+/// the given cell states and data for layer 1, returning any error as a
+/// string.
+fn layered_output(
+    cells: &[LangCellState],
+    layer: &[LangCellState],
+    source_code: &str,
+) -> Result<ConstValue, String> {
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    let mut compiler = Compiler::new().expect("Failed to create compiler");
+    let mut compiled_function = rule
+        .compile_transition_function(&mut compiler)
+        .expect("Failed to compile function");
+    compiled_function.set_neighborhood(cells);
+    compiled_function.set_layer(1, layer);
+    compiled_function
+        .call()
+        .map_err(|e| e.with_source(source_code).to_string())
+}
+
+#[test]
+fn test_layers() {
+    let cells = [0, 1, 0, 1, 0, 1, 0, 1, 0];
+    let source_code = "
+        @layers 2
+        @transition {
+            if layer(1, 0, 0) > 5 {
+                become #1
+            }
+            remain
+        }";
+    assert_eq!(
+        Ok(ConstValue::CellState(1)),
+        layered_output(&cells, &[0, 0, 0, 0, 7, 0, 0, 0, 0], source_code),
+    );
+    assert_eq!(
+        Ok(ConstValue::CellState(0)),
+        layered_output(&cells, &[9, 9, 9, 9, 3, 9, 9, 9, 9], source_code),
+    );
+
+    // Layer 0 is the cell state, and offsets can also be given as vectors.
+    let source_code = "
+        @layers 2
+        @transition {
+            if layer(0, [1, 0]) == 1 and layer(1, [-1, 1]) == 200 {
+                become #1
+            }
+            remain
+        }";
+    assert_eq!(
+        Ok(ConstValue::CellState(1)),
+        layered_output(&cells, &[0, 0, 0, 0, 0, 0, 200, 0, 0], source_code),
+    );
+
+    // Symmetries apply to every layer.
+    let source_code = "
+        @layers 2
+        @symmetry rotate4
+        @transition {
+            if layer(1, 1, 0) == 1 {
+                become #1
+            }
+            remain
+        }";
+    assert_eq!(
+        Ok(ConstValue::CellState(1)),
+        layered_output(&cells, &[0, 1, 0, 0, 0, 0, 0, 0, 0], source_code),
+    );
+
+    // Layer out of range
+    assert_eq!(
+        Err("Error at line 5; column 22
+become #(layer(n, 0, 0))
+         ^^^^^^^^^^^^^^   [E1013] Layer index out of range"
+            .to_owned()),
+        layered_output(
+            &cells,
+            &[0; 9],
+            "
+        @layers 2
+        @transition {
+            set n = 2
+            become #(layer(n, 0, 0))
+        }",
+        ),
+    );
+
+    // Too many layers
+    assert_neighborhood_output(
+        &cells,
+        Err("Error at line 1; column 9
+@layers 3 @transition { remain }
+        ^   [E0040] Number of layers must range from 1 to 2"),
+        "@layers 3 @transition { remain }",
+    );
+}
@@ -0,0 +1,122 @@
+use std::rc::Rc;
+
+use super::{assert_func_output, assert_output, ast, Compiler, ConstValue};
+
+/// Compiles the specified function of the given source code, seeds its random
+/// number generator, and returns the results of calling it the given number of
+/// times with the given arguments.
+fn random_sequence(
+    source_code: &str,
+    fn_name: Option<&str>,
+    args: &[ConstValue],
+    seed: u64,
+    len: usize,
+) -> Vec<ConstValue> {
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    let mut compiler = Compiler::new().expect("Failed to create compiler");
+    let mut compiled_function = match fn_name {
+        Some(name) => rule.compile_helper_function(&mut compiler, name),
+        None => rule.compile_transition_function(&mut compiler),
+    }
+    .expect("Failed to compile function");
+    compiled_function.set_args(args);
+    compiled_function.set_rng_seed(seed);
+    (0..len)
+        .map(|_| compiled_function.call().expect("Function failed"))
+        .collect()
+}
+
+#[test]
+fn test_random() {
+    let source_code = "
+        @transition {
+            become #(random(10))
+        }
+        @states 10";
+    let sequence = random_sequence(source_code, None, &[], 42, 50);
+
+    // The same seed always produces the same sequence.
+    assert_eq!(sequence, random_sequence(source_code, None, &[], 42, 50));
+    // A different seed produces a different sequence.
+    assert_ne!(sequence, random_sequence(source_code, None, &[], 43, 50));
+    // Every number is in range, and not every number is the same.
+    for value in &sequence {
+        match value {
+            ConstValue::CellState(0..=9) => (),
+            _ => panic!("Random number out of range: {:?}", value),
+        }
+    }
+    assert!(sequence.iter().any(|value| *value != sequence[0]));
+
+    // Random numbers in helper functions use the same generator.
+    let helper_source_code = "
+        @function int roll() {
+            return random(10)
+        }
+        @transition {
+            become #(roll())
+        }
+        @states 10";
+    assert_eq!(
+        sequence,
+        random_sequence(helper_source_code, None, &[], 42, 50)
+    );
+
+    // Random numbers are never evaluated at compile time.
+    assert_output(
+        Err("Error at line 3; column 22
+become #(random(0))
+         ^^^^^^^^^   [E1011] Upper bound of random number must be positive"),
+        "
+        @transition {
+            become #(random(0))
+        }",
+    );
+    assert_func_output(
+        &[ConstValue::Int(-3)],
+        Err("Error at line 1; column 36
+@function int test(int n) { return random(n) }
+                                   ^^^^^^^^^   [E1011] Upper bound of random number must be positive"),
+        "@function int test(int n) { return random(n) }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_random_chance() {
+    let source_code = "
+        @function bool test(int num, int den) {
+            return random_chance(num, den)
+        }";
+    let chance = |num, den| {
+        let args = [ConstValue::Int(num), ConstValue::Int(den)];
+        random_sequence(source_code, Some("test"), &args, 7, 64)
+    };
+
+    // Impossible and certain events
+    for &(num, den) in &[(0, 1), (0, 5), (-2, 5)] {
+        assert!(chance(num, den)
+            .iter()
+            .all(|b| *b == ConstValue::Bool(false)));
+    }
+    for &(num, den) in &[(1, 1), (5, 5), (7, 5)] {
+        assert!(chance(num, den)
+            .iter()
+            .all(|b| *b == ConstValue::Bool(true)));
+    }
+    // Coin flip
+    let flips = chance(1, 2);
+    assert_eq!(flips, chance(1, 2));
+    assert!(flips.contains(&ConstValue::Bool(true)));
+    assert!(flips.contains(&ConstValue::Bool(false)));
+
+    // Invalid denominator
+    assert_func_output(
+        &[ConstValue::Int(1), ConstValue::Int(0)],
+        Err("Error at line 3; column 20
+return random_chance(num, den)
+       ^^^^^^^^^^^^^^^^^^^^^^^   [E1011] Upper bound of random number must be positive"),
+        source_code,
+        Some("test"),
+    );
+}
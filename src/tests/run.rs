@@ -0,0 +1,129 @@
+use std::rc::Rc;
+
+use super::super::run::{self, CompiledRule};
+use super::{ast, ConstValue, LangCellState, LangErrorMsg};
+
+#[test]
+fn test_run() {
+    // Sample rule
+    let source_code = Rc::new(include_str!("../../examples/life.ndca").to_owned());
+    assert_eq!(
+        ConstValue::CellState(0),
+        run::compile_and_run(source_code).expect("Failed to run rule"),
+    );
+
+    // Custom neighborhood
+    let source_code = "
+        @transition {
+            become neighbor(1, 0)
+        }
+        @states 3";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    assert_eq!(
+        ConstValue::CellState(0),
+        run::run_rule(&rule, None).expect("Failed to run rule"),
+    );
+    assert_eq!(
+        ConstValue::CellState(2),
+        run::run_rule(&rule, Some(&[0, 0, 0, 0, 1, 2, 0, 0, 0])).expect("Failed to run rule"),
+    );
+
+    // Errors are returned rather than printed.
+    let source_code = "@transition { become #5 }";
+    let error = run::compile_and_run(Rc::new(source_code.to_owned())).unwrap_err();
+    assert_eq!(
+        "Error at line 1; column 22
+@transition { become #5 }
+                     ^^   [E1007] Cell state out of range",
+        error.with_source(source_code).to_string(),
+    );
+}
+
+#[test]
+fn test_run_batch() {
+    // Dummy inputs, using the bits of each index as cell states
+    let inputs: Vec<Vec<LangCellState>> = (0..1000_usize)
+        .map(|i| (0..9).map(|j| ((i >> j) & 1) as LangCellState).collect())
+        .collect();
+    let input_slices: Vec<&[LangCellState]> = inputs.iter().map(|cells| &cells[..]).collect();
+
+    // Sample rule
+    let source_code = Rc::new(include_str!("../../examples/life.ndca").to_owned());
+    let rule = ast::make_rule(source_code).expect("Failed to parse rule");
+    assert_eq!(
+        vec![0; 1000],
+        run::run_rule_batch(&rule, &input_slices).expect("Failed to run rule"),
+    );
+
+    // Conway's Game of Life
+    let source_code = "
+        @transition {
+            set n = count_neighbors_of(#1)
+            if n == 3 or n == 2 and neighbor(0, 0) == #1 {
+                become #1
+            }
+            become #0
+        }";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    let outputs = run::run_rule_batch(&rule, &input_slices).expect("Failed to run rule");
+    for (cells, &output) in inputs.iter().zip(&outputs) {
+        let n = cells.iter().sum::<LangCellState>() - cells[4];
+        let expected = (n == 3 || n == 2 && cells[4] == 1) as LangCellState;
+        assert_eq!(expected, output, "Wrong output for {:?}", cells);
+    }
+    // The batch gives the same results as running one cell at a time.
+    for (cells, &output) in inputs.iter().zip(&outputs).step_by(97) {
+        assert_eq!(
+            ConstValue::CellState(output),
+            run::run_rule(&rule, Some(cells)).expect("Failed to run rule"),
+        );
+    }
+
+    // The first error stops the batch.
+    let source_code = "@transition { become count_neighbors_of(#1) + 1 }";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    let error = run::run_rule_batch(&rule, &input_slices).unwrap_err();
+    assert_eq!(
+        "Error at line 1; column 22
+@transition { become count_neighbors_of(#1) + 1 }
+                     ^^^^^^^^^^^^^^^^^^^^^^^^^^   [E1007] Cell state out of range",
+        error.with_source(source_code).to_string(),
+    );
+}
+
+#[test]
+fn test_compiled_rule() {
+    let source_code = "
+        @transition {
+            become #(count_neighbors_of(#1))
+        }
+        @states 9";
+    let mut rule =
+        CompiledRule::from_source(Rc::new(source_code.to_owned())).expect("Failed to compile rule");
+    assert_eq!(9, rule.neighborhood_len());
+
+    // Compile once, run many times
+    for i in 0..9 {
+        let cells: Vec<LangCellState> = (0..9).map(|j| (j <= i) as LangCellState).collect();
+        let expected = if i < 4 { i + 1 } else { i };
+        assert_eq!(
+            expected as LangCellState,
+            rule.run(&cells).expect("Failed to run rule")
+        );
+    }
+    assert_eq!(0, rule.run(&[0; 9]).expect("Failed to run rule"));
+
+    // Clones can be run separately.
+    let mut other = rule.clone();
+    assert_eq!(8, other.run(&[1; 9]).expect("Failed to run rule"));
+    let batch: [&[LangCellState]; 2] = [&[1, 1, 0, 0, 0, 0, 0, 0, 0], &[0; 9]];
+    assert_eq!(
+        vec![2, 0],
+        rule.run_batch(&batch).expect("Failed to run rule")
+    );
+
+    // Compile errors are reported before anything runs.
+    let source_code = "@transition { become #(x) }";
+    let error = CompiledRule::from_source(Rc::new(source_code.to_owned())).unwrap_err();
+    assert_eq!(LangErrorMsg::UseOfUninitializedVariable, error.msg);
+}
@@ -1,17 +1,151 @@
-use super::{assert_output, ConstValue};
+use std::rc::Rc;
+
+use super::{assert_func_output, assert_output, ast, Compiler, ConstValue, LangInt};
 
 #[test]
-fn test_variable_init() {
+fn test_variable_scope() {
+    // Variables created in a block are not visible outside of it.
+    assert_output(
+        Err("Error at line 6; column 22
+become #(x)
+         ^   [E0024] This variable must be initialized before it is used"),
+        "
+        @transition {
+            if false {
+                set x = 5
+            }
+            become #(x)
+        }",
+    );
+
+    // Assigning to an existing variable in a block does not create a new one.
     assert_output(
-        Ok(ConstValue::CellState(0)),
+        Ok(ConstValue::CellState(5)),
         "
         @transition {
-            if 0 {
-                // never executed, but declares x as an integer
+            set x = 0
+            if true {
                 set x = 5
             }
             become #(x)
+        }
+        @states 6",
+    );
+
+    // Separate blocks may use the same name for variables of different types.
+    assert_func_output(
+        &[ConstValue::Int(1)],
+        Ok(ConstValue::Int(3)),
+        "
+        @function int test(int n) {
+            if n == 0 {
+                set x = #1
+                return state_id(x)
+            } else {
+                set x = n + 2
+                return x
+            }
+        }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_parallel_assignment() {
+    // All values are evaluated before any variable is assigned.
+    for &(assignment, expected) in &[
+        ("set x, y = y, x", 703),
+        ("set x, y += y, x", 1010),
+        ("set x, y, z = y, x + y, x", 3710),
+    ] {
+        assert_func_output(
+            &[ConstValue::Int(3), ConstValue::Int(7)],
+            Ok(ConstValue::Int(expected)),
+            &format!(
+                "
+                @function int test(int x, int y) {{
+                    set z = 0
+                    {}
+                    return x * 100 + y + z * 1000
+                }}",
+                assignment,
+            ),
+            Some("test"),
+        );
+    }
+
+    // Each variable has its own type.
+    assert_func_output(
+        &[ConstValue::Int(3)],
+        Ok(ConstValue::Int(5)),
+        "
+        @function int test(int x) {
+            set a, b = #2, x
+            return state_id(a) + b
+        }",
+        Some("test"),
+    );
+
+    // Type error
+    assert_func_output(
+        &[ConstValue::Int(3), ConstValue::Int(7)],
+        Err("Error at line 1; column 50
+@function int test(int x, int y) { set x, y = y, #1 return x }
+                                                 ^^   [E0020] Type error: expected integer but got cell state"),
+        "@function int test(int x, int y) { set x, y = y, #1 return x }",
+        Some("test"),
+    );
+
+    // Wrong number of values
+    assert_func_output(
+        &[ConstValue::Int(3), ConstValue::Int(7)],
+        Err("Error at line 1; column 36
+@function int test(int x, int y) { set x, y = y return x }
+                                   ^^^^^^^^^^^^   [E0035] Wrong number of values: expected 2 but got 1"),
+        "@function int test(int x, int y) { set x, y = y return x }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_chained_assignment() {
+    assert_func_output(
+        &[ConstValue::Int(5)],
+        Ok(ConstValue::Int(666)),
+        "
+        @function int test(int n) {
+            set a = 1
+            set a = b = c = n + 1
+            return a * 100 + b * 10 + c
+        }",
+        Some("test"),
+    );
+
+    // The value is only evaluated once.
+    assert_func_output(
+        &[],
+        Ok(ConstValue::Int(1)),
+        "
+        @function int test() {
+            set x = y = random(1000000000)
+            return int(x == y)
         }",
+        Some("test"),
+    );
+
+    // Every variable must have the type of the value.
+    assert_func_output(
+        &[],
+        Err("Error at line 4; column 25
+set a = b = #1
+            ^^   [E0020] Type error: expected integer but got cell state"),
+        "
+        @function int test() {
+            set a = 0
+            set a = b = #1
+            return a
+        }",
+        Some("test"),
     );
 }
 
@@ -20,7 +154,7 @@ fn test_variable_undeclared() {
     assert_output(
         Err("Error at line 3; column 22
 become #(x)
-         ^   This variable must be initialized before it is used"),
+         ^   [E0024] This variable must be initialized before it is used"),
         "
         @transition {
             become #(x)
@@ -37,9 +171,10 @@ fn test_variables() {
             set x = 3
             set another_variable = 9
             set x = another_variable - 2
-            if 1 {
+            if true {
                 become #(x + 3)
             }
+            remain
         }
         @states 11",
     );
@@ -51,7 +186,7 @@ fn test_variable_types() {
     assert_output(
         Err("Error at line 4; column 21
 set s = 3
-        ^   Type error: expected cell state but got integer"),
+        ^   [E0020] Type error: expected cell state but got integer"),
         "
         @transition {
             set s = #2
@@ -63,7 +198,7 @@ set s = 3
     assert_output(
         Err("Error at line 4; column 21
 set s = #2
-        ^^   Type error: expected integer but got cell state"),
+        ^^   [E0020] Type error: expected integer but got cell state"),
         "
         @transition {
             set s = 3
@@ -71,14 +206,284 @@ set s = #2
         }",
     );
 
-    // Return an integer
+    // Return a boolean
     assert_output(
         Err("Error at line 3; column 13
-become 0
-^^^^^^^^   Type error: expected cell state but got integer"),
+become true
+^^^^^^^^^^^   [E0020] Type error: expected cell state but got boolean"),
         "
         @transition {
-            become 0
+            become true
         }",
     )
 }
+
+#[test]
+fn test_compound_assignment() {
+    assert_output(
+        Ok(ConstValue::CellState(4)),
+        "
+        @transition {
+            set x = 7
+            set x *= 6
+            set x /= 4
+            set x %= 6
+            become #x
+        }
+        @states 5",
+    );
+
+    // Multiplication overflow
+    let source_code = "
+        @function int test(int x, int y) {
+            set x *= y
+            return x
+        }";
+    assert_func_output(
+        &[ConstValue::Int(LangInt::MAX), ConstValue::Int(2)],
+        Err("Error at line 3; column 13
+set x *= y
+^^^^^^^^^^   [E1001] Integer overflow"),
+        source_code,
+        Some("test"),
+    );
+
+    // Divide by zero
+    let source_code = "
+        @function int test(int x, int y) {
+            set x /= y
+            return x
+        }";
+    assert_func_output(
+        &[ConstValue::Int(10), ConstValue::Int(3)],
+        Ok(ConstValue::Int(3)),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(10), ConstValue::Int(0)],
+        Err("Error at line 3; column 13
+set x /= y
+^^^^^^^^^^   [E1002] Divide by zero"),
+        source_code,
+        Some("test"),
+    );
+
+    // Remainder
+    let source_code = "
+        @function int test(int x, int y) {
+            set x %= y
+            return x
+        }";
+    assert_func_output(
+        &[ConstValue::Int(-10), ConstValue::Int(3)],
+        Ok(ConstValue::Int(-1)),
+        source_code,
+        Some("test"),
+    );
+    assert_func_output(
+        &[ConstValue::Int(10), ConstValue::Int(0)],
+        Err("Error at line 3; column 13
+set x %= y
+^^^^^^^^^^   [E1002] Divide by zero"),
+        source_code,
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_debug_vars() {
+    let source_code = "
+        @transition {
+            set x = 3
+            set y = x * 2
+            set z = y - x
+            become #(z)
+        }
+        @states 4";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+    let mut compiler = Compiler::new()
+        .expect("Failed to create compiler")
+        .with_debug_mode(true);
+    let mut transition_function = rule
+        .compile_transition_function(&mut compiler)
+        .expect("Failed to compile transition function");
+    let ret = transition_function
+        .call()
+        .expect("Transition function failed");
+    assert_eq!(ConstValue::CellState(3), ret);
+
+    let mut vars = transition_function.vars();
+    vars.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+    assert_eq!(
+        vec![
+            ("x".to_owned(), ConstValue::Int(3)),
+            ("y".to_owned(), ConstValue::Int(6)),
+            ("z".to_owned(), ConstValue::Int(3)),
+        ],
+        vars,
+    );
+}
+
+#[test]
+fn test_debug() {
+    // Debugging a value does not affect the result.
+    assert_func_output(
+        &[ConstValue::Int(4)],
+        Ok(ConstValue::Int(5)),
+        "
+        @function int test(int x) {
+            debug x
+            debug [x, 1] + 2
+            set y = x + 1
+            debug y
+            return y
+        }",
+        Some("test"),
+    );
+
+    // Errors in the debugged expression are still reported.
+    assert_func_output(
+        &[ConstValue::Int(0)],
+        Err("Error at line 1; column 35
+@function int test(int x) { debug 1 / x return x }
+                                  ^^^^^   [E1002] Divide by zero"),
+        "@function int test(int x) { debug 1 / x return x }",
+        Some("test"),
+    );
+}
+
+#[test]
+fn test_debug_values() {
+    let source_code = "
+        @transition {
+            set total = 0
+            for i in 1..4 {
+                set total += i
+                debug total
+            }
+            debug total > 5
+            debug [total, 1] * 2
+            debug #(total)
+            become #(total)
+        }
+        @states 7";
+    let rule = ast::make_rule(Rc::new(source_code.to_owned())).expect("Failed to parse rule");
+
+    // Values are recorded in debug mode.
+    let mut compiler = Compiler::new()
+        .expect("Failed to create compiler")
+        .with_debug_mode(true);
+    let mut transition_function = rule
+        .compile_transition_function(&mut compiler)
+        .expect("Failed to compile transition function");
+    assert_eq!(Vec::<ConstValue>::new(), transition_function.debug_values());
+    for _ in 0..2 {
+        let ret = transition_function
+            .call()
+            .expect("Transition function failed");
+        assert_eq!(ConstValue::CellState(6), ret);
+        // The trace starts over on each call.
+        assert_eq!(
+            vec![
+                ConstValue::Int(1),
+                ConstValue::Int(3),
+                ConstValue::Int(6),
+                ConstValue::Bool(true),
+                ConstValue::Vector(vec![12, 2]),
+                ConstValue::CellState(6),
+            ],
+            transition_function.debug_values(),
+        );
+    }
+
+    // Values are not recorded otherwise.
+    let mut compiler = Compiler::new().expect("Failed to create compiler");
+    let mut transition_function = rule
+        .compile_transition_function(&mut compiler)
+        .expect("Failed to compile transition function");
+    transition_function
+        .call()
+        .expect("Transition function failed");
+    assert_eq!(Vec::<ConstValue>::new(), transition_function.debug_values());
+}
+
+#[test]
+fn test_constants() {
+    // Constant used in a condition
+    let source_code = "
+        const BIRTH = 3
+        const SURVIVE_MIN = BIRTH - 1
+        @function int test(int n) {
+            if n == BIRTH {
+                return 1
+            } else if n >= SURVIVE_MIN and n <= BIRTH {
+                return 2
+            }
+            return 0
+        }";
+    for &(n, expected) in &[(3, 1), (2, 2), (1, 0), (4, 0)] {
+        assert_func_output(
+            &[ConstValue::Int(n)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Constant used in a directive
+    assert_output(
+        Ok(ConstValue::CellState(4)),
+        "
+        const N = 5
+        @states N
+        @transition {
+            become #(N - 1)
+        }",
+    );
+
+    // Redefined constant
+    assert_output(
+        Err("Error at line 3; column 15
+const N = 4
+      ^   [E0037] There is already a constant with this name"),
+        "
+        const N = 3
+        const N = 4
+        @transition { remain }",
+    );
+
+    // Forward reference
+    assert_output(
+        Err("Error at line 2; column 19
+const A = B + 1
+          ^   [E0038] This constant is used before it is defined"),
+        "
+        const A = B + 1
+        const B = 2
+        @transition { remain }",
+    );
+
+    // Assignment to a constant
+    assert_output(
+        Err("Error at line 4; column 17
+set N = 4
+    ^   [E0039] Cannot assign to a constant"),
+        "
+        const N = 3
+        @transition {
+            set N = 4
+            remain
+        }",
+    );
+
+    // Non-integer constant
+    assert_output(
+        Err("Error at line 2; column 19
+const A = #1
+          ^^   [E0020] Type error: expected integer but got cell state"),
+        "
+        const A = #1
+        @transition { remain }",
+    );
+}
@@ -1,18 +1,21 @@
-use super::{assert_output, ConstValue};
+use super::{assert_func_output, assert_output, ConstValue, LangInt};
 
 #[test]
 fn test_vector_types() {
     assert_output(
-        Err("TODO"),
+        Ok(ConstValue::CellState(0)),
         "
         @transition {
             set x = [1, 2, 3]
             set y = [4, 5, 6]
             set x = y
+            remain
         }",
     );
     assert_output(
-        Err("TODO"),
+        Err("Error at line 5; column 21
+set x = y
+        ^   [E0020] Type error: expected vector of length 3 but got vector of length 1"),
         "
         @transition {
             set x = [1, 2, 3]
@@ -21,13 +24,99 @@ fn test_vector_types() {
         }",
     );
     assert_output(
-        Err("TODO"),
+        Err("Error at line 4; column 21
+set x = [1, 2, 3, 4]
+        ^^^^^^^^^^^^   [E0020] Type error: expected vector of length 3 but got vector of length 4"),
         "
         @transition {
             set x = [1, 2, 3]
             set x = [1, 2, 3, 4]
         }",
     );
+    assert_output(
+        Err("Error at line 3; column 21
+set x = []
+        ^^   [E0019] Vector length must range from 1 to 256"),
+        "
+        @transition {
+            set x = []
+        }",
+    );
+}
+
+#[test]
+fn test_vector_index() {
+    let source_code = "
+        @function int test(int i) {
+            set v = [1, 10, 100]
+            return v[i] + [2, 3][1]
+        }";
+    for &(i, expected) in &[(0, 4), (1, 13), (2, 103)] {
+        assert_func_output(
+            &[ConstValue::Int(i)],
+            Ok(ConstValue::Int(expected)),
+            source_code,
+            Some("test"),
+        );
+    }
+    for &i in &[3, -1, LangInt::MIN] {
+        assert_func_output(
+            &[ConstValue::Int(i)],
+            Err("Error at line 4; column 20
+return v[i] + [2, 3][1]
+       ^^^^   [E1006] Vector index out of range"),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Compile-time evaluation
+    assert_output(
+        Ok(ConstValue::CellState(4)),
+        "
+        @transition {
+            become #4
+        }
+        @states [2, 5, 9][1]",
+    );
+}
+
+#[test]
+fn test_vector_arithmetic() {
+    let source_code = "
+        @function vec3 test(int k) {
+            set v = [1, 10, 100]
+            set v += [2, 2, 2]
+            return v - [1, 2] + k * [1, 1, 1] * 2
+        }";
+    for &(k, expected) in &[(0, [2, 10, 102]), (5, [12, 20, 112]), (-1, [0, 8, 100])] {
+        assert_func_output(
+            &[ConstValue::Int(k)],
+            Ok(ConstValue::Vector(expected.to_vec())),
+            source_code,
+            Some("test"),
+        );
+    }
+
+    // Overflow in any component
+    assert_func_output(
+        &[ConstValue::Int(LangInt::MAX)],
+        Err("Error at line 1; column 37
+@function vec2 test(int k) { return [1, 2] * k }
+                                    ^^^^^^^^^^   [E1001] Integer overflow"),
+        "@function vec2 test(int k) { return [1, 2] * k }",
+        Some("test"),
+    );
+
+    // Vectors cannot be multiplied by each other
+    assert_func_output(
+        &[ConstValue::Int(1)],
+        Err("Error at line 1; column 37
+@function vec2 test(int k) { return [1, 2] * [k, k] }
+                                    ^^^^^^^^^^^^^^^   [E0022] Invalid arguments [Vector(2), Vector(2)] for binary \"*\" operator"),
+        "@function vec2 test(int k) { return [1, 2] * [k, k] }",
+        Some("test"),
+    );
 }
 
 #[test]
@@ -24,6 +24,8 @@ pub const MAX_VECTOR_LEN: usize = 256;
 pub enum Type {
     /// Integer.
     Int,
+    /// Boolean.
+    Bool,
     /// Cell state.
     CellState,
     /// Vector of a specific length (from 1 to 256).
@@ -37,18 +39,28 @@ impl Default for Type {
 impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Int => write!(f, "integer"),
-            Self::CellState => write!(f, "cell state"),
-            Self::Vector(len) => write!(f, "vector of length {}", len),
+            Self::Vector(len) => write!(f, "{} of length {}", self.name(), len),
+            _ => write!(f, "{}", self.name()),
         }
     }
 }
 impl Type {
+    /// Returns a short human-readable name for this type, such as "integer"
+    /// or "vector". Unlike the Display implementation, this omits the length
+    /// of vectors.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Int => "integer",
+            Self::Bool => "boolean",
+            Self::CellState => "cell state",
+            Self::Vector(_) => "vector",
+        }
+    }
     /// Returns true if this type has a representation in compiled code or false
     /// otherwise; i.e. whether a variable can contain a value of this type.
     pub fn has_runtime_representation(self) -> bool {
         match self {
-            Self::Int | Self::CellState | Self::Vector(_) => true,
+            Self::Int | Self::Bool | Self::CellState | Self::Vector(_) => true,
         }
     }
     /// Returns the number of bytes used to represent this type in compiled
@@ -57,6 +69,7 @@ impl Type {
         // TODO: test this method along with Value::from_bytes() and to_bytes()
         match self {
             Self::Int => Some(std::mem::size_of::<LangInt>()),
+            Self::Bool => Some(std::mem::size_of::<bool>()),
             Self::CellState => Some(std::mem::size_of::<LangCellState>()),
             Self::Vector(len) => Some(len as usize * Self::Int.size_of().unwrap()),
         }